@@ -1,10 +1,15 @@
-use liana::config::{config_folder_path, Config};
+use liana::{
+    config::{config_folder_path, Config},
+    miniscript::bitcoin::{self, consensus, util::psbt::PartiallySignedTransaction as Psbt},
+};
 
 use std::{
-    env,
+    collections::BTreeMap,
+    env, fs,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
+    str::FromStr,
 };
 
 use serde_json::Value as Json;
@@ -15,6 +20,9 @@ use std::os::unix::net::UnixStream;
 fn show_usage() {
     eprintln!("Usage:");
     eprintln!(" liana-cli [--conf conf_path] [--raw] <command> [<param 1> <param 2> ...]");
+    eprintln!(
+        " liana-cli [--conf conf_path] [--raw] batchspend <csv_path> <feerate> [<outpoints>] [--import-labels]"
+    );
     process::exit(1);
 }
 
@@ -66,9 +74,9 @@ fn from_str_hack(token: String) -> Json {
     }
 }
 
-fn rpc_request(method: String, params: Vec<String>) -> Json {
+fn rpc_request(method: String, params: Vec<Json>) -> Json {
     let method = Json::String(method);
-    let params = Json::Array(params.into_iter().map(from_str_hack).collect::<Vec<Json>>());
+    let params = Json::Array(params);
     let mut object = serde_json::Map::<String, Json>::new();
     object.insert("jsonrpc".to_string(), Json::String("2.0".to_string()));
     object.insert(
@@ -81,13 +89,17 @@ fn rpc_request(method: String, params: Vec<String>) -> Json {
     Json::Object(object)
 }
 
-fn socket_file(conf_file: Option<PathBuf>) -> PathBuf {
-    let config = Config::from_file(conf_file).unwrap_or_else(|e| {
+fn config(conf_file: Option<PathBuf>) -> Config {
+    Config::from_file(conf_file).unwrap_or_else(|e| {
         eprintln!("Error getting config: {}", e);
         process::exit(1);
-    });
+    })
+}
+
+fn socket_file(config: &Config) -> PathBuf {
     let data_dir = config
         .data_dir
+        .clone()
         .unwrap_or_else(|| config_folder_path().unwrap());
     let data_dir = data_dir.to_str().expect("Datadir is valid unicode");
 
@@ -115,21 +127,18 @@ fn trimmed(mut vec: Vec<u8>, bytes_read: usize) -> Vec<u8> {
     vec
 }
 
-fn main() {
-    let args = env::args().collect();
-    let (conf_file, raw, method, params) = parse_args(args);
-    let request = rpc_request(method, params);
-    let socket_file = socket_file(conf_file);
+// Send a request to lianad and wait for its response.
+fn send_request(socket_file: &Path, request: &Json) -> Json {
     let mut raw_response = vec![0; 256];
 
-    let mut socket = UnixStream::connect(&socket_file).unwrap_or_else(|e| {
+    let mut socket = UnixStream::connect(socket_file).unwrap_or_else(|e| {
         eprintln!("Could not connect to {:?}: '{}'", socket_file, e);
         process::exit(1);
     });
     socket
         .write_all(&[request.to_string().as_bytes(), b"\n"].concat())
         .unwrap_or_else(|e| {
-            eprintln!("Writing to {:?}: '{}'", &socket_file, e);
+            eprintln!("Writing to {:?}: '{}'", socket_file, e);
             process::exit(1);
         });
 
@@ -138,7 +147,7 @@ fn main() {
         let n = socket
             .read(&mut raw_response[total_read..])
             .unwrap_or_else(|e| {
-                eprintln!("Reading from {:?}: '{}'", &socket_file, e);
+                eprintln!("Reading from {:?}: '{}'", socket_file, e);
                 process::exit(1);
             });
         total_read += n;
@@ -152,23 +161,270 @@ fn main() {
         match serde_json::from_slice::<Json>(&raw_response) {
             Ok(response) => {
                 if response.get("id") == request.get("id") {
-                    if raw {
-                        print!("{}", response);
-                    } else if let Some(r) = response.get("result") {
-                        println!("{:#}", serde_json::json!({ "result": r }));
-                    } else if let Some(e) = response.get("error") {
-                        println!("{:#}", serde_json::json!({ "error": e }));
-                    } else {
-                        log::warn!(
-                            "lianad response doesn't contain result or error: '{}'",
-                            response
-                        );
-                        println!("{:#}", response);
-                    }
-                    return;
+                    return response;
                 }
             }
             Err(_) => continue,
         }
     }
 }
+
+fn print_response(response: &Json, raw: bool) {
+    if raw {
+        print!("{}", response);
+    } else if let Some(r) = response.get("result") {
+        println!("{:#}", serde_json::json!({ "result": r }));
+    } else if let Some(e) = response.get("error") {
+        println!("{:#}", serde_json::json!({ "error": e }));
+    } else {
+        log::warn!(
+            "lianad response doesn't contain result or error: '{}'",
+            response
+        );
+        println!("{:#}", response);
+    }
+}
+
+// A payment read from a batch CSV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Payment {
+    address: bitcoin::Address,
+    amount: u64,
+    label: Option<String>,
+}
+
+// Parse the payments from CSV lines of the form `address,amount[,label]`, with the amount in sats.
+// Empty lines and a header line are ignored. All addresses must be valid for this network.
+fn parse_payments(csv: &str, network: bitcoin::Network) -> Result<Vec<Payment>, String> {
+    let mut payments = Vec::new();
+
+    for (i, line) in csv.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, ',').map(str::trim);
+        let (address, amount) = match (fields.next(), fields.next()) {
+            (Some(address), Some(amount)) => (address, amount),
+            _ => {
+                return Err(format!(
+                    "Line {}: expected 'address,amount[,label]'.",
+                    line_number
+                ))
+            }
+        };
+        if line_number == 1 && address.eq_ignore_ascii_case("address") {
+            continue;
+        }
+
+        let address = bitcoin::Address::from_str(address).map_err(|e| {
+            format!(
+                "Line {}: invalid address '{}': {}.",
+                line_number, address, e
+            )
+        })?;
+        // NOTE: signet uses testnet addresses
+        if address.network != network
+            && !(address.network == bitcoin::Network::Testnet
+                && network == bitcoin::Network::Signet)
+        {
+            return Err(format!(
+                "Line {}: address '{}' is for '{}' but our network is '{}'.",
+                line_number, address, address.network, network
+            ));
+        }
+        let amount = amount.parse::<u64>().map_err(|_| {
+            format!(
+                "Line {}: invalid amount '{}', expected a number of sats.",
+                line_number, amount
+            )
+        })?;
+        let label = fields
+            .next()
+            .map(|label| label.trim_matches('"').to_string())
+            .filter(|label| !label.is_empty());
+
+        payments.push(Payment {
+            address,
+            amount,
+            label,
+        });
+    }
+
+    if payments.is_empty() {
+        return Err("No payment in the CSV file.".to_string());
+    }
+    Ok(payments)
+}
+
+// Create a Spend transaction paying all the payments of a CSV file, and print a summary of it.
+// Optionally label the addresses paid with the labels from the file.
+fn batch_spend(socket_file: &Path, network: bitcoin::Network, mut params: Vec<String>, raw: bool) {
+    let import_labels = params.iter().any(|p| p == "--import-labels");
+    params.retain(|p| p != "--import-labels");
+    if params.len() < 2 || params.len() > 3 {
+        eprintln!("Invalid parameters for batchspend.");
+        show_usage();
+    }
+
+    let csv = fs::read_to_string(&params[0]).unwrap_or_else(|e| {
+        eprintln!("Error reading '{}': {}", params[0], e);
+        process::exit(1);
+    });
+    let payments = parse_payments(&csv, network).unwrap_or_else(|e| {
+        eprintln!("Error parsing '{}': {}", params[0], e);
+        process::exit(1);
+    });
+    let feerate = params[1].parse::<u64>().unwrap_or_else(|_| {
+        eprintln!("Invalid feerate '{}'.", params[1]);
+        process::exit(1);
+    });
+    // Let lianad select the coins if none is given.
+    let outpoints = params
+        .get(2)
+        .map(|outpoints| from_str_hack(outpoints.clone()))
+        .unwrap_or_else(|| Json::Array(Vec::new()));
+
+    // Pass the payments as a list so the same address may be paid more than once.
+    let destinations: Vec<Json> = payments
+        .iter()
+        .map(|p| serde_json::json!({ "address": p.address.to_string(), "amount": p.amount }))
+        .collect();
+    let request = rpc_request(
+        "createspend".to_string(),
+        vec![Json::Array(destinations), outpoints, feerate.into()],
+    );
+    let response = send_request(socket_file, &request);
+    let psbt_str = match response.get("result").and_then(|r| r.get("psbt")) {
+        Some(psbt) => psbt.as_str().expect("lianad returns PSBTs as strings"),
+        None => {
+            print_response(&response, raw);
+            process::exit(1);
+        }
+    };
+    let psbt: Psbt = base64::decode(psbt_str)
+        .ok()
+        .and_then(|bytes| consensus::deserialize(&bytes).ok())
+        .expect("lianad returns valid PSBTs");
+
+    let amount: u64 = payments.iter().map(|p| p.amount).sum();
+    let in_value: u64 = psbt
+        .inputs
+        .iter()
+        .filter_map(|psbt_in| psbt_in.witness_utxo.as_ref().map(|txo| txo.value))
+        .sum();
+    let out_value: u64 = psbt.unsigned_tx.output.iter().map(|txo| txo.value).sum();
+    let mut recipients: Vec<_> = payments.iter().map(|p| &p.address).collect();
+    recipients.sort_by_key(|addr| addr.to_string());
+    recipients.dedup();
+
+    // An address paid more than once gets all its distinct labels.
+    let mut labels: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for payment in &payments {
+        if let Some(label) = &payment.label {
+            let addr_labels = labels.entry(payment.address.to_string()).or_default();
+            if !addr_labels.contains(label) {
+                addr_labels.push(label.clone());
+            }
+        }
+    }
+    let labels_imported = if import_labels && !labels.is_empty() {
+        let labels: serde_json::Map<String, Json> = labels
+            .into_iter()
+            .map(|(addr, labels)| (addr, Json::String(labels.join(", "))))
+            .collect();
+        let count = labels.len();
+        let request = rpc_request("updatelabels".to_string(), vec![Json::Object(labels)]);
+        let response = send_request(socket_file, &request);
+        if response.get("result").is_none() {
+            print_response(&response, raw);
+            process::exit(1);
+        }
+        count
+    } else {
+        0
+    };
+
+    let summary = serde_json::json!({
+        "result": {
+            "psbt": psbt_str,
+            "payments": payments.len(),
+            "recipients": recipients.len(),
+            "amount": amount,
+            "fee": in_value.saturating_sub(out_value),
+            "coins": psbt.unsigned_tx.input.len(),
+            "labels_imported": labels_imported,
+        }
+    });
+    if raw {
+        print!("{}", summary);
+    } else {
+        println!("{:#}", summary);
+    }
+}
+
+fn main() {
+    let args = env::args().collect();
+    let (conf_file, raw, method, params) = parse_args(args);
+    let config = config(conf_file);
+    let socket_file = socket_file(&config);
+
+    // Batch payments are built by the client before being passed to lianad.
+    if method == "batchspend" {
+        batch_spend(&socket_file, config.bitcoin_config.network, params, raw);
+        return;
+    }
+
+    let params = params.into_iter().map(from_str_hack).collect();
+    let request = rpc_request(method, params);
+    let response = send_request(&socket_file, &request);
+    print_response(&response, raw);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_csv() {
+        let csv = "address,amount,label
+bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv,10000,Alice
+bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l, 20000 ,\"Bob, March\"
+
+bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv,30000
+";
+        let payments = parse_payments(csv, bitcoin::Network::Bitcoin).unwrap();
+        assert_eq!(payments.len(), 3);
+        assert_eq!(payments[0].address, payments[2].address);
+        assert_eq!(
+            payments.iter().map(|p| p.amount).collect::<Vec<_>>(),
+            vec![10_000, 20_000, 30_000]
+        );
+        assert_eq!(
+            payments
+                .iter()
+                .map(|p| p.label.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("Alice"), Some("Bob, March"), None]
+        );
+
+        // Addresses must be for our network, and amounts in sats.
+        assert!(parse_payments(csv, bitcoin::Network::Testnet)
+            .unwrap_err()
+            .starts_with("Line 2:"));
+        assert!(parse_payments(
+            "bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv,0.1",
+            bitcoin::Network::Bitcoin
+        )
+        .unwrap_err()
+        .contains("invalid amount"));
+        assert!(parse_payments(
+            "bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv",
+            bitcoin::Network::Bitcoin
+        )
+        .is_err());
+        assert!(parse_payments("address,amount\n", bitcoin::Network::Bitcoin).is_err());
+    }
+}
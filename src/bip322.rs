@@ -0,0 +1,200 @@
+//! Signing and verifying messages with the BIP322 generic signed message format.
+//!
+//! A message is signed for an address by signing a virtual "to_sign" transaction, spending the
+//! output of a virtual "to_spend" transaction which commits to the message and pays to this
+//! address. We only implement the "simple" variant: the signature is the witness of the "to_sign"
+//! transaction's input.
+
+use std::{error, fmt};
+
+use miniscript::{
+    bitcoin::{
+        self, blockdata::opcodes, blockdata::script, consensus, hashes::sha256, hashes::Hash,
+        hashes::HashEngine, secp256k1, util::sighash,
+    },
+    interpreter::Interpreter,
+};
+
+// The tag of the hash of a signed message.
+const MESSAGE_TAG: &[u8] = b"BIP0322-signed-message";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bip322Error {
+    /// The signature isn't a base64-encoded witness stack.
+    MalformedSignature,
+    /// The signature doesn't satisfy the Script of the address for this message.
+    InvalidSignature(String),
+}
+
+impl fmt::Display for Bip322Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MalformedSignature => write!(f, "Signature is not a base64-encoded witness."),
+            Self::InvalidSignature(e) => write!(f, "Invalid signature: '{}'.", e),
+        }
+    }
+}
+
+impl error::Error for Bip322Error {}
+
+/// The tagged hash of a message, which the "to_spend" transaction commits to.
+pub fn message_hash(message: &[u8]) -> sha256::Hash {
+    let tag_hash = sha256::Hash::hash(MESSAGE_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine.input(message);
+    sha256::Hash::from_engine(engine)
+}
+
+/// The virtual transaction committing to the message, whose single output pays to the Script of
+/// the address the message is signed for.
+pub fn to_spend(script_pubkey: &bitcoin::Script, message: &[u8]) -> bitcoin::Transaction {
+    let script_sig = script::Builder::new()
+        .push_int(0)
+        .push_slice(&message_hash(message)[..])
+        .into_script();
+    bitcoin::Transaction {
+        version: 0,
+        lock_time: bitcoin::PackedLockTime::ZERO,
+        input: vec![bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), 0xFF_FF_FF_FF),
+            script_sig,
+            sequence: bitcoin::Sequence::ZERO,
+            witness: bitcoin::Witness::new(),
+        }],
+        output: vec![bitcoin::TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+/// The virtual transaction spending the output of the "to_spend" transaction. The signature of a
+/// message is the witness of its input. It is returned unsigned.
+pub fn to_sign(to_spend: &bitcoin::Transaction) -> bitcoin::Transaction {
+    bitcoin::Transaction {
+        version: 0,
+        lock_time: bitcoin::PackedLockTime::ZERO,
+        input: vec![bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::new(to_spend.txid(), 0),
+            script_sig: bitcoin::Script::new(),
+            sequence: bitcoin::Sequence::ZERO,
+            witness: bitcoin::Witness::new(),
+        }],
+        output: vec![bitcoin::TxOut {
+            value: 0,
+            script_pubkey: script::Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    }
+}
+
+/// Encode the witness of the "to_sign" transaction's input as a signature.
+pub fn encode_signature(witness: &bitcoin::Witness) -> String {
+    base64::encode(consensus::serialize(witness))
+}
+
+/// Check a signature of this message for this address.
+pub fn verify(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    address: &bitcoin::Address,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), Bip322Error> {
+    let witness: bitcoin::Witness = base64::decode(signature)
+        .ok()
+        .and_then(|sig| consensus::deserialize(&sig).ok())
+        .ok_or(Bip322Error::MalformedSignature)?;
+
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let mut to_sign = to_sign(&to_spend);
+    to_sign.input[0].witness = witness;
+    let txin = &to_sign.input[0];
+    let interpreter = Interpreter::from_txdata(
+        &to_spend.output[0].script_pubkey,
+        &txin.script_sig,
+        &txin.witness,
+        txin.sequence,
+        to_sign.lock_time.into(),
+    )
+    .map_err(|e| Bip322Error::InvalidSignature(e.to_string()))?;
+
+    // Going through all the constraints checks the signatures against the "to_sign" transaction.
+    let prevouts = sighash::Prevouts::All(&to_spend.output);
+    for constraint in interpreter.iter(secp, &to_sign, 0, &prevouts) {
+        constraint.map_err(|e| Bip322Error::InvalidSignature(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // The test vectors from BIP322.
+    #[test]
+    fn bip322_vectors() {
+        assert_eq!(
+            message_hash(b"").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash(b"Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let addr =
+            bitcoin::Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l").unwrap();
+        let spend_tx = to_spend(&addr.script_pubkey(), b"");
+        assert_eq!(
+            spend_tx.txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            to_sign(&spend_tx).txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+        let spend_tx = to_spend(&addr.script_pubkey(), b"Hello World");
+        assert_eq!(
+            spend_tx.txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            to_sign(&spend_tx).txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+
+        let secp = secp256k1::Secp256k1::verification_only();
+        let sig_empty = "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        let sig_hello = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        verify(&secp, &addr, b"", sig_empty).unwrap();
+        verify(&secp, &addr, b"Hello World", sig_hello).unwrap();
+
+        // The signature of one message isn't valid for another.
+        assert!(matches!(
+            verify(&secp, &addr, b"Hello World", sig_empty),
+            Err(Bip322Error::InvalidSignature(..))
+        ));
+        // Nor for another address.
+        let other_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        assert!(matches!(
+            verify(&secp, &other_addr, b"Hello World", sig_hello),
+            Err(Bip322Error::InvalidSignature(..))
+        ));
+        // The signature must be an encoded witness.
+        assert_eq!(
+            verify(&secp, &addr, b"Hello World", "not base64"),
+            Err(Bip322Error::MalformedSignature)
+        );
+
+        // A witness roundtrips through the encoding.
+        let witness: bitcoin::Witness =
+            consensus::deserialize(&base64::decode(sig_hello).unwrap()).unwrap();
+        assert_eq!(encode_signature(&witness), sig_hello);
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     config,
     descriptors::MultipathDescriptor,
 };
-use utils::{block_before_date, roundup_progress};
+use utils::{block_before_date, btc_per_kvb_to_sat_per_vb, roundup_progress};
 
 use std::{
     cmp, collections::HashSet, convert::TryInto, fs, io, str::FromStr, thread, time::Duration,
@@ -846,6 +846,70 @@ impl BitcoinD {
         }
     }
 
+    /// Get the mempool entry for this transaction, if it's in our mempool.
+    pub fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
+        // TODO: Maybe assert we got a -5 error, and not any other kind of error?
+        self.make_fallible_node_request("getmempoolentry", &params!(Json::String(txid.to_string())))
+            .ok()
+            .map(|res| res.into())
+    }
+
+    /// Get the feerate estimated by bitcoind for a confirmation within this number of blocks, in
+    /// sats/vb. None if bitcoind doesn't have enough data to estimate it.
+    pub fn estimate_smart_fee(&self, conf_target: u16) -> Option<u64> {
+        self.make_fallible_node_request(
+            "estimatesmartfee",
+            &params!(Json::Number(conf_target.into())),
+        )
+        .ok()?
+        .get("feerate")
+        .and_then(Json::as_f64)
+        .and_then(btc_per_kvb_to_sat_per_vb)
+    }
+
+    /// Get the output at this outpoint if it's in the UTxO set, without taking the mempool into
+    /// account.
+    pub fn get_tx_out(&self, outpoint: &bitcoin::OutPoint) -> Option<bitcoin::TxOut> {
+        let res = self.make_node_request(
+            "gettxout",
+            &params!(
+                Json::String(outpoint.txid.to_string()),
+                Json::Number(outpoint.vout.into()),
+                Json::Bool(false), // Whether to include the mempool
+            ),
+        );
+        if res.is_null() {
+            return None;
+        }
+
+        let value = res
+            .get("value")
+            .and_then(Json::as_f64)
+            .and_then(|a| bitcoin::Amount::from_btc(a).ok())
+            .expect("Invalid value in 'gettxout' response")
+            .to_sat();
+        let script_pubkey = res
+            .get("scriptPubKey")
+            .and_then(|spk| spk.get("hex"))
+            .and_then(Json::as_str)
+            .and_then(|s| Vec::from_hex(s).ok())
+            .map(bitcoin::Script::from)
+            .expect("Invalid scriptPubKey in 'gettxout' response");
+        Some(bitcoin::TxOut {
+            value,
+            script_pubkey,
+        })
+    }
+
+    /// Get the minimum feerate for a transaction to be accepted in bitcoind's mempool, in sats/vb.
+    pub fn mempool_min_feerate(&self) -> u64 {
+        self.make_node_request("getmempoolinfo", &[])
+            .get("mempoolminfee")
+            .and_then(Json::as_f64)
+            .and_then(btc_per_kvb_to_sat_per_vb)
+            .expect("Missing or invalid 'mempoolminfee' in 'getmempoolinfo' result?")
+    }
+
     pub fn broadcast_tx(&self, tx: &bitcoin::Transaction) -> Result<(), BitcoindError> {
         self.make_fallible_node_request(
             "sendrawtransaction",
@@ -1120,3 +1184,55 @@ pub struct BlockStats {
     pub time: u32,
     pub median_time_past: u32,
 }
+
+/// Information about an unconfirmed transaction in the mempool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MempoolEntry {
+    pub vsize: u64,
+    pub fees: bitcoin::Amount,
+    /// The number of unconfirmed transactions in the mempool this one depends on, including
+    /// itself.
+    pub ancestor_count: u64,
+    /// The size of this transaction along with all its unconfirmed ancestors.
+    pub ancestor_vsize: u64,
+    /// The fees paid by this transaction along with all its unconfirmed ancestors.
+    pub ancestor_fees: bitcoin::Amount,
+}
+
+impl From<Json> for MempoolEntry {
+    fn from(json: Json) -> MempoolEntry {
+        let vsize = json
+            .get("vsize")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
+        let fees = json
+            .get("fees")
+            .and_then(|fees| fees.get("base"))
+            .and_then(Json::as_f64)
+            .and_then(|a| bitcoin::Amount::from_btc(a).ok())
+            .expect("Must be present in bitcoind response");
+
+        let ancestor_count = json
+            .get("ancestorcount")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
+        let ancestor_vsize = json
+            .get("ancestorsize")
+            .and_then(Json::as_u64)
+            .expect("Must be present in bitcoind response");
+        let ancestor_fees = json
+            .get("fees")
+            .and_then(|fees| fees.get("ancestor"))
+            .and_then(Json::as_f64)
+            .and_then(|a| bitcoin::Amount::from_btc(a).ok())
+            .expect("Must be present in bitcoind response");
+
+        MempoolEntry {
+            vsize,
+            fees,
+            ancestor_count,
+            ancestor_vsize,
+            ancestor_fees,
+        }
+    }
+}
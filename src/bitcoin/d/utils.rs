@@ -18,6 +18,13 @@ pub fn roundup_progress(progress: f64) -> f64 {
     }
 }
 
+/// Convert a feerate in BTC/kvB, as returned by bitcoind, to sats/vb. Rounds up since we use it to
+/// get a feerate to target.
+pub fn btc_per_kvb_to_sat_per_vb(feerate: f64) -> Option<u64> {
+    let sat_per_kvb = bitcoin::Amount::from_btc(feerate).ok()?.to_sat();
+    sat_per_kvb.checked_add(999).map(|s| s / 1_000)
+}
+
 // As a standalone function to unit test it.
 /// Get the last block of the chain before the given date by performing a binary search.
 pub fn block_before_date<Fh, Fs>(
@@ -374,4 +381,13 @@ mod tests {
         assert_eq!(roundup_progress(0.9998), 1.0);
         assert_eq!(roundup_progress(0.9991), 1.0);
     }
+
+    #[test]
+    fn bitcoind_feerate_conversion() {
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.00001), Some(1));
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.00001001), Some(2));
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.00021), Some(21));
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.0), Some(0));
+        assert_eq!(btc_per_kvb_to_sat_per_vb(-0.0001), None);
+    }
 }
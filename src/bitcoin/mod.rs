@@ -5,11 +5,11 @@ pub mod d;
 pub mod poller;
 
 use crate::{
-    bitcoin::d::{BitcoindError, LSBlockEntry},
+    bitcoin::d::{BitcoindError, LSBlockEntry, MempoolEntry},
     descriptors,
 };
 
-use std::{collections::HashMap, fmt, sync};
+use std::{cmp, collections::HashMap, fmt, sync};
 
 use miniscript::bitcoin;
 
@@ -48,6 +48,14 @@ pub trait BitcoinInterface: Send {
     /// Get the timestamp set in the best block's header.
     fn tip_time(&self) -> u32;
 
+    /// Get the median time of the 11 blocks up to and including the best block. This is the time
+    /// an absolute timelock is checked against for the next block.
+    fn tip_median_time_past(&self) -> u32;
+
+    /// Get the timestamp set in the header of the block at this height in the best chain, if
+    /// there is one.
+    fn block_time(&self, height: i32) -> Option<u32>;
+
     /// Check whether this former tip is part of the current best chain.
     fn is_in_chain(&self, tip: &BlockChainTip) -> bool;
 
@@ -102,6 +110,18 @@ pub trait BitcoinInterface: Send {
         &self,
         txid: &bitcoin::Txid,
     ) -> Option<(bitcoin::Transaction, Option<Block>)>;
+
+    /// Get the virtual size and fees of this transaction, if it's unconfirmed and in the mempool.
+    fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry>;
+
+    /// Get the feerate, in sats/vb, a transaction should pay to confirm within this number of
+    /// blocks. Never lower than the minimum feerate to enter the mempool. None if no estimate is
+    /// available for this target.
+    fn estimate_feerate(&self, conf_target: u16) -> Option<u64>;
+
+    /// Get the output at this outpoint, if it's in the UTxO set as of the current tip. Outputs
+    /// created or spent by unconfirmed transactions aren't considered.
+    fn utxo(&self, outpoint: &bitcoin::OutPoint) -> Option<bitcoin::TxOut>;
 }
 
 impl BitcoinInterface for d::BitcoinD {
@@ -310,12 +330,35 @@ impl BitcoinInterface for d::BitcoinD {
         self.get_block_stats(tip.hash).time
     }
 
+    fn tip_median_time_past(&self) -> u32 {
+        let tip = self.chain_tip();
+        self.get_block_stats(tip.hash).median_time_past
+    }
+
+    fn block_time(&self, height: i32) -> Option<u32> {
+        self.get_block_hash(height)
+            .map(|hash| self.get_block_stats(hash).time)
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
     ) -> Option<(bitcoin::Transaction, Option<Block>)> {
         self.get_transaction(txid).map(|res| (res.tx, res.block))
     }
+
+    fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
+        self.mempool_entry(txid)
+    }
+
+    fn estimate_feerate(&self, conf_target: u16) -> Option<u64> {
+        let estimate = self.estimate_smart_fee(conf_target)?;
+        Some(cmp::max(cmp::max(estimate, self.mempool_min_feerate()), 1))
+    }
+
+    fn utxo(&self, outpoint: &bitcoin::OutPoint) -> Option<bitcoin::TxOut> {
+        self.get_tx_out(outpoint)
+    }
 }
 
 // FIXME: do we need to repeat the entire trait implemenation? Isn't there a nicer way?
@@ -393,12 +436,32 @@ impl BitcoinInterface for sync::Arc<sync::Mutex<dyn BitcoinInterface + 'static>>
         self.lock().unwrap().tip_time()
     }
 
+    fn tip_median_time_past(&self) -> u32 {
+        self.lock().unwrap().tip_median_time_past()
+    }
+
+    fn block_time(&self, height: i32) -> Option<u32> {
+        self.lock().unwrap().block_time(height)
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
     ) -> Option<(bitcoin::Transaction, Option<Block>)> {
         self.lock().unwrap().wallet_transaction(txid)
     }
+
+    fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
+        self.lock().unwrap().mempool_entry(txid)
+    }
+
+    fn estimate_feerate(&self, conf_target: u16) -> Option<u64> {
+        self.lock().unwrap().estimate_feerate(conf_target)
+    }
+
+    fn utxo(&self, outpoint: &bitcoin::OutPoint) -> Option<bitcoin::TxOut> {
+        self.lock().unwrap().utxo(outpoint)
+    }
 }
 
 // FIXME: We could avoid this type (and all the conversions entailing allocations) if bitcoind
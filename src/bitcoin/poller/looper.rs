@@ -63,6 +63,7 @@ fn update_coins(
                     block_time: None,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 };
                 received.push(coin);
             }
@@ -0,0 +1,720 @@
+//! Setting up a multi-party wallet using the BIP129 (BSMS) file formats.
+//!
+//! In the first round every signer shares a key record, signed with the key it contains. In
+//! the second round the coordinator shares the descriptor record: the descriptor using all these
+//! keys along with its first receive address, which every signer checks against its own device.
+//!
+//! Only the unencrypted mode is supported. Records are exchanged in plaintext, the token is only
+//! used to bind the key records to a specific setup session.
+
+use crate::descriptors::{LianaDescError, MultipathDescriptor};
+
+use std::{error, fmt, str};
+
+use miniscript::{
+    bitcoin::{
+        self,
+        hashes::{
+            hex::{FromHex, ToHex},
+            Hash,
+        },
+        secp256k1,
+        util::misc::signed_msg_hash,
+    },
+    descriptor,
+};
+
+/// The version of the BSMS specification we implement.
+pub const BSMS_VERSION: &str = "BSMS 1.0";
+
+// The path restrictions for a descriptor record whose keys derive both receive and change
+// addresses.
+const PATH_RESTRICTIONS: &str = "/0/*,/1/*";
+const NO_PATH_RESTRICTIONS: &str = "No path restrictions";
+
+// The character sets and generator of the descriptor checksum, as per BIP380.
+const CHECKSUM_INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_GENERATOR: [u64; 5] = [
+    0xf5dee51989,
+    0xa9fdca3312,
+    0x1bab10e32d,
+    0x3706b1677a,
+    0x644d626ffd,
+];
+
+#[derive(Debug)]
+pub enum BsmsError {
+    /// The record isn't for the version we support.
+    UnsupportedVersion(String),
+    /// The record doesn't have the expected number of lines.
+    InvalidRecordLength(usize),
+    InvalidToken(String),
+    InvalidKey(String),
+    /// The description of a key record must fit on a single line.
+    InvalidDescription(String),
+    /// The key record is for another setup session.
+    TokenMismatch(Token),
+    /// The key record wasn't signed by the key it contains.
+    InvalidSignature,
+    Descriptor(LianaDescError),
+    InvalidPathRestrictions(String),
+    InvalidAddress(String),
+    /// The first address in the descriptor record isn't the one derived from its descriptor.
+    FirstAddressMismatch(bitcoin::Address),
+    /// The checksum of a descriptor using the '/**' shorthand is invalid.
+    InvalidChecksum(String),
+}
+
+impl fmt::Display for BsmsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported version '{}'. Only '{}' is supported.",
+                version, BSMS_VERSION
+            ),
+            Self::InvalidRecordLength(n_lines) => {
+                write!(f, "Invalid number of lines in record: {}.", n_lines)
+            }
+            Self::InvalidToken(token) => write!(
+                f,
+                "Invalid token '{}'. Must be '00' or the hex encoding of 8 or 16 bytes.",
+                token
+            ),
+            Self::InvalidKey(key) => write!(
+                f,
+                "Invalid key '{}'. Must be an xpub with origin and without derivation steps.",
+                key
+            ),
+            Self::InvalidDescription(desc) => write!(
+                f,
+                "Invalid key description '{}'. Must be a single line.",
+                desc
+            ),
+            Self::TokenMismatch(token) => {
+                write!(f, "Key record token '{}' isn't the session's token.", token)
+            }
+            Self::InvalidSignature => {
+                write!(f, "The key record isn't signed by the key it contains.")
+            }
+            Self::Descriptor(e) => write!(f, "Invalid descriptor: '{}'.", e),
+            Self::InvalidPathRestrictions(restrictions) => write!(
+                f,
+                "Unsupported path restrictions '{}'. Must be '{}'.",
+                restrictions, PATH_RESTRICTIONS
+            ),
+            Self::InvalidAddress(addr) => write!(f, "Invalid address '{}'.", addr),
+            Self::FirstAddressMismatch(addr) => write!(
+                f,
+                "First address '{}' doesn't match the first address of the descriptor.",
+                addr
+            ),
+            Self::InvalidChecksum(desc) => {
+                write!(f, "Invalid checksum for descriptor '{}'.", desc)
+            }
+        }
+    }
+}
+
+impl error::Error for BsmsError {}
+
+impl From<LianaDescError> for BsmsError {
+    fn from(e: LianaDescError) -> Self {
+        Self::Descriptor(e)
+    }
+}
+
+// Get the lines of a record, checking its version and length.
+fn record_lines(s: &str, n_lines: usize) -> Result<Vec<&str>, BsmsError> {
+    let lines: Vec<&str> = s.trim().lines().map(|l| l.trim()).collect();
+    if lines.len() != n_lines {
+        return Err(BsmsError::InvalidRecordLength(lines.len()));
+    }
+    if lines[0] != BSMS_VERSION {
+        return Err(BsmsError::UnsupportedVersion(lines[0].to_string()));
+    }
+    Ok(lines)
+}
+
+// The BIP380 checksum of a descriptor string. We need to compute it ourselves for descriptors
+// using the '/**' shorthand, which aren't valid Miniscript descriptors. None if the string contains
+// a character which may not be part of a descriptor.
+fn desc_checksum(desc_str: &str) -> Option<String> {
+    fn polymod(chk: u64, value: u64) -> u64 {
+        let top = chk >> 35;
+        let mut chk = ((chk & 0x7ffffffff) << 5) ^ value;
+        for (i, gen) in CHECKSUM_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= gen;
+            }
+        }
+        chk
+    }
+
+    let (mut chk, mut class, mut class_count) = (1, 0, 0);
+    for c in desc_str.chars() {
+        let pos = CHECKSUM_INPUT_CHARSET.find(c)? as u64;
+        chk = polymod(chk, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            chk = polymod(chk, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        chk = polymod(chk, class);
+    }
+    for _ in 0..8 {
+        chk = polymod(chk, 0);
+    }
+    chk ^= 1;
+
+    Some(
+        (0..8)
+            .map(|i| CHECKSUM_CHARSET[((chk >> (5 * (7 - i))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+/// The token generated by the coordinator for a setup session, shared with all signers. An
+/// empty token is encoded as "00" and means records aren't encrypted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token(Vec<u8>);
+
+impl Token {
+    /// Create a token from 8 or 16 bytes of entropy.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Token, BsmsError> {
+        if bytes.len() != 8 && bytes.len() != 16 {
+            return Err(BsmsError::InvalidToken(bytes.to_hex()));
+        }
+        Ok(Token(bytes))
+    }
+
+    /// The token for a session without encryption.
+    pub fn no_encryption() -> Token {
+        Token(Vec::new())
+    }
+
+    /// Generate the token for a new setup session, from 8 bytes of the OS' randomness source.
+    pub fn new_random() -> Token {
+        let mut bytes = vec![0u8; 8];
+        getrandom::getrandom(&mut bytes).expect("OS randomness source must be available");
+        Token(bytes)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "00")
+        } else {
+            write!(f, "{}", self.0.to_hex())
+        }
+    }
+}
+
+impl str::FromStr for Token {
+    type Err = BsmsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "00" {
+            return Ok(Token::no_encryption());
+        }
+        let bytes = Vec::<u8>::from_hex(s).map_err(|_| BsmsError::InvalidToken(s.to_string()))?;
+        Token::from_bytes(bytes)
+    }
+}
+
+/// The record shared by a signer with the coordinator in the first round: its key, signed with
+/// the corresponding private key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRecord {
+    token: Token,
+    key: descriptor::DescriptorPublicKey,
+    description: String,
+    signature: String,
+}
+
+// Check a key is an xpub with an origin and without derivation steps.
+fn check_key(key: &descriptor::DescriptorPublicKey) -> Result<(), BsmsError> {
+    match key {
+        descriptor::DescriptorPublicKey::XPub(xpub)
+            if xpub.origin.is_some()
+                && xpub.derivation_path.is_master()
+                && xpub.wildcard == descriptor::Wildcard::None =>
+        {
+            Ok(())
+        }
+        _ => Err(BsmsError::InvalidKey(key.to_string())),
+    }
+}
+
+impl KeyRecord {
+    /// Create the key record of a signer from the signature of its
+    /// [signing message](KeyRecord::signing_message), checking it was made with this key.
+    pub fn new(
+        token: Token,
+        key: descriptor::DescriptorPublicKey,
+        description: String,
+        signature: String,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<KeyRecord, BsmsError> {
+        check_key(&key)?;
+        if description.contains('\n') {
+            return Err(BsmsError::InvalidDescription(description));
+        }
+        let record = KeyRecord {
+            token,
+            key,
+            description,
+            signature,
+        };
+        record.verify(&record.token, secp)?;
+        Ok(record)
+    }
+
+    /// The message a signer must sign with its key, as a Bitcoin signed message, for its key
+    /// record. The base64 encoded signature is the last line of the record.
+    pub fn signing_message(
+        token: &Token,
+        key: &descriptor::DescriptorPublicKey,
+        description: &str,
+    ) -> String {
+        format!("{}\n{}\n{}\n{}", BSMS_VERSION, token, key, description)
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    /// The xpub of the signer, along with its origin.
+    pub fn key(&self) -> &descriptor::DescriptorPublicKey {
+        &self.key
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The key of this signer to be used in a Liana descriptor, deriving both receive and change
+    /// addresses.
+    pub fn multipath_key(&self) -> descriptor::DescriptorPublicKey {
+        format!("{}/<0;1>/*", self.key)
+            .parse()
+            .expect("Always a valid multipath key")
+    }
+
+    // The signed part of the record.
+    fn message(&self) -> String {
+        KeyRecord::signing_message(&self.token, &self.key, &self.description)
+    }
+
+    /// Check this record is for the given setup session and that it was signed by the key it
+    /// contains.
+    pub fn verify(
+        &self,
+        token: &Token,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<(), BsmsError> {
+        if &self.token != token {
+            return Err(BsmsError::TokenMismatch(self.token.clone()));
+        }
+
+        let xpub = match self.key {
+            descriptor::DescriptorPublicKey::XPub(ref xpub) => xpub.xkey,
+            _ => unreachable!("Checked at parsing time"),
+        };
+        // A Bitcoin signed message: a header byte for recovering the public key followed by the
+        // compact signature. We know the public key, so we just check the signature.
+        let sig = base64::decode(&self.signature).map_err(|_| BsmsError::InvalidSignature)?;
+        if sig.len() != 65 {
+            return Err(BsmsError::InvalidSignature);
+        }
+        let mut sig = secp256k1::ecdsa::Signature::from_compact(&sig[1..])
+            .map_err(|_| BsmsError::InvalidSignature)?;
+        sig.normalize_s();
+        let msg_hash = signed_msg_hash(&self.message());
+        let msg = secp256k1::Message::from_slice(&msg_hash.into_inner()).expect("32 bytes");
+        secp.verify_ecdsa(&msg, &sig, &xpub.public_key)
+            .map_err(|_| BsmsError::InvalidSignature)
+    }
+}
+
+impl fmt::Display for KeyRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.message(), self.signature)
+    }
+}
+
+impl str::FromStr for KeyRecord {
+    type Err = BsmsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = record_lines(s, 5)?;
+        let token: Token = lines[1].parse()?;
+        let key = lines[2]
+            .parse::<descriptor::DescriptorPublicKey>()
+            .map_err(|_| BsmsError::InvalidKey(lines[2].to_string()))?;
+        check_key(&key)?;
+
+        Ok(KeyRecord {
+            token,
+            key,
+            description: lines[3].to_string(),
+            signature: lines[4].to_string(),
+        })
+    }
+}
+
+/// The record shared by the coordinator with all signers in the second round: the descriptor
+/// along with its first receive address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorRecord {
+    descriptor: MultipathDescriptor,
+    first_address: bitcoin::Address,
+}
+
+impl DescriptorRecord {
+    pub fn new(
+        descriptor: MultipathDescriptor,
+        network: bitcoin::Network,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> DescriptorRecord {
+        let first_address = descriptor
+            .receive_descriptor()
+            .derive(0.into(), secp)
+            .address(network);
+        DescriptorRecord {
+            descriptor,
+            first_address,
+        }
+    }
+
+    /// Parse a descriptor record, checking its first address matches its descriptor.
+    pub fn parse(
+        s: &str,
+        secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    ) -> Result<DescriptorRecord, BsmsError> {
+        let lines = record_lines(s, 4)?;
+
+        // The descriptor may use the '/**' shorthand for the receive and change derivation steps.
+        // If so, check its checksum before expanding it.
+        let descriptor: MultipathDescriptor = if lines[1].contains("/**") {
+            let mut parts = lines[1].splitn(2, '#');
+            let desc_str = parts.next().expect("At least one element");
+            if let Some(checksum) = parts.next() {
+                if desc_checksum(desc_str).as_deref() != Some(checksum) {
+                    return Err(BsmsError::InvalidChecksum(lines[1].to_string()));
+                }
+            }
+            desc_str.replace("/**", "/<0;1>/*").parse()?
+        } else {
+            lines[1].parse()?
+        };
+        if lines[2] != PATH_RESTRICTIONS && lines[2] != NO_PATH_RESTRICTIONS {
+            return Err(BsmsError::InvalidPathRestrictions(lines[2].to_string()));
+        }
+        let first_address: bitcoin::Address = lines[3]
+            .parse()
+            .map_err(|_| BsmsError::InvalidAddress(lines[3].to_string()))?;
+
+        let record = DescriptorRecord::new(descriptor, first_address.network, secp);
+        if record.first_address != first_address {
+            return Err(BsmsError::FirstAddressMismatch(first_address));
+        }
+        Ok(record)
+    }
+
+    pub fn descriptor(&self) -> &MultipathDescriptor {
+        &self.descriptor
+    }
+
+    pub fn first_address(&self) -> &bitcoin::Address {
+        &self.first_address
+    }
+
+    /// Whether the key of this signer is part of the descriptor.
+    pub fn contains_key(&self, key_record: &KeyRecord) -> bool {
+        let key = key_record.multipath_key();
+        let info = self.descriptor.info();
+        info.primary_path()
+            .keys()
+            .into_iter()
+            .chain(info.recovery_paths().values().flat_map(|path| path.keys()))
+            .chain(
+                info.abs_recovery_paths()
+                    .values()
+                    .flat_map(|path| path.keys()),
+            )
+            .any(|k| *k == key)
+    }
+}
+
+impl fmt::Display for DescriptorRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Signers expect the '/**' shorthand along with the path restrictions.
+        let desc_str = self.descriptor.to_string();
+        let desc_str = desc_str
+            .split('#')
+            .next()
+            .expect("At least one element")
+            .replace("/<0;1>/*", "/**");
+        let checksum = desc_checksum(&desc_str).expect("Descriptor characters only");
+        write!(
+            f,
+            "{}\n{}#{}\n{}\n{}",
+            BSMS_VERSION, desc_str, checksum, PATH_RESTRICTIONS, self.first_address
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        descriptors::{LianaDescKeys, RecoveryTimelock},
+        testutils::single_recovery_path,
+    };
+
+    use std::str::FromStr;
+
+    const KEY_RECORD: &str = "BSMS 1.0
+00
+[3442193e/48'/0'/0'/2']xpub6E64WfdQwBGz85XhbZryr9gUGUPBgoSu5WV6tJWpzAvgAmpVpdPHkT3XYm9R5J6MeWzvLQoz4q845taC9Q28XutbptxAmg7q8QPkjvTL4oi
+Signer 1 key
+IDRr3dKbpyQ1XF0jDoHwH6RXgDK4v9odymSZ3kBwIuWJcMel3TmHWFuQAS/Ql638t1lZ/8fAdK6F77z7AOyJKEg=";
+
+    #[test]
+    fn token() {
+        assert_eq!(Token::from_str("00").unwrap(), Token::no_encryption());
+        let token = Token::from_str("a54044308ceac9b7").unwrap();
+        assert_eq!(token.to_string(), "a54044308ceac9b7");
+        Token::from_str("a54044308ceac9b7a54044308ceac9b7").unwrap();
+        Token::from_str("a54044308ceac9").unwrap_err();
+        Token::from_str("a54044308ceac9zz").unwrap_err();
+        Token::from_bytes(vec![0; 4]).unwrap_err();
+
+        // Coordinators generate a random token for each session.
+        let token = Token::new_random();
+        assert_eq!(token.to_string().len(), 16);
+        assert_eq!(Token::from_str(&token.to_string()).unwrap(), token);
+        assert_ne!(Token::new_random(), token);
+    }
+
+    #[test]
+    fn key_record() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let record = KeyRecord::from_str(KEY_RECORD).unwrap();
+        assert_eq!(record.to_string(), KEY_RECORD);
+        assert_eq!(record.description(), "Signer 1 key");
+        assert_eq!(record.multipath_key().to_string(), "[3442193e/48'/0'/0'/2']xpub6E64WfdQwBGz85XhbZryr9gUGUPBgoSu5WV6tJWpzAvgAmpVpdPHkT3XYm9R5J6MeWzvLQoz4q845taC9Q28XutbptxAmg7q8QPkjvTL4oi/<0;1>/*");
+        record.verify(&Token::no_encryption(), &secp).unwrap();
+
+        // The token must be the one of the session.
+        let token = Token::from_str("a54044308ceac9b7").unwrap();
+        assert!(matches!(
+            record.verify(&token, &secp),
+            Err(BsmsError::TokenMismatch(_))
+        ));
+
+        // The signature must commit to all the record's fields.
+        let tampered =
+            KeyRecord::from_str(&KEY_RECORD.replace("Signer 1 key", "Signer 2 key")).unwrap();
+        assert!(matches!(
+            tampered.verify(&Token::no_encryption(), &secp),
+            Err(BsmsError::InvalidSignature)
+        ));
+
+        // The key must have an origin and no derivation steps.
+        KeyRecord::from_str(&KEY_RECORD.replace("[3442193e/48'/0'/0'/2']", "")).unwrap_err();
+        KeyRecord::from_str(&KEY_RECORD.replace("L4oi", "L4oi/0/*")).unwrap_err();
+        // Wrong version, or missing lines.
+        KeyRecord::from_str(&KEY_RECORD.replace("BSMS 1.0", "BSMS 2.0")).unwrap_err();
+        KeyRecord::from_str(&KEY_RECORD.replace("Signer 1 key\n", "")).unwrap_err();
+    }
+
+    #[test]
+    fn key_record_creation() {
+        let secp = secp256k1::Secp256k1::new();
+
+        // The signing message is the record without its signature.
+        let record = KeyRecord::from_str(KEY_RECORD).unwrap();
+        let signature = KEY_RECORD.lines().last().unwrap();
+        let message = &KEY_RECORD[..KEY_RECORD.len() - signature.len() - 1];
+        assert_eq!(
+            KeyRecord::signing_message(record.token(), record.key(), record.description()),
+            message
+        );
+        assert_eq!(
+            KeyRecord::new(
+                record.token().clone(),
+                record.key().clone(),
+                record.description().to_string(),
+                signature.to_string(),
+                &secp
+            )
+            .unwrap(),
+            record
+        );
+
+        // A signer creates its record by signing the message with the key it contains.
+        let master_xpriv =
+            bitcoin::util::bip32::ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, &[42; 32])
+                .unwrap();
+        let origin_path = bitcoin::util::bip32::DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+        let xpriv = master_xpriv.derive_priv(&secp, &origin_path).unwrap();
+        let key = descriptor::DescriptorPublicKey::XPub(descriptor::DescriptorXKey {
+            origin: Some((master_xpriv.fingerprint(&secp), origin_path)),
+            xkey: bitcoin::util::bip32::ExtendedPubKey::from_priv(&secp, &xpriv),
+            derivation_path: bitcoin::util::bip32::DerivationPath::master(),
+            wildcard: descriptor::Wildcard::None,
+        });
+        let token = Token::new_random();
+        let sign = |message: &str| {
+            let msg_hash = signed_msg_hash(message);
+            let msg = secp256k1::Message::from_slice(&msg_hash.into_inner()).unwrap();
+            let sig = secp.sign_ecdsa(&msg, &xpriv.private_key);
+            // The header byte is only used to recover the public key.
+            let mut sig_bytes = vec![31];
+            sig_bytes.extend_from_slice(&sig.serialize_compact());
+            base64::encode(&sig_bytes)
+        };
+        let message = KeyRecord::signing_message(&token, &key, "Hot signer");
+        let record = KeyRecord::new(
+            token.clone(),
+            key.clone(),
+            "Hot signer".to_string(),
+            sign(&message),
+            &secp,
+        )
+        .unwrap();
+        assert_eq!(KeyRecord::from_str(&record.to_string()).unwrap(), record);
+        record.verify(&token, &secp).unwrap();
+
+        // The signature must be for this record, by this key.
+        assert!(matches!(
+            KeyRecord::new(
+                token.clone(),
+                key.clone(),
+                "Another signer".to_string(),
+                sign(&message),
+                &secp
+            ),
+            Err(BsmsError::InvalidSignature)
+        ));
+        assert!(matches!(
+            KeyRecord::new(
+                token.clone(),
+                record.key().clone(),
+                "Hot signer".to_string(),
+                signature.to_string(),
+                &secp
+            ),
+            Err(BsmsError::InvalidSignature)
+        ));
+        // The description must fit on a single line, and the key must not have derivation steps.
+        let message = KeyRecord::signing_message(&token, &key, "Hot\nsigner");
+        assert!(matches!(
+            KeyRecord::new(
+                token.clone(),
+                key.clone(),
+                "Hot\nsigner".to_string(),
+                sign(&message),
+                &secp
+            ),
+            Err(BsmsError::InvalidDescription(_))
+        ));
+        let derived_key = descriptor::DescriptorPublicKey::from_str(&format!("{}/0", key)).unwrap();
+        let message = KeyRecord::signing_message(&token, &derived_key, "Hot signer");
+        assert!(matches!(
+            KeyRecord::new(
+                token,
+                derived_key,
+                "Hot signer".to_string(),
+                sign(&message),
+                &secp
+            ),
+            Err(BsmsError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn descriptor_record() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let record = KeyRecord::from_str(KEY_RECORD).unwrap();
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let desc = MultipathDescriptor::new(
+            LianaDescKeys::from_single(record.multipath_key()),
+            single_recovery_path(RecoveryTimelock::Relative(26352), heir_key),
+        )
+        .unwrap();
+
+        let desc_record = DescriptorRecord::new(desc.clone(), bitcoin::Network::Bitcoin, &secp);
+        assert!(desc_record.contains_key(&record));
+        let desc_record_str = desc_record.to_string();
+        let parsed = DescriptorRecord::parse(&desc_record_str, &secp).unwrap();
+        assert_eq!(parsed, desc_record);
+        assert_eq!(
+            parsed.first_address(),
+            &desc
+                .receive_descriptor()
+                .derive(0.into(), &secp)
+                .address(bitcoin::Network::Bitcoin)
+        );
+
+        // The descriptor is written using the '/**' shorthand, with a valid checksum.
+        let desc_str = desc.to_string();
+        let mut desc_parts = desc_str.split('#');
+        let (long_desc_str, checksum) = (desc_parts.next().unwrap(), desc_parts.next().unwrap());
+        assert_eq!(desc_checksum(long_desc_str).unwrap(), checksum);
+        let short_desc_str = long_desc_str.replace("/<0;1>/*", "/**");
+        let record_desc_line = desc_record_str.lines().nth(1).unwrap();
+        assert_eq!(
+            record_desc_line,
+            format!(
+                "{}#{}",
+                short_desc_str,
+                desc_checksum(&short_desc_str).unwrap()
+            )
+        );
+        assert_eq!(desc_record_str.lines().nth(2).unwrap(), "/0/*,/1/*");
+
+        // The shorthand may be used without a checksum, but not with an invalid one. The expanded
+        // descriptor is supported too.
+        let no_checksum_str = desc_record_str.replace(record_desc_line, &short_desc_str);
+        assert_eq!(
+            DescriptorRecord::parse(&no_checksum_str, &secp).unwrap(),
+            desc_record
+        );
+        let bad_checksum_str = desc_record_str.replace(
+            record_desc_line,
+            &format!("{}#{}", short_desc_str, checksum),
+        );
+        assert!(matches!(
+            DescriptorRecord::parse(&bad_checksum_str, &secp),
+            Err(BsmsError::InvalidChecksum(_))
+        ));
+        let long_record_str = desc_record_str.replace(record_desc_line, &desc_str);
+        assert_eq!(
+            DescriptorRecord::parse(&long_record_str, &secp).unwrap(),
+            desc_record
+        );
+
+        // The first address must match.
+        let other_addr = desc
+            .receive_descriptor()
+            .derive(1.into(), &secp)
+            .address(bitcoin::Network::Bitcoin);
+        let tampered = desc_record_str.replace(
+            &desc_record.first_address().to_string(),
+            &other_addr.to_string(),
+        );
+        assert!(matches!(
+            DescriptorRecord::parse(&tampered, &secp),
+            Err(BsmsError::FirstAddressMismatch(_))
+        ));
+    }
+}
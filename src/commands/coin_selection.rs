@@ -0,0 +1,246 @@
+//! Coin selection for Spend transactions.
+//!
+//! We first look for a selection which doesn't need a change output using branch-and-bound. If
+//! there is none, we fall back to selecting the coins with the largest value first.
+
+use miniscript::bitcoin;
+
+// The maximum number of branches explored by the branch-and-bound search.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// The parameters of a coin selection, all in sats.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectionParams {
+    /// The value of the outputs plus the fee for the transaction without any input.
+    pub target: u64,
+    /// The fee for including a single input, including its satisfaction.
+    pub input_fee: u64,
+    /// The fee for adding a change output.
+    pub change_fee: u64,
+    /// The cost of adding a change output and later spending it. A selection exceeding the
+    /// target by less than this is preferable to creating a change output.
+    pub cost_of_change: u64,
+    /// The minimum value of a change output.
+    pub min_change: u64,
+}
+
+// Explore the inclusion and exclusion branches for the coin at this index, recording the
+// selection which exceeds the target by the smallest amount. The coins' effective values must be
+// sorted in descending order.
+#[allow(clippy::too_many_arguments)]
+fn bnb_explore(
+    eff_values: &[u64],
+    index: usize,
+    curr_value: u64,
+    remaining_value: u64,
+    target: u64,
+    upper_bound: u64,
+    selection: &mut Vec<bool>,
+    best: &mut Option<(u64, Vec<bool>)>,
+    tries: &mut usize,
+) {
+    if *tries == 0 || curr_value > upper_bound {
+        return;
+    }
+    *tries -= 1;
+    if curr_value >= target {
+        let excess = curr_value - target;
+        if best.as_ref().map(|(e, _)| excess < *e).unwrap_or(true) {
+            *best = Some((excess, selection.clone()));
+        }
+        return;
+    }
+    if index == eff_values.len() || curr_value + remaining_value < target {
+        return;
+    }
+
+    let value = eff_values[index];
+    selection[index] = true;
+    bnb_explore(
+        eff_values,
+        index + 1,
+        curr_value + value,
+        remaining_value - value,
+        target,
+        upper_bound,
+        selection,
+        best,
+        tries,
+    );
+    selection[index] = false;
+    // Excluding a coin of the same value as a previously excluded one would lead to the same
+    // selections.
+    if index > 0 && !selection[index - 1] && eff_values[index - 1] == value {
+        return;
+    }
+    bnb_explore(
+        eff_values,
+        index + 1,
+        curr_value,
+        remaining_value - value,
+        target,
+        upper_bound,
+        selection,
+        best,
+        tries,
+    );
+}
+
+/// Select coins among the given candidates (outpoint and value in sats) to fund a transaction.
+/// Prefers a selection for which no change output is needed. Returns None if the candidates
+/// can't fund the transaction.
+pub fn select_coins(
+    candidates: &[(bitcoin::OutPoint, u64)],
+    params: &SelectionParams,
+) -> Option<Vec<bitcoin::OutPoint>> {
+    // Only consider the coins worth more than what it costs to spend them, by decreasing
+    // effective value.
+    let mut coins: Vec<(bitcoin::OutPoint, u64)> = candidates
+        .iter()
+        .filter(|(_, value)| *value > params.input_fee)
+        .map(|(op, value)| (*op, value - params.input_fee))
+        .collect();
+    coins.sort_unstable_by(|(op_a, a), (op_b, b)| b.cmp(a).then(op_a.cmp(op_b)));
+
+    // First try to find a changeless solution.
+    let eff_values: Vec<u64> = coins.iter().map(|(_, v)| *v).collect();
+    let total_value = eff_values.iter().sum();
+    let mut best = None;
+    let mut tries = BNB_MAX_TRIES;
+    bnb_explore(
+        &eff_values,
+        0,
+        0,
+        total_value,
+        params.target,
+        params.target + params.cost_of_change,
+        &mut vec![false; eff_values.len()],
+        &mut best,
+        &mut tries,
+    );
+    if let Some((_, selection)) = best {
+        return Some(
+            coins
+                .iter()
+                .zip(selection)
+                .filter_map(|((op, _), selected)| if selected { Some(*op) } else { None })
+                .collect(),
+        );
+    }
+
+    // Otherwise, select the largest coins first until we can afford a change output. If we
+    // can't, the excess will go to fees.
+    let with_change_target = params.target + params.change_fee + params.min_change;
+    let mut selected = Vec::new();
+    let mut selected_value = 0;
+    for (op, value) in coins {
+        selected.push(op);
+        selected_value += value;
+        if selected_value >= with_change_target {
+            break;
+        }
+    }
+    if selected_value >= params.target {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dummy_candidates(values: &[u64]) -> Vec<(bitcoin::OutPoint, u64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let op = bitcoin::OutPoint::from_str(&format!(
+                    "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:{}",
+                    i
+                ))
+                .unwrap();
+                (op, *value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn coin_selection() {
+        let candidates = dummy_candidates(&[10_000, 50_000, 20_100, 30_100, 100_000]);
+        let params = SelectionParams {
+            target: 50_000,
+            input_fee: 100,
+            change_fee: 50,
+            cost_of_change: 200,
+            min_change: 5_000,
+        };
+
+        // The effective values of the coins, net of the input fee, are 9_900, 49_900, 20_000,
+        // 30_000 and 99_900. An exact match without change is preferred.
+        let mut selected = select_coins(&candidates, &params).unwrap();
+        selected.sort();
+        assert_eq!(selected, vec![candidates[2].0, candidates[3].0]);
+        let params = SelectionParams {
+            target: 50_000 - 100,
+            ..params
+        };
+        assert_eq!(
+            select_coins(&candidates, &params).unwrap(),
+            vec![candidates[1].0]
+        );
+
+        // Combination of coins without change.
+        let params = SelectionParams {
+            target: 59_800,
+            ..params
+        };
+        let mut selected = select_coins(&candidates, &params).unwrap();
+        selected.sort();
+        assert_eq!(selected, vec![candidates[0].0, candidates[1].0]);
+
+        // No changeless solution: the largest coins are selected first, until a change output
+        // can be afforded.
+        let params = SelectionParams {
+            target: 60_000,
+            ..params
+        };
+        assert_eq!(
+            select_coins(&candidates, &params).unwrap(),
+            vec![candidates[4].0]
+        );
+        let params = SelectionParams {
+            target: 95_000,
+            ..params
+        };
+        assert_eq!(
+            select_coins(&candidates, &params).unwrap(),
+            vec![candidates[4].0, candidates[1].0]
+        );
+        let params = SelectionParams {
+            target: 120_000,
+            ..params
+        };
+        assert_eq!(
+            select_coins(&candidates, &params).unwrap(),
+            vec![candidates[4].0, candidates[1].0]
+        );
+
+        // Coins worth less than the cost of spending them are ignored, and we may not have
+        // enough funds.
+        let params = SelectionParams {
+            target: 10_000,
+            input_fee: 10_000,
+            ..params
+        };
+        assert!(select_coins(&dummy_candidates(&[10_000, 5_000]), &params).is_none());
+        let params = SelectionParams {
+            target: 210_000,
+            input_fee: 100,
+            ..params
+        };
+        assert!(select_coins(&candidates, &params).is_none());
+    }
+}
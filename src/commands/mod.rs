@@ -2,26 +2,36 @@
 //!
 //! External interface to the Liana daemon.
 
+mod coin_selection;
 mod utils;
 
 use crate::{
+    bip322,
     bitcoin::BitcoinInterface,
-    database::{Coin, CoinType, DatabaseInterface},
-    descriptors, DaemonControl, VERSION,
+    database::{Coin, CoinType, DatabaseConnection, DatabaseInterface, LabelItem},
+    descriptors, reserves, DaemonControl, VERSION,
 };
 
-use utils::{deser_amount_from_sats, deser_base64, deser_hex, ser_amount, ser_base64, ser_hex};
+use utils::{
+    deser_amount_from_sats, deser_base64, deser_hex, deser_optional_amount_from_sats, ser_amount,
+    ser_base64, ser_hex, ser_optional_amount,
+};
 
 use std::{
-    collections::{hash_map, BTreeMap, HashMap},
-    convert::TryInto,
-    fmt,
+    cmp,
+    collections::{hash_map, BTreeMap, HashMap, HashSet},
+    convert::{TryFrom, TryInto},
+    fmt, iter,
+    str::FromStr,
 };
 
 use miniscript::{
     bitcoin::{
         self,
-        util::psbt::{Input as PsbtIn, Output as PsbtOut, PartiallySignedTransaction as Psbt},
+        util::{
+            bip32,
+            psbt::{Input as PsbtIn, Output as PsbtOut, PartiallySignedTransaction as Psbt},
+        },
     },
     psbt::PsbtExt,
 };
@@ -37,6 +47,20 @@ const MAX_FEE: u64 = bitcoin::blockdata::constants::COIN_VALUE;
 // Assume that paying more than 1000sat/vb in feerate is a bug.
 const MAX_FEERATE: u64 = 1_000;
 
+// The feerate a replacement transaction must pay for its own relay, on top of the fees of the
+// transaction it replaces (BIP125 rule 4). That's Bitcoin Core's default.
+const INCREMENTAL_RELAY_FEERATE: u64 = 1;
+
+// The confirmation targets, in blocks, for which we give fee estimates. Bitcoin Core doesn't
+// estimate feerates for targets further than 1008 blocks.
+const FEE_ESTIMATES_TARGETS: [u16; 7] = [2, 3, 6, 12, 24, 144, 1008];
+const MAX_CONF_TARGET: u16 = 1008;
+
+// The number of blocks over which we measure the average interval between blocks, and the
+// interval we assume if we can't.
+const BLOCK_INTERVAL_WINDOW: i32 = 144;
+const DEFAULT_BLOCK_INTERVAL_SECS: u32 = 600;
+
 // Timestamp in the header of the genesis block. Used for sanity checks.
 const MAINNET_GENESIS_TIME: u32 = 1231006505;
 
@@ -65,6 +89,28 @@ pub enum CommandError {
     /// An error that might occur in the racy rescan triggering logic.
     RescanTrigger(String),
     RecoveryNotAvailable,
+    UnknownRecoveryTimelock(descriptors::RecoveryTimelock),
+    /// The PSBT doesn't have as many inputs and outputs as its unsigned transaction.
+    MalformedPsbt,
+    /// The Spend transaction isn't pending in the mempool.
+    NotReplaceable(bitcoin::Txid),
+    ReplacementFeerate(/* requested */ u64, /* current */ u64),
+    ReplacedSpend(bitcoin::Txid, /* replacement */ bitcoin::Txid),
+    AlreadyConfirmed(bitcoin::OutPoint),
+    NotInMempool(bitcoin::Txid),
+    InvalidConfTarget(/* blocks */ u16),
+    FeeEstimateUnavailable(/* blocks */ u16),
+    MalformedLabels(/* line */ usize, String),
+    FrozenCoin(bitcoin::OutPoint),
+    NoExpiringCoin(/* blocks */ u32),
+    RecoveryNotMatured(bitcoin::OutPoint),
+    UnknownAddress(bitcoin::Address),
+    InvalidMessageSignature(String),
+    UnconfirmedCoin(bitcoin::OutPoint),
+    NoConfirmedCoin,
+    InvalidReservesProof(String),
+    /// This parent transaction has unconfirmed ancestors, and is bumped along with other parents.
+    UnconfirmedAncestors(bitcoin::Txid),
 }
 
 impl fmt::Display for CommandError {
@@ -109,6 +155,68 @@ impl fmt::Display for CommandError {
                 f,
                 "No coin currently available through the timelocked recovery path."
             ),
+            Self::UnknownRecoveryTimelock(tl) => {
+                write!(f, "There is no recovery path with timelock '{}'.", tl)
+            }
+            Self::MalformedPsbt => write!(
+                f,
+                "The PSBT doesn't have as many inputs and outputs as its unsigned transaction."
+            ),
+            Self::NotReplaceable(txid) => write!(
+                f,
+                "Spend transaction '{}' is not pending confirmation, it can't be replaced.",
+                txid
+            ),
+            Self::ReplacementFeerate(requested, current) => write!(
+                f,
+                "Replacement feerate ({} sat/vb) must be higher than the current one ({} sat/vb).",
+                requested, current
+            ),
+            Self::ReplacedSpend(txid, replacement) => write!(
+                f,
+                "Spend transaction '{}' was replaced by '{}'.",
+                txid, replacement
+            ),
+            Self::AlreadyConfirmed(op) => write!(f, "Coin at '{}' is already confirmed.", op),
+            Self::NotInMempool(txid) => {
+                write!(f, "Transaction '{}' is not in the mempool.", txid)
+            }
+            Self::InvalidConfTarget(target) => write!(
+                f,
+                "Invalid confirmation target: {} blocks. Must be between 1 and {}.",
+                target, MAX_CONF_TARGET
+            ),
+            Self::FeeEstimateUnavailable(target) => write!(
+                f,
+                "No fee estimate available for a confirmation within {} blocks. Please provide a feerate.",
+                target
+            ),
+            Self::FrozenCoin(op) => write!(f, "Coin at '{}' is frozen.", op),
+            Self::RecoveryNotMatured(op) => write!(
+                f,
+                "The recovery path of the coin at '{}' isn't available at the next block.",
+                op
+            ),
+            Self::NoExpiringCoin(blocks) => write!(
+                f,
+                "No coin's recovery path becomes available within {} blocks.",
+                blocks
+            ),
+            Self::MalformedLabels(line, e) => {
+                write!(f, "Invalid BIP-329 label record at line {}: {}", line, e)
+            }
+            Self::UnknownAddress(addr) => write!(f, "Address '{}' is not one of ours.", addr),
+            Self::InvalidMessageSignature(e) => {
+                write!(f, "Invalid message signature: '{}'.", e)
+            }
+            Self::UnconfirmedCoin(op) => write!(f, "Coin at '{}' is not confirmed.", op),
+            Self::NoConfirmedCoin => write!(f, "No confirmed unspent coin."),
+            Self::InvalidReservesProof(e) => write!(f, "Invalid proof of reserves: '{}'.", e),
+            Self::UnconfirmedAncestors(txid) => write!(
+                f,
+                "Transaction '{}' has unconfirmed ancestors. It may only be accelerated on its own.",
+                txid
+            ),
         }
     }
 }
@@ -127,9 +235,72 @@ fn check_output_value(value: bitcoin::Amount) -> Result<(), CommandError> {
     }
 }
 
+// A random number from the OS' randomness source. It is only used for privacy purposes.
+fn random_u32() -> u32 {
+    let mut buf = [0u8; 4];
+    getrandom::getrandom(&mut buf).expect("OS randomness source must be available");
+    u32::from_le_bytes(buf)
+}
+
+// The nLockTime to set on a new transaction to discourage fee sniping, as Bitcoin Core does: the
+// current tip height, and once in a while a bit less so that transactions delayed before being
+// broadcast don't stand out.
+fn anti_fee_sniping_locktime(tip_height: i32) -> bitcoin::PackedLockTime {
+    let mut height = u32::try_from(tip_height).expect("Height is never negative");
+    if random_u32() % 10 == 0 {
+        height = height.saturating_sub(random_u32() % 100);
+    }
+    bitcoin::PackedLockTime(height)
+}
+
+// The nLockTime of a transaction spending through a recovery path with this absolute timelock. If
+// the timelock is a height, we can use a later one to discourage fee sniping.
+fn abs_recovery_locktime(cltv_value: u32, tip_height: i32) -> bitcoin::PackedLockTime {
+    if cltv_value < descriptors::LOCKTIME_THRESHOLD {
+        cmp::max(
+            bitcoin::PackedLockTime(cltv_value),
+            anti_fee_sniping_locktime(tip_height),
+        )
+    } else {
+        bitcoin::PackedLockTime(cltv_value)
+    }
+}
+
+// Set the fields of the transaction used to discourage fee sniping, given the confirmation height
+// of the coin spent by each input.
+// For a Taproot descriptor, as per BIP326 half of the time we use the nSequence of a random input
+// instead of the nLockTime. This is so our transactions look like those from protocols which need
+// to set the nSequence (such as Liana's recovery transactions). This is only possible if all the
+// coins are confirmed, and none was confirmed more than 65535 blocks ago.
+// We only do so for single-input transactions. Setting it on one input among many would give the
+// inputs different nSequences, which we would then fail to analyze (see
+// `MultipathDescriptor::partial_spend_info`).
+fn set_anti_fee_sniping(
+    tx: &mut bitcoin::Transaction,
+    coins_heights: &[Option<i32>],
+    tip_height: i32,
+    is_taproot: bool,
+) {
+    let confirmations: Option<Vec<u16>> = coins_heights
+        .iter()
+        .map(|h| h.and_then(|h| u16::try_from(tip_height + 1 - h).ok()))
+        .collect();
+    match confirmations {
+        Some(confs) if is_taproot && confs.len() == 1 && random_u32() % 2 == 0 => {
+            let mut sequence = confs[0];
+            if random_u32() % 10 == 0 {
+                sequence = cmp::max(sequence.saturating_sub((random_u32() % 100) as u16), 1);
+            }
+            tx.input[0].sequence = bitcoin::Sequence::from_height(sequence);
+            tx.lock_time = bitcoin::PackedLockTime::ZERO;
+        }
+        _ => tx.lock_time = anti_fee_sniping_locktime(tip_height),
+    }
+}
+
 // Apply some sanity checks on a created transaction's PSBT.
 // TODO: add more sanity checks from revault_tx
-fn sanity_check_psbt(psbt: &Psbt) -> Result<(), CommandError> {
+fn sanity_check_psbt(psbt: &Psbt, tip_height: i32) -> Result<(), CommandError> {
     let tx = &psbt.unsigned_tx;
 
     // Must have as many in/out in the PSBT and Bitcoin tx.
@@ -137,11 +308,29 @@ fn sanity_check_psbt(psbt: &Psbt) -> Result<(), CommandError> {
         return Err(CommandError::SanityCheckFailure(psbt.clone()));
     }
 
+    // If set, the nLockTime must be enforced. If it's a height, it must not prevent the
+    // transaction from being included in the next block.
+    let lock_time = tx.lock_time.to_u32();
+    if lock_time != 0 {
+        if !tx
+            .input
+            .iter()
+            .any(|txin| txin.sequence.enables_absolute_lock_time())
+        {
+            return Err(CommandError::SanityCheckFailure(psbt.clone()));
+        }
+        if lock_time < descriptors::LOCKTIME_THRESHOLD
+            && i64::from(lock_time) > i64::from(tip_height)
+        {
+            return Err(CommandError::SanityCheckFailure(psbt.clone()));
+        }
+    }
+
     // Compute the transaction input value, checking all PSBT inputs have the derivation
     // index set for signing devices to recognize them as ours.
     let mut value_in = 0;
     for psbtin in psbt.inputs.iter() {
-        if psbtin.bip32_derivation.is_empty() {
+        if psbtin.bip32_derivation.is_empty() && psbtin.tap_key_origins.is_empty() {
             return Err(CommandError::SanityCheckFailure(psbt.clone()));
         }
         value_in += psbtin
@@ -179,6 +368,77 @@ fn sanity_check_psbt(psbt: &Psbt) -> Result<(), CommandError> {
     Ok(())
 }
 
+// Get the value of the coin spent by this PSBT input, if the PSBT input contains it.
+fn psbt_in_value(psbt_in: &PsbtIn, txin: &bitcoin::TxIn) -> Option<bitcoin::Amount> {
+    psbt_in
+        .witness_utxo
+        .as_ref()
+        .or_else(|| {
+            psbt_in.non_witness_utxo.as_ref().and_then(|tx| {
+                tx.output
+                    .get(usize::try_from(txin.previous_output.vout).ok()?)
+            })
+        })
+        .map(|txo| bitcoin::Amount::from_sat(txo.value))
+}
+
+// Get the signatures present and missing for each of the spending paths available to an input.
+// Also returns the size of a satisfaction through each of them.
+fn analyze_paths(
+    main_desc: &descriptors::MultipathDescriptor,
+    desc_info: &descriptors::LianaDescInfo,
+    spend_info: &descriptors::PartialSpendInfo,
+) -> Vec<(AnalyzedPath, usize)> {
+    let recovery_paths = spend_info.recovery_paths().iter().map(|(csv, info)| {
+        (
+            descriptors::RecoveryTimelock::Relative(*csv),
+            &desc_info.recovery_paths()[csv],
+            info,
+        )
+    });
+    let abs_recovery_paths = spend_info.abs_recovery_paths().iter().map(|(cltv, info)| {
+        (
+            descriptors::RecoveryTimelock::Absolute(*cltv),
+            &desc_info.abs_recovery_paths()[cltv],
+            info,
+        )
+    });
+    let paths = std::iter::once((
+        descriptors::SpendingPath::Primary,
+        desc_info.primary_path(),
+        spend_info.primary_path(),
+    ))
+    .chain(
+        recovery_paths
+            .chain(abs_recovery_paths)
+            .map(|(tl, path_info, info)| {
+                (descriptors::SpendingPath::Recovery(tl), path_info, info)
+            }),
+    );
+
+    paths
+        .map(|(path, path_info, info)| {
+            let mut signed: Vec<bip32::Fingerprint> = info.signed_pubkeys.keys().copied().collect();
+            signed.sort_unstable();
+            let mut missing: Vec<bip32::Fingerprint> = path_info
+                .thresh_fingerprints()
+                .1
+                .into_iter()
+                .filter(|fg| !info.signed_pubkeys.contains_key(fg))
+                .collect();
+            missing.sort_unstable();
+            let analyzed_path = AnalyzedPath {
+                path,
+                threshold: info.threshold,
+                signed,
+                missing,
+                has_preimage: info.has_preimage,
+            };
+            (analyzed_path, main_desc.path_sat_vbytes(path_info))
+        })
+        .collect()
+}
+
 // Get the size of a type that can be serialized (txos, transactions, ..)
 fn serializable_size<T: bitcoin::consensus::Encodable + ?Sized>(t: &T) -> u64 {
     bitcoin::consensus::serialize(t).len().try_into().unwrap()
@@ -195,6 +455,177 @@ impl DaemonControl {
         desc.derive(coin.derivation_index, &self.secp)
     }
 
+    // Create the PSBT input spending this coin, populated with the information needed by signers.
+    fn coin_psbt_in(&self, coin: &Coin) -> Result<PsbtIn, CommandError> {
+        // Fetch the transaction that created it.
+        let spent_tx = self
+            .bitcoin
+            .wallet_transaction(&coin.outpoint.txid)
+            .ok_or(CommandError::FetchingTransaction(coin.outpoint))?
+            .0;
+
+        let coin_desc = self.derived_desc(coin);
+        let witness_utxo = Some(bitcoin::TxOut {
+            value: coin.amount.to_sat(),
+            script_pubkey: coin_desc.script_pubkey(),
+        });
+        let mut psbt_in = PsbtIn {
+            witness_utxo,
+            non_witness_utxo: Some(spent_tx),
+            ..PsbtIn::default()
+        };
+        coin_desc.update_psbt_in(&mut psbt_in);
+
+        Ok(psbt_in)
+    }
+
+    // Create the PSBT output for a payment to this address. If it's an address of ours, signal it
+    // as change to signing devices by adding the BIP32 derivation path to the PSBT output.
+    fn destination_psbt_out(
+        &self,
+        db_conn: &mut dyn DatabaseConnection,
+        address: &bitcoin::Address,
+    ) -> PsbtOut {
+        let mut psbt_out = PsbtOut::default();
+        if let Some((index, is_change)) = db_conn.derivation_index_by_address(address) {
+            let desc = if is_change {
+                self.config.main_descriptor.change_descriptor()
+            } else {
+                self.config.main_descriptor.receive_descriptor()
+            };
+            desc.derive(index, &self.secp)
+                .update_psbt_out(&mut psbt_out);
+        }
+        psbt_out
+    }
+
+    // The timelock of the recovery path used by default: the one with the lowest relative
+    // timelock, or if there is none with a relative timelock the one with the lowest absolute
+    // timelock.
+    fn default_recovery_timelock(&self) -> descriptors::RecoveryTimelock {
+        let desc_info = self.config.main_descriptor.info();
+        desc_info
+            .recovery_paths()
+            .keys()
+            .next()
+            .map(|csv| descriptors::RecoveryTimelock::Relative(*csv))
+            .or_else(|| {
+                desc_info
+                    .abs_recovery_paths()
+                    .keys()
+                    .next()
+                    .map(|cltv| descriptors::RecoveryTimelock::Absolute(*cltv))
+            })
+            .expect("There is always at least one recovery path")
+    }
+
+    // Check the recovery path with this timelock exists, or get the one used by default.
+    fn recovery_timelock(
+        &self,
+        timelock: Option<descriptors::RecoveryTimelock>,
+    ) -> Result<descriptors::RecoveryTimelock, CommandError> {
+        match timelock {
+            Some(timelock)
+                if !self
+                    .config
+                    .main_descriptor
+                    .info()
+                    .has_recovery_path(timelock) =>
+            {
+                Err(CommandError::UnknownRecoveryTimelock(timelock))
+            }
+            Some(timelock) => Ok(timelock),
+            None => Ok(self.default_recovery_timelock()),
+        }
+    }
+
+    // Whether a recovery path with this absolute timelock may be used in the next block. The next
+    // block's height must be strictly greater than the nLockTime, or the median time past of the
+    // current tip if the nLockTime is a timestamp.
+    fn is_abs_recovery_available(&self, cltv_value: u32, tip_height: i32) -> bool {
+        if cltv_value < descriptors::LOCKTIME_THRESHOLD {
+            i64::from(tip_height) >= i64::from(cltv_value)
+        } else {
+            self.bitcoin.tip_median_time_past() > cltv_value
+        }
+    }
+
+    // Get the recovery path a transaction we created spends through, if it isn't the primary path.
+    // It's recognized from the nSequence and nLockTime set by `create_spend_through`. Through a
+    // recovery path with a relative timelock all inputs have its nSequence, and the nLockTime is
+    // set to discourage fee sniping. Through the primary path the nSequence of a single input may
+    // be used to discourage fee sniping instead, but the nLockTime is then null.
+    // A spend through the primary path can't be told apart from one through a recovery path with
+    // an absolute timelock on a height which had already expired. In this case we assume it spends
+    // through the path with the largest satisfaction, so as never to underestimate its size.
+    fn spent_recovery_timelock(
+        &self,
+        tx: &bitcoin::Transaction,
+    ) -> Option<descriptors::RecoveryTimelock> {
+        let main_desc = &self.config.main_descriptor;
+        let desc_info = main_desc.info();
+        let first_sequence = tx.input.first()?.sequence;
+        if !tx.input.iter().all(|txin| txin.sequence == first_sequence) {
+            return None;
+        }
+
+        if tx.lock_time != bitcoin::PackedLockTime::ZERO {
+            if let Some(csv) = desc_info
+                .recovery_paths()
+                .keys()
+                .find(|csv| first_sequence == bitcoin::Sequence::from_height(**csv))
+            {
+                return Some(descriptors::RecoveryTimelock::Relative(*csv));
+            }
+        }
+
+        if !first_sequence.enables_absolute_lock_time() {
+            return None;
+        }
+        let lock_time = tx.lock_time.to_u32();
+        let is_height = lock_time < descriptors::LOCKTIME_THRESHOLD;
+        // The spending paths whose timelock is satisfied by the nLockTime, with the weight of
+        // their satisfaction.
+        let mut candidates: Vec<(Option<descriptors::RecoveryTimelock>, usize)> = desc_info
+            .abs_recovery_paths()
+            .iter()
+            .filter(|(cltv, _)| {
+                (**cltv < descriptors::LOCKTIME_THRESHOLD) == is_height && **cltv <= lock_time
+            })
+            .map(|(cltv, path)| {
+                (
+                    Some(descriptors::RecoveryTimelock::Absolute(*cltv)),
+                    main_desc.path_sat_weight(path),
+                )
+            })
+            .collect();
+        if is_height {
+            candidates.push((None, main_desc.path_sat_weight(desc_info.primary_path())));
+            candidates
+                .into_iter()
+                .max_by_key(|(_, weight)| *weight)
+                .and_then(|(timelock, _)| timelock)
+        } else {
+            // Our nLockTime is never a timestamp unless it's for an absolute timelock.
+            candidates.pop().and_then(|(timelock, _)| timelock)
+        }
+    }
+
+    // The average interval between the last blocks, in seconds.
+    fn average_block_interval(&self, tip_height: i32) -> u32 {
+        let window = cmp::min(tip_height, BLOCK_INTERVAL_WINDOW);
+        if window < 1 {
+            return DEFAULT_BLOCK_INTERVAL_SECS;
+        }
+        match (
+            self.bitcoin.block_time(tip_height - window),
+            self.bitcoin.block_time(tip_height),
+        ) {
+            (Some(start), Some(end)) if end > start => (end - start) / window as u32,
+            _ => DEFAULT_BLOCK_INTERVAL_SECS,
+        }
+    }
+
     // Check whether this address is valid for the network we are operating on.
     fn validate_address(&self, addr: &bitcoin::Address) -> Result<(), CommandError> {
         // NOTE: signet uses testnet addresses
@@ -229,6 +660,7 @@ impl DaemonControl {
             descriptors: GetInfoDescriptors {
                 main: self.config.main_descriptor.clone(),
             },
+            policy: self.config.main_descriptor.info().explanation(),
             rescan_progress,
         }
     }
@@ -251,12 +683,127 @@ impl DaemonControl {
         GetAddressResult { address }
     }
 
+    /// List the addresses we derived so far along with their usage, by increasing derivation
+    /// index. At most `count` derivation indexes are listed starting from `start_index`, each with
+    /// its receive and change addresses if they were already handed out.
+    pub fn list_addresses(&self, start_index: u32, count: u32) -> ListAddressesResult {
+        let mut db_conn = self.db.connection();
+        let receive_index: u32 = db_conn.receive_index().into();
+        let change_index: u32 = db_conn.change_index().into();
+        let end_index = cmp::min(
+            start_index.saturating_add(count),
+            cmp::max(receive_index, change_index),
+        );
+
+        // The number of coins and the value received at each derivation index, for each kind.
+        let mut usage: HashMap<(u32, bool), (usize, bitcoin::Amount)> = HashMap::new();
+        for coin in db_conn.coins(CoinType::All).values() {
+            let index: u32 = coin.derivation_index.into();
+            if (start_index..end_index).contains(&index) {
+                let entry = usage
+                    .entry((index, coin.is_change))
+                    .or_insert((0, bitcoin::Amount::from_sat(0)));
+                entry.0 += 1;
+                entry.1 += coin.amount;
+            }
+        }
+
+        let network = self.config.bitcoin_config.network;
+        let receive_desc = self.config.main_descriptor.receive_descriptor();
+        let change_desc = self.config.main_descriptor.change_descriptor();
+        let mut addresses = Vec::new();
+        for index in start_index..end_index {
+            for (is_change, desc, next_index) in &[
+                (false, receive_desc, receive_index),
+                (true, change_desc, change_index),
+            ] {
+                if index >= *next_index {
+                    continue;
+                }
+                let address = desc.derive(index.into(), &self.secp).address(network);
+                let (coins, received) = usage
+                    .get(&(index, *is_change))
+                    .copied()
+                    .unwrap_or((0, bitcoin::Amount::from_sat(0)));
+                addresses.push(ListAddressesEntry {
+                    index,
+                    address,
+                    is_change: *is_change,
+                    coins,
+                    received,
+                });
+            }
+        }
+
+        ListAddressesResult { addresses }
+    }
+
+    /// Get whether this address is one of ours. If it is, also get its derivation index and the
+    /// origin of the keys it is derived from.
+    pub fn get_address_info(
+        &self,
+        address: &bitcoin::Address,
+    ) -> Result<GetAddressInfoResult, CommandError> {
+        self.validate_address(address)?;
+
+        let mut db_conn = self.db.connection();
+        let (index, is_change) = match db_conn.derivation_index_by_address(address) {
+            Some(info) => info,
+            None => {
+                return Ok(GetAddressInfoResult {
+                    address: address.clone(),
+                    is_mine: false,
+                    index: None,
+                    is_change: false,
+                    derivations: Vec::new(),
+                })
+            }
+        };
+
+        let desc = if is_change {
+            self.config.main_descriptor.change_descriptor()
+        } else {
+            self.config.main_descriptor.receive_descriptor()
+        };
+        let derived_desc = desc.derive(index, &self.secp);
+        let derivations = if self.config.main_descriptor.is_taproot() {
+            derived_desc
+                .tap_key_origins()
+                .into_iter()
+                .map(|(key, (_, (fingerprint, derivation_path)))| KeyDerivation {
+                    pubkey: key.to_string(),
+                    fingerprint,
+                    derivation_path,
+                })
+                .collect()
+        } else {
+            derived_desc
+                .bip32_derivations()
+                .into_iter()
+                .map(|(key, (fingerprint, derivation_path))| KeyDerivation {
+                    pubkey: key.to_string(),
+                    fingerprint,
+                    derivation_path,
+                })
+                .collect()
+        };
+
+        Ok(GetAddressInfoResult {
+            address: address.clone(),
+            is_mine: true,
+            index: Some(index.into()),
+            is_change,
+            derivations,
+        })
+    }
+
     /// Get a list of all known coins.
     pub fn list_coins(&self) -> ListCoinsResult {
         let mut db_conn = self.db.connection();
+        let coins = db_conn.coins(CoinType::All);
+        let mut labels = db_conn.labels(&coins.keys().map(|op| LabelItem::OutPoint(*op)).collect());
         #[allow(clippy::iter_kv_map)] // Because Rust 1.48
-        let coins: Vec<ListCoinsEntry> = db_conn
-            .coins(CoinType::All)
+        let coins: Vec<ListCoinsEntry> = coins
             // Can't use into_values as of Rust 1.48
             .into_iter()
             .map(|(_, coin)| {
@@ -266,6 +813,7 @@ impl DaemonControl {
                     block_height,
                     spend_txid,
                     spend_block,
+                    is_frozen,
                     ..
                 } = coin;
                 let spend_info = spend_txid.map(|txid| LCSpendInfo {
@@ -277,22 +825,239 @@ impl DaemonControl {
                     outpoint,
                     block_height,
                     spend_info,
+                    is_frozen,
+                    label: labels.remove(&LabelItem::OutPoint(outpoint)),
                 }
             })
             .collect();
         ListCoinsResult { coins }
     }
 
+    /// Freeze (or unfreeze) some of our unspent coins. A frozen coin is never selected
+    /// automatically, nor swept by a recovery transaction, and can only be spent if explicitly
+    /// requested.
+    pub fn freeze_coins(
+        &self,
+        outpoints: &[bitcoin::OutPoint],
+        frozen: bool,
+    ) -> Result<(), CommandError> {
+        if outpoints.is_empty() {
+            return Err(CommandError::NoOutpoint);
+        }
+
+        let mut db_conn = self.db.connection();
+        let coins = db_conn.coins_by_outpoints(outpoints);
+        for op in outpoints {
+            let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+            if coin.is_spent() {
+                return Err(CommandError::AlreadySpent(*op));
+            }
+        }
+        db_conn.freeze_coins(outpoints, frozen);
+
+        Ok(())
+    }
+
+    /// Get when a recovery path first becomes available for each of our unspent coins, and which
+    /// one. The coins are grouped by whether they are already recoverable, become recoverable
+    /// within `expiring_within` blocks, or are safe. The time at which a recovery path unlocks is
+    /// projected using the average interval between the last blocks.
+    pub fn get_expiry_report(&self, expiring_within: u32) -> ExpiryReportResult {
+        let desc_info = self.config.main_descriptor.info();
+        let timelocks: Vec<descriptors::RecoveryTimelock> = desc_info
+            .recovery_paths()
+            .keys()
+            .map(|csv| descriptors::RecoveryTimelock::Relative(*csv))
+            .chain(
+                desc_info
+                    .abs_recovery_paths()
+                    .keys()
+                    .map(|cltv| descriptors::RecoveryTimelock::Absolute(*cltv)),
+            )
+            .collect();
+        let tip_height = self.bitcoin.chain_tip().height;
+        let avg_block_interval_secs = self.average_block_interval(tip_height);
+        // The time of the block at this height, projected if it isn't mined yet.
+        let tip_time = self.bitcoin.block_time(tip_height);
+        let block_time_at = |height: i32| -> Option<u32> {
+            if height <= tip_height {
+                self.bitcoin.block_time(height)
+            } else {
+                let blocks_left = u32::try_from(height - tip_height).ok()?;
+                tip_time?.checked_add(blocks_left.checked_mul(avg_block_interval_secs)?)
+            }
+        };
+        let horizon_height = i64::from(tip_height) + i64::from(expiring_within);
+        let horizon_time = tip_time
+            .unwrap_or(0)
+            .saturating_add(expiring_within.saturating_mul(avg_block_interval_secs));
+
+        // For a coin and a recovery path, get the height of the first block in which the recovery
+        // path may be used and the time from which it may be used if they are known, and whether
+        // it's already available or will be within the horizon.
+        let path_expiry = |coin: &Coin, timelock: descriptors::RecoveryTimelock| {
+            let (unlock_height, unlock_time) = match timelock {
+                descriptors::RecoveryTimelock::Relative(csv) => {
+                    let height = coin.block_height.map(|h| h + i32::from(csv));
+                    (height, height.and_then(block_time_at))
+                }
+                descriptors::RecoveryTimelock::Absolute(cltv)
+                    if cltv < descriptors::LOCKTIME_THRESHOLD =>
+                {
+                    let height = i32::try_from(cltv).ok().and_then(|h| h.checked_add(1));
+                    (height, height.and_then(block_time_at))
+                }
+                descriptors::RecoveryTimelock::Absolute(cltv) => (None, Some(cltv)),
+            };
+
+            // For a timelock on a timestamp we can only compare the time, from the median time
+            // past for the next block to be mined.
+            let (is_recoverable, is_expiring) = match (unlock_height, timelock) {
+                (Some(height), _) => (
+                    height <= tip_height + 1,
+                    i64::from(height) <= horizon_height,
+                ),
+                (None, descriptors::RecoveryTimelock::Absolute(cltv)) => (
+                    self.bitcoin.tip_median_time_past() > cltv,
+                    cltv < horizon_time,
+                ),
+                // The relative timelock of an unconfirmed coin didn't start yet.
+                (None, descriptors::RecoveryTimelock::Relative(_)) => (false, false),
+            };
+            (unlock_height, unlock_time, is_recoverable, is_expiring)
+        };
+
+        let mut res = ExpiryReportResult {
+            avg_block_interval_secs,
+            recoverable: Vec::new(),
+            expiring: Vec::new(),
+            safe: Vec::new(),
+        };
+        let mut db_conn = self.db.connection();
+        for coin in db_conn.coins(CoinType::Unspent).values() {
+            // Use the recovery path which becomes available first. Heights and timestamps may only
+            // be compared through the (projected) time, so unknown times come last.
+            let (timelock, (unlock_height, unlock_time, is_recoverable, is_expiring)) = timelocks
+                .iter()
+                .map(|timelock| (*timelock, path_expiry(coin, *timelock)))
+                .min_by_key(|(_, (height, time, is_recoverable, is_expiring))| {
+                    (
+                        !is_recoverable,
+                        !is_expiring,
+                        time.unwrap_or(u32::MAX),
+                        height.unwrap_or(i32::MAX),
+                    )
+                })
+                .expect("There is always at least one recovery path");
+            let entry = ExpiryReportEntry {
+                outpoint: coin.outpoint,
+                amount: coin.amount,
+                block_height: coin.block_height,
+                timelock,
+                unlock_height,
+                unlock_time,
+            };
+            if is_recoverable {
+                res.recoverable.push(entry);
+            } else if is_expiring {
+                res.expiring.push(entry);
+            } else {
+                res.safe.push(entry);
+            }
+        }
+
+        // The coins which unlock first come first. Those for which it's unknown last.
+        for entries in &mut [&mut res.recoverable, &mut res.expiring, &mut res.safe] {
+            entries.sort_by_key(|e| {
+                (
+                    e.unlock_time.unwrap_or(u32::MAX),
+                    e.unlock_height.unwrap_or(i32::MAX),
+                    e.outpoint,
+                )
+            });
+        }
+
+        res
+    }
+
+    /// Get the feerates to use for a transaction to confirm within a range of targets.
+    pub fn estimate_fees(&self) -> EstimateFeesResult {
+        let estimates = FEE_ESTIMATES_TARGETS
+            .iter()
+            .map(|conf_target| FeeEstimate {
+                conf_target: *conf_target,
+                feerate: self.bitcoin.estimate_feerate(*conf_target),
+            })
+            .collect();
+        EstimateFeesResult { estimates }
+    }
+
+    /// Get the feerate, in sats/vb, to use for a transaction to confirm within this number of
+    /// blocks.
+    pub fn feerate_for_target(&self, conf_target: u16) -> Result<u64, CommandError> {
+        if !(1..=MAX_CONF_TARGET).contains(&conf_target) {
+            return Err(CommandError::InvalidConfTarget(conf_target));
+        }
+        self.bitcoin
+            .estimate_feerate(conf_target)
+            .ok_or(CommandError::FeeEstimateUnavailable(conf_target))
+    }
+
     pub fn create_spend(
         &self,
-        destinations: &HashMap<bitcoin::Address, u64>,
+        destinations: &[(bitcoin::Address, u64)],
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        remainder_address: Option<&bitcoin::Address>,
+        spend_frozen: bool,
+    ) -> Result<CreateSpendResult, CommandError> {
+        self.create_spend_through(
+            destinations,
+            coins_outpoints,
+            feerate_vb,
+            remainder_address,
+            spend_frozen,
+            None,
+        )
+    }
+
+    /// Create a transaction spending the given coins through the recovery path with this timelock
+    /// (by default the one used by `create_recovery`) to the given destinations, with a change
+    /// output if needed. The recovery path must be available at the next block for all the coins.
+    pub fn create_recovery_spend(
+        &self,
+        destinations: &[(bitcoin::Address, u64)],
         coins_outpoints: &[bitcoin::OutPoint],
         feerate_vb: u64,
+        timelock: Option<descriptors::RecoveryTimelock>,
     ) -> Result<CreateSpendResult, CommandError> {
         if coins_outpoints.is_empty() {
             return Err(CommandError::NoOutpoint);
         }
-        if destinations.is_empty() {
+        let timelock = self.recovery_timelock(timelock)?;
+
+        self.create_spend_through(
+            destinations,
+            coins_outpoints,
+            feerate_vb,
+            None,
+            false,
+            Some(timelock),
+        )
+    }
+
+    // Create a transaction spending coins through the primary path, or through the recovery path
+    // with the given timelock.
+    fn create_spend_through(
+        &self,
+        destinations: &[(bitcoin::Address, u64)],
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+        remainder_address: Option<&bitcoin::Address>,
+        spend_frozen: bool,
+        recovery_timelock: Option<descriptors::RecoveryTimelock>,
+    ) -> Result<CreateSpendResult, CommandError> {
+        if destinations.is_empty() && remainder_address.is_none() {
             return Err(CommandError::NoDestination);
         }
         if feerate_vb < 1 {
@@ -300,58 +1065,20 @@ impl DaemonControl {
         }
         let mut db_conn = self.db.connection();
 
-        // Iterate through given outpoints to fetch the coins (hence checking their existence
-        // at the same time). We checked there is at least one, therefore after this loop the
-        // list of coins is not empty.
-        // While doing so, we record the total input value of the transaction to later compute
-        // fees, and add necessary information to the PSBT inputs.
-        let mut in_value = bitcoin::Amount::from_sat(0);
-        let txin_sat_vb = self.config.main_descriptor.max_sat_vbytes();
-        let mut sat_vb = 0;
-        let mut txins = Vec::with_capacity(coins_outpoints.len());
-        let mut psbt_ins = Vec::with_capacity(coins_outpoints.len());
-        let mut spent_txs = HashMap::with_capacity(coins_outpoints.len());
-        let coins = db_conn.coins_by_outpoints(coins_outpoints);
-        for op in coins_outpoints {
-            // Get the coin from our in-DB unspent txos
-            let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
-            if coin.is_spent() {
-                return Err(CommandError::AlreadySpent(*op));
-            }
-            // Fetch the transaction that created it if necessary
-            if !spent_txs.contains_key(op) {
-                let tx = self
-                    .bitcoin
-                    .wallet_transaction(&op.txid)
-                    .ok_or(CommandError::FetchingTransaction(*op))?;
-                spent_txs.insert(*op, tx.0);
+        let main_desc = &self.config.main_descriptor;
+        let desc_info = main_desc.info();
+        let spend_path = match recovery_timelock {
+            Some(timelock) => desc_info
+                .recovery_path(timelock)
+                .expect("Checked by the caller"),
+            None => desc_info.primary_path(),
+        };
+        let txin_sat_vb = main_desc.path_sat_vbytes(spend_path);
+        let tip_height = self.bitcoin.chain_tip().height;
+        if let Some(descriptors::RecoveryTimelock::Absolute(cltv_value)) = recovery_timelock {
+            if !self.is_abs_recovery_available(cltv_value, tip_height) {
+                return Err(CommandError::RecoveryNotAvailable);
             }
-
-            in_value += coin.amount;
-            txins.push(bitcoin::TxIn {
-                previous_output: *op,
-                sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
-                // TODO: once we move to Taproot, anti-fee-sniping using nSequence
-                ..bitcoin::TxIn::default()
-            });
-
-            // Populate the PSBT input with the information needed by signers.
-            let coin_desc = self.derived_desc(coin);
-            sat_vb += txin_sat_vb;
-            let witness_script = Some(coin_desc.witness_script());
-            let witness_utxo = Some(bitcoin::TxOut {
-                value: coin.amount.to_sat(),
-                script_pubkey: coin_desc.script_pubkey(),
-            });
-            let non_witness_utxo = spent_txs.get(op).cloned();
-            let bip32_derivation = coin_desc.bip32_derivations();
-            psbt_ins.push(PsbtIn {
-                witness_script,
-                witness_utxo,
-                bip32_derivation,
-                non_witness_utxo,
-                ..PsbtIn::default()
-            });
         }
 
         // Add the destinations outputs to the transaction and PSBT. At the same time record the
@@ -370,91 +1097,227 @@ impl DaemonControl {
                 value: amount.to_sat(),
                 script_pubkey: address.script_pubkey(),
             });
-            // If it's an address of ours, signal it as change to signing devices by adding the
-            // BIP32 derivation path to the PSBT output.
-            let bip32_derivation =
-                if let Some((index, is_change)) = db_conn.derivation_index_by_address(address) {
-                    let desc = if is_change {
-                        self.config.main_descriptor.change_descriptor()
-                    } else {
-                        self.config.main_descriptor.receive_descriptor()
-                    };
-                    desc.derive(index, &self.secp).bip32_derivations()
-                } else {
-                    Default::default()
-                };
-            psbt_outs.push(PsbtOut {
-                bip32_derivation,
-                ..PsbtOut::default()
+            psbt_outs.push(self.destination_psbt_out(db_conn.as_mut(), address));
+        }
+        // The output receiving the remainder, if any, is added last. Its value is only known once
+        // the transaction is complete.
+        if let Some(address) = remainder_address {
+            self.validate_address(address)?;
+            txouts.push(bitcoin::TxOut {
+                value: std::u64::MAX,
+                script_pubkey: address.script_pubkey(),
             });
+            psbt_outs.push(self.destination_psbt_out(db_conn.as_mut(), address));
         }
 
-        // Now create the transaction, compute its fees and already sanity check if its feerate
-        // isn't much less than what was asked (and obviously that fees aren't negative).
-        let mut tx = bitcoin::Transaction {
-            version: 2,
-            lock_time: bitcoin::PackedLockTime(0), // TODO: randomized anti fee sniping
-            input: txins,
-            output: txouts,
-        };
-        let nochange_vb = (tx.vsize() + sat_vb) as u64;
-        let absolute_fee =
-            in_value
-                .checked_sub(out_value)
-                .ok_or(CommandError::InsufficientFunds(
-                    in_value, out_value, feerate_vb,
-                ))?;
-        let nochange_feerate_vb = absolute_fee.to_sat().checked_div(nochange_vb).unwrap();
-        if nochange_feerate_vb.checked_mul(10).unwrap() < feerate_vb.checked_mul(9).unwrap() {
-            return Err(CommandError::InsufficientFunds(
-                in_value, out_value, feerate_vb,
-            ));
-        }
+        // If no coin was given, select them ourselves among our confirmed coins which aren't
+        // frozen nor already spent by one of the stored Spend transactions.
+        let coins_outpoints = if coins_outpoints.is_empty() {
+            let drafts_spent: HashSet<bitcoin::OutPoint> = db_conn
+                .list_spend()
+                .into_iter()
+                .flat_map(|psbt| psbt.unsigned_tx.input.into_iter())
+                .map(|txin| txin.previous_output)
+                .collect();
+            let candidates: Vec<(bitcoin::OutPoint, u64)> = db_conn
+                .coins(CoinType::Unspent)
+                .values()
+                .filter(|c| c.is_confirmed() && !c.is_frozen && !drafts_spent.contains(&c.outpoint))
+                .map(|c| (c.outpoint, c.amount.to_sat()))
+                .collect();
 
-        // If necessary, add a change output. The computation here is a bit convoluted: we infer
-        // the needed change value from the target feerate and the size of the transaction *with
-        // an added output* (for the change).
-        if nochange_feerate_vb > feerate_vb {
-            // Get the change address to create a dummy change txo.
-            let change_index = db_conn.change_index();
-            let change_desc = self
-                .config
-                .main_descriptor
-                .change_descriptor()
-                .derive(change_index, &self.secp);
-            // Don't forget to update our next change index!
-            let next_index = change_index
-                .increment()
-                .expect("Must not get into hardened territory");
-            db_conn.set_change_index(next_index, &self.secp);
-            let mut change_txo = bitcoin::TxOut {
+            // The size of the transaction without any input, and of a change output.
+            let base_vb = bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(0),
+                input: Vec::new(),
+                output: txouts.clone(),
+            }
+            .vsize() as u64;
+            let change_vb: u64 = serializable_size(&bitcoin::TxOut {
                 value: std::u64::MAX,
-                script_pubkey: change_desc.script_pubkey(),
+                script_pubkey: main_desc
+                    .change_descriptor()
+                    .derive(db_conn.change_index(), &self.secp)
+                    .script_pubkey(),
+            });
+            // Outpoint, nSequence, empty scriptSig and its length.
+            let txin_vb = (32 + 4 + 4 + 1 + txin_sat_vb) as u64;
+            let params = coin_selection::SelectionParams {
+                target: out_value
+                    .to_sat()
+                    .checked_add(base_vb.checked_mul(feerate_vb).unwrap())
+                    .unwrap(),
+                input_fee: txin_vb.checked_mul(feerate_vb).unwrap(),
+                change_fee: change_vb.checked_mul(feerate_vb).unwrap(),
+                cost_of_change: (change_vb + txin_vb).checked_mul(feerate_vb).unwrap(),
+                min_change: DUST_OUTPUT_SATS,
             };
-            // Serialized size is equal to the virtual size for an output.
-            let change_vb: u64 = serializable_size(&change_txo);
-            // We assume the added output does not increase the size of the varint for
-            // the output count.
-            let with_change_vb = nochange_vb.checked_add(change_vb).unwrap();
-            let with_change_feerate_vb = absolute_fee.to_sat().checked_div(with_change_vb).unwrap();
-
-            if with_change_feerate_vb > feerate_vb {
-                let target_fee = with_change_vb.checked_mul(feerate_vb).unwrap();
-                let change_amount = absolute_fee
-                    .checked_sub(bitcoin::Amount::from_sat(target_fee))
-                    .unwrap();
-                if change_amount.to_sat() >= DUST_OUTPUT_SATS {
-                    check_output_value(change_amount)?;
-
-                    // TODO: shuffle once we have Taproot
-                    change_txo.value = change_amount.to_sat();
-                    tx.output.push(change_txo);
-                    psbt_outs.push(PsbtOut {
-                        bip32_derivation: change_desc.bip32_derivations(),
-                        ..PsbtOut::default()
-                    });
-                }
-            }
+            let selection = if remainder_address.is_some() {
+                // When sending the remainder, sweep all the available coins.
+                Some(candidates.iter().map(|(op, _)| *op).collect::<Vec<_>>())
+                    .filter(|ops| !ops.is_empty())
+            } else {
+                coin_selection::select_coins(&candidates, &params)
+            };
+            selection.ok_or_else(|| {
+                let available = candidates.iter().map(|(_, value)| value).sum();
+                CommandError::InsufficientFunds(
+                    bitcoin::Amount::from_sat(available),
+                    out_value,
+                    feerate_vb,
+                )
+            })?
+        } else {
+            coins_outpoints.to_vec()
+        };
+
+        // Iterate through given outpoints to fetch the coins (hence checking their existence
+        // at the same time). There is at least one, therefore after this loop the list of coins
+        // is not empty.
+        // While doing so, we record the total input value of the transaction to later compute
+        // fees, and add necessary information to the PSBT inputs.
+        let mut in_value = bitcoin::Amount::from_sat(0);
+        let mut sat_vb = 0;
+        let mut txins = Vec::with_capacity(coins_outpoints.len());
+        let mut psbt_ins = Vec::with_capacity(coins_outpoints.len());
+        let mut coins_heights = Vec::with_capacity(coins_outpoints.len());
+        let coins = db_conn.coins_by_outpoints(&coins_outpoints);
+        for op in &coins_outpoints {
+            // Get the coin from our in-DB unspent txos
+            let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+            if coin.is_spent() {
+                return Err(CommandError::AlreadySpent(*op));
+            }
+            if coin.is_frozen && !spend_frozen {
+                return Err(CommandError::FrozenCoin(*op));
+            }
+            // Through a recovery path with a relative timelock, the coin must have been confirmed
+            // long enough ago for the transaction to be included in the next block.
+            let sequence = match recovery_timelock {
+                Some(descriptors::RecoveryTimelock::Relative(csv_value)) => {
+                    if !coin
+                        .block_height
+                        .map(|h| tip_height + 1 >= h + i32::from(csv_value))
+                        .unwrap_or(false)
+                    {
+                        return Err(CommandError::RecoveryNotMatured(*op));
+                    }
+                    bitcoin::Sequence::from_height(csv_value)
+                }
+                _ => bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+            };
+
+            in_value += coin.amount;
+            txins.push(bitcoin::TxIn {
+                previous_output: *op,
+                sequence,
+                ..bitcoin::TxIn::default()
+            });
+            coins_heights.push(coin.block_height);
+            sat_vb += txin_sat_vb;
+            psbt_ins.push(self.coin_psbt_in(coin)?);
+        }
+
+        // Now create the transaction, compute its fees and already sanity check if its feerate
+        // isn't less than what was asked (and obviously that fees aren't negative).
+        let mut tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: txins,
+            output: txouts,
+        };
+        // The nSequence of the inputs is already used by a relative timelock, and the nLockTime
+        // by an absolute one.
+        match recovery_timelock {
+            None => {
+                set_anti_fee_sniping(&mut tx, &coins_heights, tip_height, main_desc.is_taproot())
+            }
+            Some(descriptors::RecoveryTimelock::Relative(_)) => {
+                tx.lock_time = anti_fee_sniping_locktime(tip_height)
+            }
+            Some(descriptors::RecoveryTimelock::Absolute(cltv_value)) => {
+                tx.lock_time = abs_recovery_locktime(cltv_value, tip_height)
+            }
+        }
+        let nochange_vb = (tx.vsize() + sat_vb) as u64;
+
+        // If an output receives the remainder, its value is whatever is left after paying for the
+        // other outputs and the fees at the target feerate. There is no change output in this case.
+        if remainder_address.is_some() {
+            let target_fee =
+                bitcoin::Amount::from_sat(nochange_vb.checked_mul(feerate_vb).unwrap());
+            let remainder = in_value
+                .checked_sub(out_value)
+                .and_then(|v| v.checked_sub(target_fee))
+                .filter(|v| v.to_sat() >= DUST_OUTPUT_SATS)
+                .ok_or(CommandError::InsufficientFunds(
+                    in_value, out_value, feerate_vb,
+                ))?;
+            check_output_value(remainder)?;
+            tx.output
+                .last_mut()
+                .expect("The remainder output was added last")
+                .value = remainder.to_sat();
+        } else {
+            let absolute_fee =
+                in_value
+                    .checked_sub(out_value)
+                    .ok_or(CommandError::InsufficientFunds(
+                        in_value, out_value, feerate_vb,
+                    ))?;
+            let nochange_feerate_vb = absolute_fee.to_sat().checked_div(nochange_vb).unwrap();
+            if nochange_feerate_vb < feerate_vb {
+                return Err(CommandError::InsufficientFunds(
+                    in_value, out_value, feerate_vb,
+                ));
+            }
+
+            // If necessary, add a change output. The computation here is a bit convoluted: we infer
+            // the needed change value from the target feerate and the size of the transaction *with
+            // an added output* (for the change).
+            if nochange_feerate_vb > feerate_vb {
+                // Get the change address to create a dummy change txo.
+                let change_index = db_conn.change_index();
+                let change_desc = self
+                    .config
+                    .main_descriptor
+                    .change_descriptor()
+                    .derive(change_index, &self.secp);
+                // Don't forget to update our next change index!
+                let next_index = change_index
+                    .increment()
+                    .expect("Must not get into hardened territory");
+                db_conn.set_change_index(next_index, &self.secp);
+                let mut change_txo = bitcoin::TxOut {
+                    value: std::u64::MAX,
+                    script_pubkey: change_desc.script_pubkey(),
+                };
+                // Serialized size is equal to the virtual size for an output.
+                let change_vb: u64 = serializable_size(&change_txo);
+                // We assume the added output does not increase the size of the varint for
+                // the output count.
+                let with_change_vb = nochange_vb.checked_add(change_vb).unwrap();
+                let with_change_feerate_vb =
+                    absolute_fee.to_sat().checked_div(with_change_vb).unwrap();
+
+                if with_change_feerate_vb > feerate_vb {
+                    let target_fee = with_change_vb.checked_mul(feerate_vb).unwrap();
+                    let change_amount = absolute_fee
+                        .checked_sub(bitcoin::Amount::from_sat(target_fee))
+                        .unwrap();
+                    if change_amount.to_sat() >= DUST_OUTPUT_SATS {
+                        check_output_value(change_amount)?;
+
+                        // TODO: shuffle once we have Taproot
+                        change_txo.value = change_amount.to_sat();
+                        tx.output.push(change_txo);
+                        let mut psbt_out = PsbtOut::default();
+                        change_desc.update_psbt_out(&mut psbt_out);
+                        psbt_outs.push(psbt_out);
+                    }
+                }
+            }
         }
 
         let psbt = Psbt {
@@ -466,18 +1329,94 @@ impl DaemonControl {
             inputs: psbt_ins,
             outputs: psbt_outs,
         };
-        sanity_check_psbt(&psbt)?;
+        sanity_check_psbt(&psbt, tip_height)?;
         // TODO: maybe check for common standardness rules (max size, ..)?
 
-        Ok(CreateSpendResult { psbt })
+        Ok(CreateSpendResult {
+            psbt,
+            coins: coins_outpoints,
+        })
+    }
+
+    /// Create a transaction spending coins back to a fresh change address through the primary
+    /// path, restarting the relative timelock of their recovery path. The coins are either the
+    /// given ones, or all those whose first relative timelock expires within `expiring_within`
+    /// blocks. The transaction is stored as a Spend draft.
+    pub fn create_refresh(
+        &self,
+        coins_outpoints: &[bitcoin::OutPoint],
+        expiring_within: Option<u32>,
+        feerate_vb: u64,
+    ) -> Result<CreateRefreshResult, CommandError> {
+        let mut db_conn = self.db.connection();
+
+        // If no coin was given, refresh all our confirmed coins which aren't frozen nor already
+        // spent by one of the stored Spend transactions and whose recovery path will be available
+        // within the given number of blocks (or already is).
+        let coins_outpoints = if coins_outpoints.is_empty() {
+            let expiring_within = expiring_within.ok_or(CommandError::NoOutpoint)?;
+            let drafts_spent: HashSet<bitcoin::OutPoint> = db_conn
+                .list_spend()
+                .into_iter()
+                .flat_map(|psbt| psbt.unsigned_tx.input.into_iter())
+                .map(|txin| txin.previous_output)
+                .collect();
+            let tip_height = i64::from(self.bitcoin.chain_tip().height);
+            let timelock = self.config.main_descriptor.first_timelock_value();
+            let mut outpoints: Vec<bitcoin::OutPoint> = db_conn
+                .coins(CoinType::Unspent)
+                .values()
+                .filter(|c| !c.is_frozen && !drafts_spent.contains(&c.outpoint))
+                .filter(|c| match (c.block_height, timelock) {
+                    (Some(height), Some(timelock)) => {
+                        i64::from(height) + i64::from(timelock)
+                            <= tip_height + i64::from(expiring_within)
+                    }
+                    _ => false,
+                })
+                .map(|c| c.outpoint)
+                .collect();
+            if outpoints.is_empty() {
+                return Err(CommandError::NoExpiringCoin(expiring_within));
+            }
+            outpoints.sort();
+            outpoints
+        } else {
+            coins_outpoints.to_vec()
+        };
+
+        // Consolidate the coins into a fresh change address.
+        let change_index = db_conn.change_index();
+        let change_address = self
+            .config
+            .main_descriptor
+            .change_descriptor()
+            .derive(change_index, &self.secp)
+            .address(self.config.bitcoin_config.network);
+        let CreateSpendResult { psbt, coins } = self.create_spend(
+            &[],
+            &coins_outpoints,
+            feerate_vb,
+            Some(&change_address),
+            false,
+        )?;
+
+        // Don't forget to update our next change index!
+        let next_index = change_index
+            .increment()
+            .expect("Must not get into hardened territory");
+        db_conn.set_change_index(next_index, &self.secp);
+        db_conn.store_spend(&psbt);
+
+        Ok(CreateRefreshResult { psbt, coins })
     }
 
     pub fn update_spend(&self, mut psbt: Psbt) -> Result<(), CommandError> {
         let mut db_conn = self.db.connection();
         let tx = &psbt.unsigned_tx;
 
-        // If the transaction already exists in DB, merge the signatures for each input on a best
-        // effort basis.
+        // If the transaction already exists in DB, merge the signatures and hash preimages for each
+        // input on a best effort basis.
         // We work on the newly provided PSBT, in case its content was updated.
         let txid = tx.txid();
         if let Some(db_psbt) = db_conn.spend_tx(&txid) {
@@ -502,6 +1441,15 @@ impl DaemonControl {
                 psbtin
                     .partial_sigs
                     .extend(db_psbtin.partial_sigs.clone().into_iter());
+                psbtin
+                    .tap_script_sigs
+                    .extend(db_psbtin.tap_script_sigs.clone().into_iter());
+                if psbtin.tap_key_sig.is_none() {
+                    psbtin.tap_key_sig = db_psbtin.tap_key_sig;
+                }
+                psbtin
+                    .sha256_preimages
+                    .extend(db_psbtin.sha256_preimages.clone().into_iter());
             }
         } else {
             // If the transaction doesn't exist in DB already, sanity check its inputs.
@@ -524,12 +1472,120 @@ impl DaemonControl {
         Ok(())
     }
 
+    /// Analyze a PSBT, which may have been created by another wallet, against our descriptor:
+    /// which inputs spend our coins and through which spending paths, what signatures are present
+    /// or missing, which outputs pay to us and what fees are paid.
+    pub fn analyze_psbt(&self, psbt: &Psbt) -> Result<AnalyzePsbtResult, CommandError> {
+        let tx = &psbt.unsigned_tx;
+        if psbt.inputs.len() != tx.input.len() || psbt.outputs.len() != tx.output.len() {
+            return Err(CommandError::MalformedPsbt);
+        }
+        let mut db_conn = self.db.connection();
+        let main_desc = &self.config.main_descriptor;
+        let desc_info = main_desc.info();
+
+        // Analyze each input. Only the inputs spending one of our coins are analyzed against
+        // our descriptor. We can only compute the fee if we know the value of all inputs, and
+        // only estimate the feerate if we know the satisfaction size of all inputs. For the
+        // latter we assume the most expensive of the available paths is used.
+        let outpoints: Vec<bitcoin::OutPoint> =
+            tx.input.iter().map(|txin| txin.previous_output).collect();
+        let coins = db_conn.coins_by_outpoints(&outpoints);
+        let mut in_value = Some(bitcoin::Amount::from_sat(0));
+        let mut sat_vb = Some(0);
+        let mut inputs = Vec::with_capacity(tx.input.len());
+        let mut our_psbt = psbt.clone();
+        our_psbt.unsigned_tx.input.clear();
+        our_psbt.inputs.clear();
+        for (txin, psbt_in) in tx.input.iter().zip(psbt.inputs.iter()) {
+            let coin = coins.get(&txin.previous_output);
+            let amount = coin
+                .map(|coin| coin.amount)
+                .or_else(|| psbt_in_value(psbt_in, txin));
+            in_value = in_value.and_then(|v| v.checked_add(amount?));
+
+            let paths = if coin.is_some() {
+                let spend_info = main_desc.partial_spend_info_txin(psbt_in, txin, tx.lock_time);
+                let paths = analyze_paths(main_desc, &desc_info, &spend_info);
+                let txin_sat_vb = paths.iter().map(|(_, vb)| *vb).max();
+                sat_vb = sat_vb.and_then(|vb| Some(vb + txin_sat_vb?));
+                our_psbt.unsigned_tx.input.push(txin.clone());
+                our_psbt.inputs.push(psbt_in.clone());
+                paths.into_iter().map(|(path, _)| path).collect()
+            } else {
+                sat_vb = None;
+                Vec::new()
+            };
+
+            inputs.push(AnalyzedInput {
+                outpoint: txin.previous_output,
+                amount,
+                is_mine: coin.is_some(),
+                paths,
+            });
+        }
+
+        // The spend info must be the same across all our inputs.
+        let inconsistency = if our_psbt.inputs.is_empty() {
+            None
+        } else {
+            main_desc
+                .partial_spend_info(&our_psbt)
+                .err()
+                .map(|e| e.to_string())
+        };
+
+        // Analyze each output, recording whether it pays to one of our addresses.
+        let network = self.config.bitcoin_config.network;
+        let mut out_value = bitcoin::Amount::from_sat(0);
+        let mut outputs = Vec::with_capacity(tx.output.len());
+        for txo in tx.output.iter() {
+            let amount = bitcoin::Amount::from_sat(txo.value);
+            out_value = out_value.checked_add(amount).unwrap();
+            let address = bitcoin::Address::from_script(&txo.script_pubkey, network).ok();
+            let (is_mine, is_change) = address
+                .as_ref()
+                .and_then(|addr| db_conn.derivation_index_by_address(addr))
+                .map(|(_, is_change)| (true, is_change))
+                .unwrap_or((false, false));
+            outputs.push(AnalyzedOutput {
+                address,
+                amount,
+                is_mine,
+                is_change,
+            });
+        }
+
+        let fee = in_value.and_then(|v| v.checked_sub(out_value));
+        let feerate_vb = fee.and_then(|fee| {
+            let tx_vb = (tx.vsize() + sat_vb?) as u64;
+            fee.to_sat().checked_div(tx_vb)
+        });
+
+        Ok(AnalyzePsbtResult {
+            inputs,
+            outputs,
+            fee,
+            feerate_vb,
+            inconsistency,
+        })
+    }
+
     pub fn list_spend(&self) -> ListSpendResult {
         let mut db_conn = self.db.connection();
-        let spend_txs = db_conn
-            .list_spend()
+        let psbts = db_conn.list_spend();
+        let mut labels = db_conn.labels(
+            &psbts
+                .iter()
+                .map(|psbt| LabelItem::Txid(psbt.unsigned_tx.txid()))
+                .collect(),
+        );
+        let spend_txs = psbts
             .into_iter()
-            .map(|psbt| ListSpendEntry { psbt })
+            .map(|psbt| {
+                let label = labels.remove(&LabelItem::Txid(psbt.unsigned_tx.txid()));
+                ListSpendEntry { psbt, label }
+            })
             .collect();
         ListSpendResult { spend_txs }
     }
@@ -548,6 +1604,9 @@ impl DaemonControl {
         let mut spend_psbt = db_conn
             .spend_tx(txid)
             .ok_or(CommandError::UnknownSpend(*txid))?;
+        if let Some(replacement_txid) = db_conn.spend_replacement(txid) {
+            return Err(CommandError::ReplacedSpend(*txid, replacement_txid));
+        }
         spend_psbt.finalize_mut(&self.secp).map_err(|e| {
             CommandError::SpendFinalization(
                 e.into_iter()
@@ -565,6 +1624,337 @@ impl DaemonControl {
             .map_err(CommandError::TxBroadcast)
     }
 
+    /// Create a transaction replacing this unconfirmed Spend transaction (as per BIP125) at a
+    /// higher feerate, and store it. The fees are taken from the change output first, and new
+    /// coins are added if necessary. The replaced transaction is marked as such and won't be
+    /// broadcast anymore.
+    pub fn bump_fee(
+        &self,
+        txid: &bitcoin::Txid,
+        feerate_vb: u64,
+    ) -> Result<BumpFeeResult, CommandError> {
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        let mut db_conn = self.db.connection();
+        let prev_psbt = db_conn
+            .spend_tx(txid)
+            .ok_or(CommandError::UnknownSpend(*txid))?;
+        if let Some(replacement_txid) = db_conn.spend_replacement(txid) {
+            return Err(CommandError::ReplacedSpend(*txid, replacement_txid));
+        }
+
+        // We can only replace a transaction which is pending, that is for which all the coins
+        // are marked as spent by it but not confirmed yet.
+        let prev_tx = &prev_psbt.unsigned_tx;
+        let prev_outpoints: Vec<bitcoin::OutPoint> = prev_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect();
+        let prev_coins = db_conn.coins_by_outpoints(&prev_outpoints);
+        if prev_coins.len() != prev_outpoints.len()
+            || prev_coins
+                .values()
+                .any(|c| c.spend_txid != Some(*txid) || c.spend_block.is_some())
+        {
+            return Err(CommandError::NotReplaceable(*txid));
+        }
+
+        // The replacement must pay a higher feerate than the transaction it replaces. Both are
+        // sized for the spending path the replaced transaction uses.
+        let main_desc = &self.config.main_descriptor;
+        let desc_info = main_desc.info();
+        let recovery_timelock = self.spent_recovery_timelock(prev_tx);
+        let spend_path = match recovery_timelock {
+            Some(timelock) => desc_info
+                .recovery_path(timelock)
+                .expect("Recognized from our descriptor"),
+            None => desc_info.primary_path(),
+        };
+        let txin_sat_vb = main_desc.path_sat_vbytes(spend_path) as u64;
+        let tip_height = self.bitcoin.chain_tip().height;
+        let mut in_value: bitcoin::Amount = prev_coins.values().map(|c| c.amount).sum();
+        let prev_out_value: u64 = prev_tx.output.iter().map(|txo| txo.value).sum();
+        let prev_fee = in_value.to_sat().checked_sub(prev_out_value).unwrap();
+        let prev_vb = prev_tx.vsize() as u64 + txin_sat_vb * prev_tx.input.len() as u64;
+        let prev_feerate_vb = prev_fee.checked_div(prev_vb).unwrap();
+        if feerate_vb <= prev_feerate_vb {
+            return Err(CommandError::ReplacementFeerate(
+                feerate_vb,
+                prev_feerate_vb,
+            ));
+        }
+        // It must also pay for its own relay on top of the fees of the replaced transaction.
+        let min_fee = |vb: u64| {
+            cmp::max(
+                vb.checked_mul(feerate_vb).unwrap(),
+                prev_fee
+                    .checked_add(vb.checked_mul(INCREMENTAL_RELAY_FEERATE).unwrap())
+                    .unwrap(),
+            )
+        };
+
+        // Re-create the inputs, as the signatures for the replaced transaction are invalid for
+        // the replacement. Set aside our change output, if any, as its value is recomputed below.
+        let mut tx = prev_tx.clone();
+        let mut psbt_ins = Vec::with_capacity(tx.input.len());
+        for op in &prev_outpoints {
+            psbt_ins.push(self.coin_psbt_in(&prev_coins[op])?);
+        }
+        let mut psbt_outs = prev_psbt.outputs.clone();
+        // We recognize our change output from the derivation information we set on the PSBT
+        // output when creating the transaction.
+        let change_desc = main_desc.change_descriptor();
+        let change_position = tx
+            .output
+            .iter()
+            .zip(psbt_outs.iter())
+            .position(|(txo, psbt_out)| {
+                psbt_out
+                    .bip32_derivation
+                    .values()
+                    .map(|(_, path)| path)
+                    .chain(psbt_out.tap_key_origins.values().map(|(_, (_, path))| path))
+                    .filter_map(|path| path.as_ref().last().filter(|index| index.is_normal()))
+                    .any(|index| {
+                        change_desc.derive(*index, &self.secp).script_pubkey() == txo.script_pubkey
+                    })
+            });
+        let prev_change = change_position.map(|i| (tx.output.remove(i), psbt_outs.remove(i)));
+        let out_value = bitcoin::Amount::from_sat(tx.output.iter().map(|txo| txo.value).sum());
+
+        // The coins we may add if the existing inputs aren't sufficient, largest first. Frozen
+        // coins are never added. Through a recovery path with a relative timelock, the added
+        // inputs use the same nSequence as the existing ones so the coins must be mature.
+        let (added_sequence, min_confs) = match recovery_timelock {
+            Some(descriptors::RecoveryTimelock::Relative(csv)) => {
+                (bitcoin::Sequence::from_height(csv), i32::from(csv))
+            }
+            _ => (bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME, 1),
+        };
+        let drafts_spent: HashSet<bitcoin::OutPoint> = db_conn
+            .list_spend()
+            .into_iter()
+            .flat_map(|psbt| psbt.unsigned_tx.input.into_iter())
+            .map(|txin| txin.previous_output)
+            .collect();
+        let mut candidates: Vec<Coin> = db_conn
+            .coins(CoinType::Unspent)
+            .values()
+            .filter(|c| {
+                c.block_height
+                    .map(|h| tip_height + 1 >= h + min_confs)
+                    .unwrap_or(false)
+                    && !c.is_frozen
+                    && !drafts_spent.contains(&c.outpoint)
+            })
+            .copied()
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.amount.cmp(&a.amount));
+        let mut candidates = candidates.into_iter();
+
+        // Reuse the change output of the replaced transaction if there was one, otherwise get a
+        // new change address in case we need one.
+        let (mut change_txo, change_psbt_out) = match prev_change {
+            Some(change) => change,
+            None => {
+                let change_desc = change_desc.derive(db_conn.change_index(), &self.secp);
+                let mut psbt_out = PsbtOut::default();
+                change_desc.update_psbt_out(&mut psbt_out);
+                let txo = bitcoin::TxOut {
+                    value: std::u64::MAX,
+                    script_pubkey: change_desc.script_pubkey(),
+                };
+                (txo, psbt_out)
+            }
+        };
+        // Serialized size is equal to the virtual size for an output.
+        let change_vb: u64 = serializable_size(&change_txo);
+
+        // Add coins until we can pay for the replacement, with a change output if possible.
+        loop {
+            let available = in_value.to_sat().checked_sub(out_value.to_sat()).unwrap();
+            let nochange_vb = tx.vsize() as u64 + txin_sat_vb * tx.input.len() as u64;
+            let with_change_fee = min_fee(nochange_vb + change_vb);
+            if available >= with_change_fee + DUST_OUTPUT_SATS {
+                change_txo.value = available - with_change_fee;
+                check_output_value(bitcoin::Amount::from_sat(change_txo.value))?;
+                // If it's a new change address, don't forget to update our next change index!
+                if change_position.is_none() {
+                    let next_index = db_conn
+                        .change_index()
+                        .increment()
+                        .expect("Must not get into hardened territory");
+                    db_conn.set_change_index(next_index, &self.secp);
+                }
+                tx.output.push(change_txo);
+                psbt_outs.push(change_psbt_out);
+                break;
+            }
+            if available >= min_fee(nochange_vb) {
+                break;
+            }
+
+            let coin = candidates.next().ok_or(CommandError::InsufficientFunds(
+                in_value, out_value, feerate_vb,
+            ))?;
+            in_value += coin.amount;
+            // If the replaced transaction used the nSequence of its single input to discourage
+            // fee sniping, use the nLockTime instead so all inputs have the same nSequence.
+            if recovery_timelock.is_none() && tx.lock_time == bitcoin::PackedLockTime::ZERO {
+                for txin in tx.input.iter_mut() {
+                    txin.sequence = bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME;
+                }
+                tx.lock_time = anti_fee_sniping_locktime(tip_height);
+            }
+            tx.input.push(bitcoin::TxIn {
+                previous_output: coin.outpoint,
+                sequence: added_sequence,
+                ..bitcoin::TxIn::default()
+            });
+            psbt_ins.push(self.coin_psbt_in(&coin)?);
+        }
+
+        let psbt = Psbt {
+            unsigned_tx: tx,
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            inputs: psbt_ins,
+            outputs: psbt_outs,
+        };
+        sanity_check_psbt(&psbt, tip_height)?;
+        db_conn.replace_spend(txid, &psbt);
+
+        Ok(BumpFeeResult { psbt })
+    }
+
+    /// Create a transaction spending these unconfirmed coins back to a change address, paying
+    /// enough fees for the package formed with their parent transactions to reach the given
+    /// feerate (Child Pays For Parent).
+    pub fn create_cpfp(
+        &self,
+        coins_outpoints: &[bitcoin::OutPoint],
+        feerate_vb: u64,
+    ) -> Result<CreateCpfpResult, CommandError> {
+        if coins_outpoints.is_empty() {
+            return Err(CommandError::NoOutpoint);
+        }
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        let mut db_conn = self.db.connection();
+        let main_desc = &self.config.main_descriptor;
+        let txin_sat_vb = main_desc.path_sat_vbytes(main_desc.info().primary_path()) as u64;
+
+        // Fetch the coins to be spent, and the size and fees of their parents from the mempool.
+        let mut in_value = bitcoin::Amount::from_sat(0);
+        let mut txins = Vec::with_capacity(coins_outpoints.len());
+        let mut psbt_ins = Vec::with_capacity(coins_outpoints.len());
+        let mut parents = HashMap::new();
+        let coins = db_conn.coins_by_outpoints(coins_outpoints);
+        for op in coins_outpoints {
+            let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+            if coin.is_spent() {
+                return Err(CommandError::AlreadySpent(*op));
+            }
+            if coin.is_confirmed() {
+                return Err(CommandError::AlreadyConfirmed(*op));
+            }
+            if coin.is_frozen {
+                return Err(CommandError::FrozenCoin(*op));
+            }
+            if let hash_map::Entry::Vacant(entry) = parents.entry(op.txid) {
+                let mempool_entry = self
+                    .bitcoin
+                    .mempool_entry(&op.txid)
+                    .ok_or(CommandError::NotInMempool(op.txid))?;
+                entry.insert(mempool_entry);
+            }
+
+            in_value += coin.amount;
+            txins.push(bitcoin::TxIn {
+                previous_output: *op,
+                sequence: bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                ..bitcoin::TxIn::default()
+            });
+            psbt_ins.push(self.coin_psbt_in(coin)?);
+        }
+        // The package also contains the unconfirmed ancestors of the parents. We can't tell
+        // whether several parents share ancestors, so we only accept unconfirmed ancestors for
+        // a single parent.
+        if parents.len() > 1 {
+            if let Some(txid) = parents
+                .iter()
+                .find(|(_, entry)| entry.ancestor_count > 1)
+                .map(|(txid, _)| txid)
+            {
+                return Err(CommandError::UnconfirmedAncestors(*txid));
+            }
+        }
+        let parents_vb: u64 = parents.values().map(|entry| entry.ancestor_vsize).sum();
+        let parents_fee: bitcoin::Amount = parents.values().map(|entry| entry.ancestor_fees).sum();
+
+        // Get a change address to send the coins back to.
+        let change_index = db_conn.change_index();
+        let change_desc = main_desc
+            .change_descriptor()
+            .derive(change_index, &self.secp);
+        let tip_height = self.bitcoin.chain_tip().height;
+        let mut tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: anti_fee_sniping_locktime(tip_height),
+            input: txins,
+            output: vec![bitcoin::TxOut {
+                value: std::u64::MAX,
+                script_pubkey: change_desc.script_pubkey(),
+            }],
+        };
+        let mut psbt_out = PsbtOut::default();
+        change_desc.update_psbt_out(&mut psbt_out);
+
+        // The child pays for the whole package to reach the target feerate, and at least for its
+        // own relay.
+        let child_vb = tx.vsize() as u64 + txin_sat_vb * tx.input.len() as u64;
+        let package_fee = parents_vb
+            .checked_add(child_vb)
+            .unwrap()
+            .checked_mul(feerate_vb)
+            .unwrap();
+        let child_fee = cmp::max(package_fee.saturating_sub(parents_fee.to_sat()), child_vb);
+        let change_amount = in_value
+            .checked_sub(bitcoin::Amount::from_sat(child_fee))
+            .filter(|amount| amount.to_sat() >= DUST_OUTPUT_SATS)
+            .ok_or(CommandError::InsufficientFunds(
+                in_value,
+                bitcoin::Amount::from_sat(0),
+                feerate_vb,
+            ))?;
+        check_output_value(change_amount)?;
+        tx.output[0].value = change_amount.to_sat();
+
+        let psbt = Psbt {
+            unsigned_tx: tx,
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            inputs: psbt_ins,
+            outputs: vec![psbt_out],
+        };
+        sanity_check_psbt(&psbt, tip_height)?;
+
+        // Don't forget to update our next change index!
+        let next_index = change_index
+            .increment()
+            .expect("Must not get into hardened territory");
+        db_conn.set_change_index(next_index, &self.secp);
+
+        Ok(CreateCpfpResult { psbt })
+    }
+
     /// Trigger a rescan of the block chain for transactions involving our main descriptor between
     /// the given date and the current tip.
     /// The date must be after the genesis block time and before the current tip blocktime.
@@ -596,27 +1986,14 @@ impl DaemonControl {
         end: u32,
         limit: u64,
     ) -> ListTransactionsResult {
-        let mut db_conn = self.db.connection();
-        let txids = db_conn.list_txids(start, end, limit);
-        let transactions = txids
-            .iter()
-            .filter_map(|txid| {
-                // TODO: batch those calls to the Bitcoin backend
-                // so it can in turn optimize its queries.
-                self.bitcoin
-                    .wallet_transaction(txid)
-                    .map(|(tx, block)| TransactionInfo {
-                        tx,
-                        height: block.map(|b| b.height),
-                        time: block.map(|b| b.time),
-                    })
-            })
-            .collect();
-        ListTransactionsResult { transactions }
+        let txids = self.db.connection().list_txids(start, end, limit);
+        self.list_transactions(&txids)
     }
 
     /// list_transactions retrieves the transactions with the given txids.
     pub fn list_transactions(&self, txids: &[bitcoin::Txid]) -> ListTransactionsResult {
+        let mut db_conn = self.db.connection();
+        let mut labels = db_conn.labels(&txids.iter().map(|txid| LabelItem::Txid(*txid)).collect());
         let transactions = txids
             .iter()
             .filter_map(|txid| {
@@ -628,32 +2005,188 @@ impl DaemonControl {
                         tx,
                         height: block.map(|b| b.height),
                         time: block.map(|b| b.time),
+                        label: labels.remove(&LabelItem::Txid(*txid)),
                     })
             })
             .collect();
         ListTransactionsResult { transactions }
     }
 
-    /// Create a transaction that sweeps all coins whose timelocked recovery path is currently
-    /// available to a provided address with the provided feerate.
-    ///
-    /// Note that not all coins may be spendable through the recovery path at the same time.
-    pub fn create_recovery(
+    /// Set the label of these items, or remove it if the new label is None.
+    pub fn update_labels(
         &self,
-        address: bitcoin::Address,
-        feerate_vb: u64,
-    ) -> Result<CreateRecoveryResult, CommandError> {
-        if feerate_vb < 1 {
-            return Err(CommandError::InvalidFeerate(feerate_vb));
+        items: &HashMap<LabelItem, Option<String>>,
+    ) -> Result<(), CommandError> {
+        for item in items.keys() {
+            if let LabelItem::Address(addr) = item {
+                self.validate_address(addr)?;
+            }
         }
-        self.validate_address(&address)?;
-        let mut db_conn = self.db.connection();
+        self.db.connection().update_labels(items);
+        Ok(())
+    }
 
-        // The transaction template. We'll fill-in the inputs afterward.
-        let mut psbt = Psbt {
-            unsigned_tx: bitcoin::Transaction {
+    /// Get the label of these items, for those which have one.
+    pub fn get_labels(&self, items: &HashSet<LabelItem>) -> GetLabelsResult {
+        let labels = self
+            .db
+            .connection()
+            .labels(items)
+            .into_iter()
+            .map(|(item, label)| (item.to_string(), label))
+            .collect();
+        GetLabelsResult { labels }
+    }
+
+    /// Export all our labels in the BIP-329 format: one JSON record per line.
+    pub fn export_labels(&self) -> ExportLabelsResult {
+        let labels = self
+            .db
+            .connection()
+            .list_labels()
+            .into_iter()
+            .map(|(item, label)| {
+                let record_type = match item {
+                    LabelItem::Address(_) => "addr",
+                    LabelItem::Txid(_) => "tx",
+                    LabelItem::OutPoint(_) => "output",
+                };
+                serde_json::json!({
+                    "type": record_type,
+                    "ref": item.to_string(),
+                    "label": label,
+                })
+                .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        ExportLabelsResult { labels }
+    }
+
+    /// Import labels in the BIP-329 format. Existing labels for the same items are overwritten.
+    /// Records of a type we don't support, or without a label, are ignored.
+    pub fn import_labels(&self, bip329: &str) -> Result<ImportLabelsResult, CommandError> {
+        let mut items = HashMap::new();
+        let mut skipped = 0;
+        for (i, line) in bip329.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line_number = i + 1;
+            let malformed = |e: &str| CommandError::MalformedLabels(line_number, e.to_string());
+
+            let record: serde_json::Value =
+                serde_json::from_str(line).map_err(|e| malformed(&e.to_string()))?;
+            let record_type = record
+                .get("type")
+                .and_then(serde_json::Value::as_str)
+                .ok_or_else(|| malformed("missing 'type'"))?;
+            let reference = record
+                .get("ref")
+                .and_then(serde_json::Value::as_str)
+                .ok_or_else(|| malformed("missing 'ref'"))?;
+            let label = match record.get("label").and_then(serde_json::Value::as_str) {
+                Some(label) if !label.is_empty() => label,
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            let item = match record_type {
+                "tx" => bitcoin::Txid::from_str(reference)
+                    .map(LabelItem::Txid)
+                    .map_err(|_| malformed("invalid txid"))?,
+                "addr" => {
+                    let addr = bitcoin::Address::from_str(reference)
+                        .map_err(|_| malformed("invalid address"))?;
+                    self.validate_address(&addr)?;
+                    LabelItem::Address(addr)
+                }
+                "output" => bitcoin::OutPoint::from_str(reference)
+                    .map(LabelItem::OutPoint)
+                    .map_err(|_| malformed("invalid outpoint"))?,
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            items.insert(item, Some(label.to_string()));
+        }
+
+        let imported = items.len();
+        self.db.connection().update_labels(&items);
+        Ok(ImportLabelsResult { imported, skipped })
+    }
+
+    /// Create a transaction that sweeps all coins whose timelocked recovery path is currently
+    /// available to a provided address with the provided feerate.
+    ///
+    /// Note that not all coins may be spendable through the recovery path at the same time.
+    ///
+    /// The recovery path to use is identified by its timelock. If none is given, the recovery path
+    /// with the lowest relative timelock is used, or the one with the lowest absolute timelock if
+    /// there is no relative timelock.
+    pub fn create_recovery(
+        &self,
+        address: bitcoin::Address,
+        feerate_vb: u64,
+        timelock: Option<descriptors::RecoveryTimelock>,
+    ) -> Result<CreateRecoveryResult, CommandError> {
+        if feerate_vb < 1 {
+            return Err(CommandError::InvalidFeerate(feerate_vb));
+        }
+        self.validate_address(&address)?;
+        let desc_info = self.config.main_descriptor.info();
+        let timelock = self.recovery_timelock(timelock)?;
+        let mut db_conn = self.db.connection();
+
+        // Query the coins that we can spend through the recovery path from the database. For a
+        // relative timelock, it depends on the confirmation height of each coin. For an absolute
+        // timelock, all of them are available as soon as the locktime is reached.
+        // We are interested in coins available at the *next* block.
+        let current_height = self.bitcoin.chain_tip().height;
+        // Frozen coins are never swept.
+        let unspent_coins = db_conn
+            .coins(CoinType::Unspent)
+            .into_iter()
+            .filter(|(_, c)| !c.is_frozen);
+        let (lock_time, sequence, sweepable_coins): (_, _, Vec<Coin>) = match timelock {
+            descriptors::RecoveryTimelock::Relative(csv_value) => {
+                let timelock: i32 = csv_value.into();
+                let coins = unspent_coins
+                    .filter_map(|(_, c)| {
+                        c.block_height
+                            .filter(|h| current_height + 1 >= h + timelock)
+                            .map(|_| c)
+                    })
+                    .collect();
+                (
+                    anti_fee_sniping_locktime(current_height),
+                    bitcoin::Sequence::from_height(csv_value),
+                    coins,
+                )
+            }
+            descriptors::RecoveryTimelock::Absolute(cltv_value) => {
+                if !self.is_abs_recovery_available(cltv_value, current_height) {
+                    return Err(CommandError::RecoveryNotAvailable);
+                }
+                let coins = unspent_coins
+                    .filter_map(|(_, c)| c.block_height.map(|_| c))
+                    .collect();
+                (
+                    abs_recovery_locktime(cltv_value, current_height),
+                    bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    coins,
+                )
+            }
+        };
+
+        // The transaction template. We'll fill-in the inputs afterward.
+        let mut psbt = Psbt {
+            unsigned_tx: bitcoin::Transaction {
                 version: 2,
-                lock_time: bitcoin::PackedLockTime(0), // TODO: anti-fee sniping
+                lock_time,
                 input: Vec::new(),
                 output: vec![bitcoin::TxOut {
                     script_pubkey: address.script_pubkey(),
@@ -668,36 +2201,20 @@ impl DaemonControl {
             outputs: vec![PsbtOut::default()],
         };
 
-        // Query the coins that we can spend through the recovery path from the database.
-        let current_height = self.bitcoin.chain_tip().height;
-        let desc_timelock = self.config.main_descriptor.timelock_value();
-        let timelock: i32 = desc_timelock
-            .try_into()
-            .expect("Must fit, it's effectively a u16");
-        let sweepable_coins = db_conn
-            .coins(CoinType::Unspent)
-            .into_iter()
-            .filter(|(_, c)| {
-                // We are interested in coins available at the *next* block
-                c.block_height
-                    .map(|h| current_height + 1 >= h + timelock)
-                    .unwrap_or(false)
-            });
-
         // Fill-in the transaction inputs and PSBT inputs information. Record the value
         // that is fed to the transaction while doing so, to compute the fees afterward.
-        let csv_value: u16 = desc_timelock
-            .try_into()
-            .expect("Must fit, it's effectively a u16");
         let mut in_value = bitcoin::Amount::from_sat(0);
-        let txin_sat_vb = self.config.main_descriptor.max_sat_vbytes();
+        let recovery_path = desc_info
+            .recovery_path(timelock)
+            .expect("Checked above or taken from the descriptor");
+        let txin_sat_vb = self.config.main_descriptor.path_sat_vbytes(recovery_path);
         let mut sat_vb = 0;
         let mut spent_txs = HashMap::new();
-        for (_, coin) in sweepable_coins {
+        for coin in sweepable_coins {
             in_value += coin.amount;
             psbt.unsigned_tx.input.push(bitcoin::TxIn {
                 previous_output: coin.outpoint,
-                sequence: bitcoin::Sequence::from_height(csv_value),
+                sequence,
                 // TODO: once we move to Taproot, anti-fee-sniping using nSequence
                 ..bitcoin::TxIn::default()
             });
@@ -713,20 +2230,18 @@ impl DaemonControl {
 
             let coin_desc = self.derived_desc(&coin);
             sat_vb += txin_sat_vb;
-            let witness_script = Some(coin_desc.witness_script());
             let witness_utxo = Some(bitcoin::TxOut {
                 value: coin.amount.to_sat(),
                 script_pubkey: coin_desc.script_pubkey(),
             });
             let non_witness_utxo = spent_txs.get(&coin.outpoint).cloned();
-            let bip32_derivation = coin_desc.bip32_derivations();
-            psbt.inputs.push(PsbtIn {
-                witness_script,
+            let mut psbt_in = PsbtIn {
                 witness_utxo,
                 non_witness_utxo,
-                bip32_derivation,
                 ..PsbtIn::default()
-            });
+            };
+            coin_desc.update_psbt_in(&mut psbt_in);
+            psbt.inputs.push(psbt_in);
         }
 
         // The sweepable_coins iterator may have been empty.
@@ -742,10 +2257,217 @@ impl DaemonControl {
         })?;
         psbt.unsigned_tx.output[0].value = output_value.to_sat();
 
-        sanity_check_psbt(&psbt)?;
+        sanity_check_psbt(&psbt, current_height)?;
 
         Ok(CreateRecoveryResult { psbt })
     }
+
+    /// Create a PSBT of the BIP322 "to_sign" virtual transaction, to sign this message with one of
+    /// our addresses. Once signed, it can be turned into a signature with `finalize_message_psbt`.
+    pub fn create_message_psbt(
+        &self,
+        address: &bitcoin::Address,
+        message: &str,
+    ) -> Result<CreateMessagePsbtResult, CommandError> {
+        self.validate_address(address)?;
+
+        let mut db_conn = self.db.connection();
+        let (index, is_change) = db_conn
+            .derivation_index_by_address(address)
+            .ok_or_else(|| CommandError::UnknownAddress(address.clone()))?;
+        let desc = if is_change {
+            self.config.main_descriptor.change_descriptor()
+        } else {
+            self.config.main_descriptor.receive_descriptor()
+        };
+
+        // The "to_spend" transaction is never broadcast, we only need it to give the signer the
+        // previous output of the "to_sign" transaction.
+        let to_spend = bip322::to_spend(&address.script_pubkey(), message.as_bytes());
+        let mut psbt_in = PsbtIn {
+            witness_utxo: Some(to_spend.output[0].clone()),
+            non_witness_utxo: Some(to_spend.clone()),
+            ..PsbtIn::default()
+        };
+        desc.derive(index, &self.secp).update_psbt_in(&mut psbt_in);
+
+        let psbt = Psbt {
+            unsigned_tx: bip322::to_sign(&to_spend),
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            inputs: vec![psbt_in],
+            outputs: vec![PsbtOut::default()],
+        };
+        Ok(CreateMessagePsbtResult { psbt })
+    }
+
+    /// Turn a signed PSBT created by `create_message_psbt` into the BIP322 signature of this
+    /// message for this address.
+    pub fn finalize_message_psbt(
+        &self,
+        address: &bitcoin::Address,
+        message: &str,
+        mut psbt: Psbt,
+    ) -> Result<FinalizeMessagePsbtResult, CommandError> {
+        let to_spend = bip322::to_spend(&address.script_pubkey(), message.as_bytes());
+        if psbt.unsigned_tx != bip322::to_sign(&to_spend) || psbt.inputs.len() != 1 {
+            return Err(CommandError::MalformedPsbt);
+        }
+
+        psbt.finalize_mut(&self.secp).map_err(|errors| {
+            CommandError::InvalidMessageSignature(
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })?;
+        let witness = psbt.inputs[0]
+            .final_script_witness
+            .clone()
+            .unwrap_or_default();
+        let signature = bip322::encode_signature(&witness);
+
+        // Sanity check the signature before returning it.
+        bip322::verify(&self.secp, address, message.as_bytes(), &signature)
+            .map_err(|e| CommandError::InvalidMessageSignature(e.to_string()))?;
+
+        Ok(FinalizeMessagePsbtResult { signature })
+    }
+
+    /// Check a BIP322 signature of this message for any address.
+    pub fn verify_message(
+        &self,
+        address: &bitcoin::Address,
+        message: &str,
+        signature: &str,
+    ) -> Result<VerifyMessageResult, CommandError> {
+        match bip322::verify(&self.secp, address, message.as_bytes(), signature) {
+            Ok(()) => Ok(VerifyMessageResult { valid: true }),
+            Err(bip322::Bip322Error::InvalidSignature(_)) => {
+                Ok(VerifyMessageResult { valid: false })
+            }
+            Err(e @ bip322::Bip322Error::MalformedSignature) => {
+                Err(CommandError::InvalidMessageSignature(e.to_string()))
+            }
+        }
+    }
+
+    /// Create a BIP127 proof of reserves committing to this message, for these coins or all our
+    /// confirmed unspent coins if none is given. The PSBT must be signed like a Spend, but the
+    /// transaction can never be broadcast.
+    pub fn create_reserves_proof(
+        &self,
+        message: &str,
+        coins_outpoints: &[bitcoin::OutPoint],
+    ) -> Result<CreateReservesProofResult, CommandError> {
+        let mut db_conn = self.db.connection();
+        let mut coins: Vec<Coin> = if coins_outpoints.is_empty() {
+            db_conn
+                .coins(CoinType::Unspent)
+                .into_iter()
+                .map(|(_, coin)| coin)
+                .filter(|coin| coin.is_confirmed())
+                .collect()
+        } else {
+            let coins = db_conn.coins_by_outpoints(coins_outpoints);
+            let mut selected = Vec::with_capacity(coins_outpoints.len());
+            for op in coins_outpoints {
+                let coin = coins.get(op).ok_or(CommandError::UnknownOutpoint(*op))?;
+                if coin.is_spent() {
+                    return Err(CommandError::AlreadySpent(*op));
+                }
+                // The proof is checked against the confirmed UTxO set.
+                if !coin.is_confirmed() {
+                    return Err(CommandError::UnconfirmedCoin(*op));
+                }
+                selected.push(*coin);
+            }
+            selected
+        };
+        if coins.is_empty() {
+            return Err(CommandError::NoConfirmedCoin);
+        }
+        // A coin can only be counted once.
+        coins.sort_by_key(|coin| coin.outpoint);
+        coins.dedup_by_key(|coin| coin.outpoint);
+
+        let coins_values: Vec<_> = coins
+            .iter()
+            .map(|coin| (coin.outpoint, coin.amount))
+            .collect();
+        let commitment_in = PsbtIn {
+            witness_utxo: Some(reserves::commitment_txout()),
+            ..PsbtIn::default()
+        };
+        let inputs = iter::once(Ok(commitment_in))
+            .chain(coins.iter().map(|coin| self.coin_psbt_in(coin)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let psbt = Psbt {
+            unsigned_tx: reserves::proof_tx(message.as_bytes(), &coins_values),
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+            inputs,
+            outputs: vec![PsbtOut::default()],
+        };
+
+        Ok(CreateReservesProofResult { psbt })
+    }
+
+    /// Check a signed BIP127 proof of reserves for this message against the current UTxO set. The
+    /// coins don't have to be ours.
+    pub fn verify_reserves_proof(
+        &self,
+        message: &str,
+        mut psbt: Psbt,
+    ) -> Result<VerifyReservesProofResult, CommandError> {
+        if psbt.inputs.len() != psbt.unsigned_tx.input.len()
+            || psbt.outputs.len() != psbt.unsigned_tx.output.len()
+        {
+            return Err(CommandError::MalformedPsbt);
+        }
+
+        // Get the spent outputs from bitcoind rather than trusting the PSBT. This also makes sure
+        // they are all still unspent.
+        let coins: Vec<_> = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .skip(1)
+            .map(|txin| txin.previous_output)
+            .collect();
+        let mut prevouts = Vec::with_capacity(coins.len());
+        for op in &coins {
+            let prevout = self.bitcoin.utxo(op).ok_or_else(|| {
+                CommandError::InvalidReservesProof(format!("coin at '{}' is not unspent", op))
+            })?;
+            prevouts.push(prevout);
+        }
+
+        // All inputs but the commitment one must be finalized for us to check the signatures.
+        for index in 1..psbt.inputs.len() {
+            let psbt_in = &psbt.inputs[index];
+            if psbt_in.final_script_witness.is_none() && psbt_in.final_script_sig.is_none() {
+                psbt.finalize_inp_mut(&self.secp, index)
+                    .map_err(|e| CommandError::InvalidReservesProof(e.to_string()))?;
+            }
+        }
+        let tx = psbt.extract_tx();
+        reserves::verify(&self.secp, &tx, message.as_bytes(), &prevouts)
+            .map_err(|e| CommandError::InvalidReservesProof(e.to_string()))?;
+
+        let amount = bitcoin::Amount::from_sat(prevouts.iter().map(|txo| txo.value).sum());
+        Ok(VerifyReservesProofResult {
+            amount,
+            coins,
+            block_height: self.bitcoin.chain_tip().height,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -761,6 +2483,8 @@ pub struct GetInfoResult {
     pub block_height: i32,
     pub sync: f64,
     pub descriptors: GetInfoDescriptors,
+    /// An explanation of the spending policy of the main descriptor
+    pub policy: descriptors::PolicyExplanation,
     /// The progress as a percentage (between 0 and 1) of an ongoing rescan if there is any
     pub rescan_progress: Option<f64>,
 }
@@ -770,6 +2494,51 @@ pub struct GetAddressResult {
     pub address: bitcoin::Address,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListAddressesEntry {
+    /// The derivation index of this address.
+    pub index: u32,
+    pub address: bitcoin::Address,
+    pub is_change: bool,
+    /// The number of coins received on this address.
+    pub coins: usize,
+    /// The total value received on this address.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub received: bitcoin::Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListAddressesResult {
+    pub addresses: Vec<ListAddressesEntry>,
+}
+
+/// The origin of a public key used in one of our addresses.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyDerivation {
+    /// The derived public key, as hex. It is x-only for a Taproot descriptor.
+    pub pubkey: String,
+    /// The master fingerprint of the key it is derived from.
+    pub fingerprint: bip32::Fingerprint,
+    /// The full derivation path from the master key.
+    pub derivation_path: bip32::DerivationPath,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GetAddressInfoResult {
+    pub address: bitcoin::Address,
+    /// Whether this address was derived from our descriptor.
+    pub is_mine: bool,
+    /// The derivation index of this address, if it is ours.
+    pub index: Option<u32>,
+    /// Whether this is one of our change addresses.
+    pub is_change: bool,
+    /// The origin of all the keys this address is derived from, if it is ours.
+    pub derivations: Vec<KeyDerivation>,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct LCSpendInfo {
     pub txid: bitcoin::Txid,
@@ -777,7 +2546,7 @@ pub struct LCSpendInfo {
     pub height: Option<i32>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListCoinsEntry {
     #[serde(
         serialize_with = "ser_amount",
@@ -788,6 +2557,9 @@ pub struct ListCoinsEntry {
     pub block_height: Option<i32>,
     /// Information about the transaction spending this coin.
     pub spend_info: Option<LCSpendInfo>,
+    /// Whether this coin was frozen by the user, excluding it from being spent.
+    pub is_frozen: bool,
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -795,16 +2567,82 @@ pub struct ListCoinsResult {
     pub coins: Vec<ListCoinsEntry>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// The number of blocks within which the transaction is targeted to confirm.
+    pub conf_target: u16,
+    /// The feerate to use, in sats/vb. None if no estimate is available.
+    pub feerate: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EstimateFeesResult {
+    pub estimates: Vec<FeeEstimate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpiryReportEntry {
+    pub outpoint: bitcoin::OutPoint,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    /// The block height this coin was confirmed at, if it was.
+    pub block_height: Option<i32>,
+    /// The timelock of the recovery path which becomes available first for this coin.
+    pub timelock: descriptors::RecoveryTimelock,
+    /// The height of the first block in which the recovery path may be used, if it's known.
+    pub unlock_height: Option<i32>,
+    /// The (projected, if in the future) timestamp from which the recovery path may be used, if
+    /// it's known.
+    pub unlock_time: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExpiryReportResult {
+    /// The average interval between blocks used for the projections, in seconds.
+    pub avg_block_interval_secs: u32,
+    /// The coins which may already be swept through the recovery path.
+    pub recoverable: Vec<ExpiryReportEntry>,
+    /// The coins which may be swept through the recovery path within the given number of blocks.
+    pub expiring: Vec<ExpiryReportEntry>,
+    /// The other coins.
+    pub safe: Vec<ExpiryReportEntry>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CreateSpendResult {
     #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
     pub psbt: Psbt,
+    /// The coins spent by the transaction. Selected by us if none were given.
+    pub coins: Vec<bitcoin::OutPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreateRefreshResult {
+    #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
+    pub psbt: Psbt,
+    pub coins: Vec<bitcoin::OutPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreateCpfpResult {
+    #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BumpFeeResult {
+    #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
+    pub psbt: Psbt,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListSpendEntry {
     #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
     pub psbt: Psbt,
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -823,6 +2661,25 @@ pub struct TransactionInfo {
     pub tx: bitcoin::Transaction,
     pub height: Option<i32>,
     pub time: Option<u32>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GetLabelsResult {
+    /// The label of each item which has one, keyed by the item.
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportLabelsResult {
+    /// The labels in the BIP-329 JSON Lines format.
+    pub labels: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportLabelsResult {
+    pub imported: usize,
+    pub skipped: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -831,10 +2688,111 @@ pub struct CreateRecoveryResult {
     pub psbt: Psbt,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreateMessagePsbtResult {
+    #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FinalizeMessagePsbtResult {
+    /// The base64-encoded BIP322 signature.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifyMessageResult {
+    pub valid: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreateReservesProofResult {
+    #[serde(serialize_with = "ser_base64", deserialize_with = "deser_base64")]
+    pub psbt: Psbt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifyReservesProofResult {
+    /// The total value of the coins proven.
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    pub coins: Vec<bitcoin::OutPoint>,
+    /// The height of the tip at which the coins were checked to be unspent.
+    pub block_height: i32,
+}
+
+/// The signatures present and missing for one of the spending paths available to an input.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzedPath {
+    pub path: descriptors::SpendingPath,
+    /// The number of signatures needed to spend through this path.
+    pub threshold: usize,
+    /// The master fingerprints of the keys which signed for this path.
+    pub signed: Vec<bip32::Fingerprint>,
+    /// The master fingerprints of the keys of this path which did not sign.
+    pub missing: Vec<bip32::Fingerprint>,
+    /// For a path with a hash lock, whether a valid preimage is present.
+    pub has_preimage: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzedInput {
+    pub outpoint: bitcoin::OutPoint,
+    /// The value of the spent coin, if known.
+    #[serde(
+        serialize_with = "ser_optional_amount",
+        deserialize_with = "deser_optional_amount_from_sats"
+    )]
+    pub amount: Option<bitcoin::Amount>,
+    /// Whether this input spends one of our coins.
+    pub is_mine: bool,
+    /// For our coins, the spending paths available given the nSequence and nLockTime.
+    pub paths: Vec<AnalyzedPath>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzedOutput {
+    /// None if the Script isn't a standard one.
+    pub address: Option<bitcoin::Address>,
+    #[serde(
+        serialize_with = "ser_amount",
+        deserialize_with = "deser_amount_from_sats"
+    )]
+    pub amount: bitcoin::Amount,
+    /// Whether this output pays to one of our addresses.
+    pub is_mine: bool,
+    /// Whether this output pays to one of our change addresses.
+    pub is_change: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzePsbtResult {
+    pub inputs: Vec<AnalyzedInput>,
+    pub outputs: Vec<AnalyzedOutput>,
+    /// The absolute fee, if the value of all inputs is known.
+    #[serde(
+        serialize_with = "ser_optional_amount",
+        deserialize_with = "deser_optional_amount_from_sats"
+    )]
+    pub fee: Option<bitcoin::Amount>,
+    /// The estimated feerate in sats/vb, if the fee is known and all inputs are ours.
+    pub feerate_vb: Option<u64>,
+    /// Set if our inputs are inconsistent with each other, for instance if they were signed by
+    /// different keys.
+    pub inconsistency: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{bitcoin::Block, database::SpendBlock, testutils::*};
+    use crate::{
+        bitcoin::{d::MempoolEntry, Block},
+        database::SpendBlock,
+        testutils::*,
+    };
 
     use bitcoin::{
         blockdata::transaction::{TxIn, TxOut},
@@ -845,37 +2803,1442 @@ mod tests {
 
     use bitcoin::util::bip32;
 
-    #[test]
-    fn getinfo() {
-        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
-        // We can query getinfo
-        ms.handle.control.get_info();
+    #[test]
+    fn getinfo() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        // We can query getinfo
+        ms.handle.control.get_info();
+        ms.shutdown();
+    }
+
+    #[test]
+    fn estimate_fees() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // We get an estimate for each target, decreasing as the target gets further.
+        let estimates = control.estimate_fees().estimates;
+        assert_eq!(estimates.len(), FEE_ESTIMATES_TARGETS.len());
+        assert_eq!(
+            estimates[0],
+            FeeEstimate {
+                conf_target: 2,
+                feerate: Some(72)
+            }
+        );
+        assert_eq!(estimates.last().unwrap().feerate, Some(1));
+        assert!(estimates.windows(2).all(|w| w[0].feerate >= w[1].feerate));
+
+        // We can get the feerate for a specific target, if it's sane.
+        assert_eq!(control.feerate_for_target(6), Ok(24));
+        assert_eq!(
+            control.feerate_for_target(0),
+            Err(CommandError::InvalidConfTarget(0))
+        );
+        assert_eq!(
+            control.feerate_for_target(1009),
+            Err(CommandError::InvalidConfTarget(1009))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn expiry_report() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // No coin.
+        let res = control.get_expiry_report(1_000);
+        assert_eq!(res.avg_block_interval_secs, 600);
+        assert!(res.recoverable.is_empty() && res.expiring.is_empty() && res.safe.is_empty());
+
+        // The tip is at height 100 and the timelock is 10_000 blocks. The recovery path for the
+        // first coin becomes available at height 10_001, at 10_050 for the second one. The third
+        // one is unconfirmed.
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b, op_c) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+            bitcoin::OutPoint::new(dummy_txid, 2),
+        );
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[op_a, op_b, op_c]
+                .iter()
+                .map(|outpoint| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(10_000),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 1, 1_000_000), (op_b, 50, 1_000_000)]);
+
+        let res = control.get_expiry_report(9_900);
+        assert!(res.recoverable.is_empty() && res.expiring.is_empty());
+        let safe: Vec<_> = res.safe.iter().map(|e| e.outpoint).collect();
+        assert_eq!(safe, vec![op_a, op_b, op_c]);
+
+        let res = control.get_expiry_report(9_901);
+        assert!(res.recoverable.is_empty());
+        assert_eq!(res.expiring.len(), 1);
+        let entry = &res.expiring[0];
+        assert_eq!(entry.outpoint, op_a);
+        assert_eq!(entry.block_height, Some(1));
+        assert_eq!(
+            entry.timelock,
+            descriptors::RecoveryTimelock::Relative(10_000)
+        );
+        assert_eq!(entry.unlock_height, Some(10_001));
+        assert_eq!(
+            entry.unlock_time,
+            Some(1_600_000_000 + 100 * 600 + 9_901 * 600)
+        );
+        assert_eq!(res.safe.len(), 2);
+        assert_eq!(res.safe[0].unlock_height, Some(10_050));
+        assert_eq!(res.safe[1].outpoint, op_c);
+        assert_eq!(res.safe[1].unlock_height, None);
+        assert_eq!(res.safe[1].unlock_time, None);
+
+        ms.shutdown();
+
+        // With an additional recovery path with an absolute timelock at height 10_020, it becomes
+        // available first for the second coin and for the unconfirmed one.
+        let second_heir_key = descriptors::LianaDescKeys::from_single(miniscript::descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let desc = descriptors::MultipathDescriptor::new(
+            dummy_owner_key(),
+            [
+                (
+                    descriptors::RecoveryTimelock::Relative(10_000),
+                    dummy_heir_key(),
+                ),
+                (
+                    descriptors::RecoveryTimelock::Absolute(10_020),
+                    second_heir_key,
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        )
+        .unwrap();
+        let ms = DummyLiana::with_descriptor(DummyBitcoind::new(), DummyDatabase::new(), desc);
+        let control = &ms.handle.control;
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[op_a, op_b, op_c]
+                .iter()
+                .map(|outpoint| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(10_000),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 1, 1_000_000), (op_b, 50, 1_000_000)]);
+
+        let res = control.get_expiry_report(9_901);
+        assert!(res.recoverable.is_empty());
+        let expiring: Vec<_> = res
+            .expiring
+            .iter()
+            .map(|e| (e.outpoint, e.timelock, e.unlock_height))
+            .collect();
+        assert_eq!(
+            expiring,
+            vec![(
+                op_a,
+                descriptors::RecoveryTimelock::Relative(10_000),
+                Some(10_001)
+            )]
+        );
+        let safe: Vec<_> = res
+            .safe
+            .iter()
+            .map(|e| (e.outpoint, e.timelock, e.unlock_height))
+            .collect();
+        assert_eq!(
+            safe,
+            vec![
+                (
+                    op_b,
+                    descriptors::RecoveryTimelock::Absolute(10_020),
+                    Some(10_021)
+                ),
+                (
+                    op_c,
+                    descriptors::RecoveryTimelock::Absolute(10_020),
+                    Some(10_021)
+                ),
+            ]
+        );
+        let res = control.get_expiry_report(9_921);
+        assert_eq!(res.expiring.len(), 3);
+        assert!(res.safe.is_empty());
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn getnewaddress() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+
+        let control = &ms.handle.control;
+        // We can get an address
+        let addr = control.get_new_address().address;
+        assert_eq!(
+            addr,
+            bitcoin::Address::from_str(
+                "bc1q9ksrc647hx8zp2cewl8p5f487dgux3777yees8rjcx46t4daqzzqt7yga8"
+            )
+            .unwrap()
+        );
+        // We won't get the same twice.
+        let addr2 = control.get_new_address().address;
+        assert_ne!(addr, addr2);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn addresses() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // Nothing was derived yet.
+        assert!(control.list_addresses(0, 10).addresses.is_empty());
+
+        // Hand out three addresses, and receive two coins on the second one.
+        let addrs: Vec<_> = (0..3).map(|_| control.get_new_address().address).collect();
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(0, 10_000), (1, 20_000)]
+                .iter()
+                .map(|(vout, value)| Coin {
+                    outpoint: bitcoin::OutPoint::new(dummy_txid, *vout),
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: bip32::ChildNumber::from(1),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        // The dummy database uses the same index for change, so each index comes with both.
+        let res = control.list_addresses(0, 10);
+        assert_eq!(res.addresses.len(), 6);
+        let receive: Vec<_> = res
+            .addresses
+            .iter()
+            .filter(|e| !e.is_change)
+            .map(|e| e.address.clone())
+            .collect();
+        assert_eq!(receive, addrs);
+        let entry = res
+            .addresses
+            .iter()
+            .find(|e| e.index == 1 && !e.is_change)
+            .unwrap();
+        assert_eq!(entry.coins, 2);
+        assert_eq!(entry.received, bitcoin::Amount::from_sat(30_000));
+        assert!(res
+            .addresses
+            .iter()
+            .filter(|e| e.index != 1 || e.is_change)
+            .all(|e| e.coins == 0 && e.received == bitcoin::Amount::from_sat(0)));
+
+        // It can be paged through.
+        let res = control.list_addresses(1, 1);
+        assert_eq!(res.addresses.len(), 2);
+        assert!(res.addresses.iter().all(|e| e.index == 1));
+        assert_eq!(res.addresses[0].address, addrs[1]);
+        assert!(control.list_addresses(3, 10).addresses.is_empty());
+
+        // A foreign address isn't ours, and one for another network is refused.
+        let foreign_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let res = control.get_address_info(&foreign_addr).unwrap();
+        assert!(!res.is_mine);
+        assert_eq!(res.index, None);
+        assert!(res.derivations.is_empty());
+        let testnet_addr = bitcoin::Address {
+            network: bitcoin::Network::Testnet,
+            payload: foreign_addr.payload.clone(),
+        };
+        assert_eq!(
+            control.get_address_info(&testnet_addr),
+            Err(CommandError::AddressNetwork(
+                testnet_addr,
+                bitcoin::Network::Bitcoin
+            ))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn message_signing() {
+        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // We can only sign for our own addresses, on our network.
+        let foreign_addr =
+            bitcoin::Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l").unwrap();
+        assert_eq!(
+            control.create_message_psbt(&foreign_addr, "Hello World"),
+            Err(CommandError::UnknownAddress(foreign_addr.clone()))
+        );
+        let testnet_addr = bitcoin::Address {
+            network: bitcoin::Network::Testnet,
+            payload: foreign_addr.payload.clone(),
+        };
+        assert_eq!(
+            control.create_message_psbt(&testnet_addr, "Hello World"),
+            Err(CommandError::AddressNetwork(
+                testnet_addr,
+                bitcoin::Network::Bitcoin
+            ))
+        );
+
+        // A PSBT for another message's "to_sign" transaction is refused.
+        let to_spend = bip322::to_spend(&foreign_addr.script_pubkey(), b"Hello Word");
+        let dummy_psbt = Psbt::from_unsigned_tx(bip322::to_sign(&to_spend)).unwrap();
+        assert_eq!(
+            control.finalize_message_psbt(&foreign_addr, "Hello World", dummy_psbt),
+            Err(CommandError::MalformedPsbt)
+        );
+
+        // Signatures can be verified for any address.
+        let sig = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        assert!(
+            control
+                .verify_message(&foreign_addr, "Hello World", sig)
+                .unwrap()
+                .valid
+        );
+        assert!(
+            !control
+                .verify_message(&foreign_addr, "Hello Word", sig)
+                .unwrap()
+                .valid
+        );
+        assert!(matches!(
+            control.verify_message(&foreign_addr, "Hello World", "not base64"),
+            Err(CommandError::InvalidMessageSignature(..))
+        ));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn reserves_proof() {
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b, op_c, op_d) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+            bitcoin::OutPoint::new(dummy_txid, 2),
+            bitcoin::OutPoint::new(dummy_txid, 3),
+        );
+        // Only the first two outputs are in the UTxO set.
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: [60_000, 30_000]
+                        .iter()
+                        .map(|value| bitcoin::TxOut {
+                            value: *value,
+                            script_pubkey: bitcoin::Script::new(),
+                        })
+                        .collect(),
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+        let message = "Reserves as of block 100";
+
+        // There is no coin to prove yet.
+        assert_eq!(
+            control.create_reserves_proof(message, &[]),
+            Err(CommandError::NoConfirmedCoin)
+        );
+
+        // Two confirmed coins, one of which is frozen, an unconfirmed one and a spent one.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[
+                (op_a, 60_000),
+                (op_b, 30_000),
+                (op_c, 10_000),
+                (op_d, 5_000),
+            ]
+            .iter()
+            .map(|(outpoint, value)| Coin {
+                outpoint: *outpoint,
+                block_height: None,
+                block_time: None,
+                amount: bitcoin::Amount::from_sat(*value),
+                derivation_index: bip32::ChildNumber::from(13),
+                is_change: false,
+                spend_txid: None,
+                spend_block: None,
+                is_frozen: false,
+            })
+            .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 100, 1_000_000), (op_b, 100, 1_000_000)]);
+        db_conn.freeze_coins(&[op_b], true);
+        db_conn.spend_coins(&[(op_d, dummy_txid)]);
+
+        // By default all the confirmed coins are proven, frozen or not.
+        let psbt = control.create_reserves_proof(message, &[]).unwrap().psbt;
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(
+            tx.input
+                .iter()
+                .map(|txin| txin.previous_output)
+                .collect::<Vec<_>>(),
+            vec![
+                reserves::commitment_outpoint(message.as_bytes()),
+                op_a,
+                op_b
+            ]
+        );
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].value, 90_000);
+        assert_eq!(
+            psbt.inputs[0].witness_utxo,
+            Some(reserves::commitment_txout())
+        );
+        assert!(psbt.inputs[1..]
+            .iter()
+            .all(|psbt_in| psbt_in.witness_utxo.is_some() && !psbt_in.bip32_derivation.is_empty()));
+
+        // Coins can be selected, but only once and if they are confirmed and unspent.
+        let psbt = control
+            .create_reserves_proof(message, &[op_a, op_a])
+            .unwrap()
+            .psbt;
+        assert_eq!(psbt.unsigned_tx.input.len(), 2);
+        assert_eq!(psbt.unsigned_tx.output[0].value, 60_000);
+        assert_eq!(
+            control.create_reserves_proof(message, &[op_a, op_c]),
+            Err(CommandError::UnconfirmedCoin(op_c))
+        );
+        assert_eq!(
+            control.create_reserves_proof(message, &[op_d]),
+            Err(CommandError::AlreadySpent(op_d))
+        );
+        let unknown_op = bitcoin::OutPoint::new(dummy_txid, 4);
+        assert_eq!(
+            control.create_reserves_proof(message, &[unknown_op]),
+            Err(CommandError::UnknownOutpoint(unknown_op))
+        );
+
+        // An unsigned proof isn't valid, and neither is a proof for coins not in the UTxO set.
+        assert!(matches!(
+            control.verify_reserves_proof(message, psbt.clone()),
+            Err(CommandError::InvalidReservesProof(..))
+        ));
+        let mut psbt = psbt;
+        psbt.unsigned_tx.input[1].previous_output = op_c;
+        assert_eq!(
+            control.verify_reserves_proof(message, psbt),
+            Err(CommandError::InvalidReservesProof(format!(
+                "coin at '{}' is not unspent",
+                op_c
+            )))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend() {
+        let dummy_op = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
+        )
+        .unwrap();
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_op.txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // Arguments sanity checking
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let dummy_value = 10_000;
+        let mut destinations = vec![(dummy_addr.clone(), dummy_value)];
+        assert_eq!(
+            control.create_spend(&[], &[dummy_op], 1, None, false),
+            Err(CommandError::NoDestination)
+        );
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 0, None, false),
+            Err(CommandError::InvalidFeerate(0))
+        );
+
+        // The coin doesn't exist. If we create a new unspent one at this outpoint with a much
+        // higher value, we'll get a Spend transaction with a change output.
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 1, None, false),
+            Err(CommandError::UnknownOutpoint(dummy_op))
+        );
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            block_height: None,
+            block_time: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_frozen: false,
+        }]);
+        let res = control
+            .create_spend(&destinations, &[dummy_op], 1, None, false)
+            .unwrap();
+        assert!(res.psbt.inputs[0].non_witness_utxo.is_some());
+        let tx = res.psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, dummy_op);
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].script_pubkey, dummy_addr.script_pubkey());
+        assert_eq!(tx.output[0].value, dummy_value);
+
+        // Transaction is 1 in (P2WSH satisfaction through the primary path), 2 outs. At 1sat/vb,
+        // it's 162 sats fees. At 2sats/vb, it's twice that.
+        assert_eq!(tx.output[1].value, 89_838);
+        let res = control
+            .create_spend(&destinations, &[dummy_op], 2, None, false)
+            .unwrap();
+        let tx = res.psbt.unsigned_tx;
+        assert_eq!(tx.output[1].value, 89_676);
+
+        // If we ask for a too high feerate, or a too large/too small output, it'll fail.
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 10_000, None, false),
+            Err(CommandError::InsufficientFunds(
+                bitcoin::Amount::from_sat(100_000),
+                bitcoin::Amount::from_sat(10_000),
+                10_000
+            ))
+        );
+        destinations[0].1 = 100_001;
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 1, None, false),
+            Err(CommandError::InsufficientFunds(
+                bitcoin::Amount::from_sat(100_000),
+                bitcoin::Amount::from_sat(100_001),
+                1
+            ))
+        );
+        destinations[0].1 = 4_500;
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 1, None, false),
+            Err(CommandError::InvalidOutputValue(bitcoin::Amount::from_sat(
+                4_500
+            )))
+        );
+
+        // If we ask to create an output for an address from another network, it will fail.
+        let invalid_addr = bitcoin::Address {
+            network: bitcoin::Network::Testnet,
+            payload: dummy_addr.payload.clone(),
+        };
+        let invalid_destinations = vec![(invalid_addr.clone(), dummy_value)];
+        assert_eq!(
+            control.create_spend(&invalid_destinations, &[dummy_op], 1, None, false),
+            Err(CommandError::AddressNetwork(
+                invalid_addr,
+                bitcoin::Network::Bitcoin
+            ))
+        );
+
+        // If we ask for a large, but valid, output we won't get a change output. 95_000 because we
+        // won't create an output lower than 5k sats.
+        destinations[0].1 = 95_000;
+        let res = control
+            .create_spend(&destinations, &[dummy_op], 1, None, false)
+            .unwrap();
+        let tx = res.psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, dummy_op);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].script_pubkey, dummy_addr.script_pubkey());
+        assert_eq!(tx.output[0].value, 95_000);
+
+        // We can also ask for an output to receive whatever remains after paying the fees. The
+        // transaction is then 1 in, 1 P2WPKH out: 119 vbytes.
+        let res = control
+            .create_spend(&[], &[dummy_op], 1, Some(&dummy_addr), false)
+            .unwrap();
+        let tx = res.psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].script_pubkey, dummy_addr.script_pubkey());
+        assert_eq!(tx.output[0].value, 100_000 - 119);
+        let res = control
+            .create_spend(&[], &[dummy_op], 2, Some(&dummy_addr), false)
+            .unwrap();
+        assert_eq!(res.psbt.unsigned_tx.output[0].value, 100_000 - 2 * 119);
+
+        // Along with other destinations, the remainder output comes last and no change is added.
+        destinations[0].1 = 10_000;
+        let res = control
+            .create_spend(&destinations, &[dummy_op], 1, Some(&dummy_addr), false)
+            .unwrap();
+        let tx = res.psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].value, 10_000);
+        assert_eq!(tx.output[1].value, 90_000 - 150);
+
+        // The remainder must not be dust.
+        destinations[0].1 = 95_000;
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 1, Some(&dummy_addr), false),
+            Err(CommandError::InsufficientFunds(
+                bitcoin::Amount::from_sat(100_000),
+                bitcoin::Amount::from_sat(95_000),
+                1
+            ))
+        );
+
+        // The same address may be paid more than once, with an output for each payment.
+        let res = control
+            .create_spend(
+                &[(dummy_addr.clone(), 10_000), (dummy_addr.clone(), 20_000)],
+                &[dummy_op],
+                1,
+                None,
+                false,
+            )
+            .unwrap();
+        let mut values: Vec<_> = res
+            .psbt
+            .unsigned_tx
+            .output
+            .iter()
+            .filter(|txo| txo.script_pubkey == dummy_addr.script_pubkey())
+            .map(|txo| txo.value)
+            .collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![10_000, 20_000]);
+
+        // Now if we mark the coin as spent, we won't create another Spend transaction containing
+        // it.
+        db_conn.spend_coins(&[(
+            dummy_op,
+            bitcoin::Txid::from_str(
+                "ef78f79ba747813887747cf8582897a48f1a09f1ca04d2cd3d6fcfdcbb5e0797",
+            )
+            .unwrap(),
+        )]);
+        assert_eq!(
+            control.create_spend(&destinations, &[dummy_op], 1, None, false),
+            Err(CommandError::AlreadySpent(dummy_op))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn bump_fee() {
+        let dummy_txid =
+            Txid::from_str("3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810")
+                .unwrap();
+        let (op_a, op_b) = (OutPoint::new(dummy_txid, 0), OutPoint::new(dummy_txid, 1));
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                Transaction {
+                    version: 2,
+                    lock_time: PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(op_a, 100_000), (op_b, 200_000)]
+                .iter()
+                .map(|(outpoint, value)| Coin {
+                    outpoint: *outpoint,
+                    block_height: Some(100),
+                    block_time: Some(1_000_000),
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let fee = |psbt: &Psbt| {
+            let in_value: u64 = psbt
+                .inputs
+                .iter()
+                .map(|psbt_in| psbt_in.witness_utxo.as_ref().unwrap().value)
+                .sum();
+            let out_value: u64 = psbt.unsigned_tx.output.iter().map(|txo| txo.value).sum();
+            in_value - out_value
+        };
+
+        // Create a Spend with a change output. We can't bump it until it's broadcast.
+        let destinations = vec![(dummy_addr.clone(), 50_000)];
+        let psbt = control
+            .create_spend(&destinations, &[op_a], 1, None, false)
+            .unwrap()
+            .psbt;
+        let txid = psbt.unsigned_tx.txid();
+        assert_eq!(
+            control.bump_fee(&txid, 10),
+            Err(CommandError::UnknownSpend(txid))
+        );
+        db_conn.store_spend(&psbt);
+        assert_eq!(
+            control.bump_fee(&txid, 10),
+            Err(CommandError::NotReplaceable(txid))
+        );
+        db_conn.spend_coins(&[(op_a, txid)]);
+
+        // The replacement must have a higher feerate. The fees are taken from the change.
+        assert_eq!(
+            control.bump_fee(&txid, 1),
+            Err(CommandError::ReplacementFeerate(1, 1))
+        );
+        let bumped = control.bump_fee(&txid, 10).unwrap().psbt;
+        let bumped_tx = &bumped.unsigned_tx;
+        assert_eq!(bumped_tx.input.len(), 1);
+        assert_eq!(bumped_tx.input[0].previous_output, op_a);
+        assert_eq!(bumped_tx.output.len(), 2);
+        assert_eq!(
+            bumped_tx.output[0].script_pubkey,
+            dummy_addr.script_pubkey()
+        );
+        assert_eq!(bumped_tx.output[0].value, 50_000);
+        assert_eq!(
+            bumped_tx.output[1].script_pubkey,
+            psbt.unsigned_tx.output[1].script_pubkey
+        );
+        assert_eq!(fee(&bumped), 1_620);
+
+        // The replacement was stored, and the replaced transaction may not be broadcast
+        // nor bumped anymore.
+        let bumped_txid = bumped_tx.txid();
+        assert_eq!(db_conn.spend_tx(&bumped_txid), Some(bumped.clone()));
+        assert_eq!(
+            control.broadcast_spend(&txid),
+            Err(CommandError::ReplacedSpend(txid, bumped_txid))
+        );
+        assert_eq!(
+            control.bump_fee(&txid, 20),
+            Err(CommandError::ReplacedSpend(txid, bumped_txid))
+        );
+
+        // Once the replacement is broadcast, its coin is marked as spent by it. If there isn't
+        // enough value in its change output to bump it further, we'll add coins.
+        let coin_a = db_conn.coins_by_outpoints(&[op_a])[&op_a];
+        db_conn.new_unspent_coins(&[Coin {
+            spend_txid: Some(bumped_txid),
+            ..coin_a
+        }]);
+        let rebumped = control.bump_fee(&bumped_txid, 450).unwrap().psbt;
+        let rebumped_tx = &rebumped.unsigned_tx;
+        assert_eq!(rebumped_tx.input.len(), 2);
+        assert_eq!(rebumped_tx.input[0].previous_output, op_a);
+        assert_eq!(rebumped_tx.input[1].previous_output, op_b);
+        assert_eq!(rebumped_tx.output.len(), 2);
+        assert_eq!(rebumped_tx.output[0].value, 50_000);
+        assert!(fee(&rebumped) >= fee(&bumped) + 450 * 162);
+        assert_eq!(
+            control.broadcast_spend(&bumped_txid),
+            Err(CommandError::ReplacedSpend(bumped_txid, rebumped_tx.txid()))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn bump_fee_recovery() {
+        let dummy_txid =
+            Txid::from_str("3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810")
+                .unwrap();
+        let (op_a, op_b, op_c) = (
+            OutPoint::new(dummy_txid, 0),
+            OutPoint::new(dummy_txid, 1),
+            OutPoint::new(dummy_txid, 2),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                Transaction {
+                    version: 2,
+                    lock_time: PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        // A descriptor with a short relative timelock, for the coins to be recoverable.
+        let desc = descriptors::MultipathDescriptor::new(
+            dummy_owner_key(),
+            single_recovery_path(
+                descriptors::RecoveryTimelock::Relative(10),
+                dummy_heir_key(),
+            ),
+        )
+        .unwrap();
+        let desc_info = desc.info();
+        let recovery_sat_vb = desc.path_sat_vbytes(&desc_info.recovery_paths()[&10]) as u64;
+        assert!(recovery_sat_vb > desc.path_sat_vbytes(desc_info.primary_path()) as u64);
+        let ms = DummyLiana::with_descriptor(dummy_bitcoind, DummyDatabase::new(), desc);
+        let control = &ms.handle.control;
+        let mut db_conn = control.db().lock().unwrap().connection();
+        // The third coin isn't mature yet: the tip is at height 100.
+        db_conn.new_unspent_coins(
+            &[
+                (op_a, 100_000, 50),
+                (op_b, 200_000, 50),
+                (op_c, 300_000, 95),
+            ]
+            .iter()
+            .map(|(outpoint, value, height)| Coin {
+                outpoint: *outpoint,
+                block_height: Some(*height),
+                block_time: Some(1_000_000),
+                amount: bitcoin::Amount::from_sat(*value),
+                derivation_index: ChildNumber::from(13),
+                is_change: false,
+                spend_txid: None,
+                spend_block: None,
+                is_frozen: false,
+            })
+            .collect::<Vec<_>>(),
+        );
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let fee = |psbt: &Psbt| {
+            let in_value: u64 = psbt
+                .inputs
+                .iter()
+                .map(|psbt_in| psbt_in.witness_utxo.as_ref().unwrap().value)
+                .sum();
+            let out_value: u64 = psbt.unsigned_tx.output.iter().map(|txo| txo.value).sum();
+            in_value - out_value
+        };
+
+        // Bump a transaction spending through the recovery path. It's sized for this path.
+        let psbt = control
+            .create_recovery_spend(&[(dummy_addr.clone(), 50_000)], &[op_a], 1, None)
+            .unwrap()
+            .psbt;
+        let txid = psbt.unsigned_tx.txid();
+        db_conn.store_spend(&psbt);
+        db_conn.spend_coins(&[(op_a, txid)]);
+        let bumped = control.bump_fee(&txid, 10).unwrap().psbt;
+        let bumped_tx = &bumped.unsigned_tx;
+        assert_eq!(bumped_tx.input.len(), 1);
+        assert_eq!(bumped_tx.input[0].sequence, Sequence::from_height(10));
+        assert_eq!(bumped_tx.output.len(), 2);
+        assert_eq!(
+            fee(&bumped),
+            (bumped_tx.vsize() as u64 + recovery_sat_vb) * 10
+        );
+
+        // If coins must be added, only mature ones are and they use the same nSequence.
+        let bumped_txid = bumped_tx.txid();
+        let coin_a = db_conn.coins_by_outpoints(&[op_a])[&op_a];
+        db_conn.new_unspent_coins(&[Coin {
+            spend_txid: Some(bumped_txid),
+            ..coin_a
+        }]);
+        let rebumped = control.bump_fee(&bumped_txid, 1_000).unwrap().psbt;
+        let rebumped_tx = &rebumped.unsigned_tx;
+        assert_eq!(rebumped_tx.input.len(), 2);
+        assert_eq!(rebumped_tx.input[1].previous_output, op_b);
+        assert!(rebumped_tx
+            .input
+            .iter()
+            .all(|txin| txin.sequence == Sequence::from_height(10)));
+        assert!(fee(&rebumped) >= (rebumped_tx.vsize() as u64 + 2 * recovery_sat_vb) * 1_000);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_cpfp() {
+        let parent_txid =
+            Txid::from_str("3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810")
+                .unwrap();
+        let (op_a, op_b) = (OutPoint::new(parent_txid, 0), OutPoint::new(parent_txid, 1));
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            parent_txid,
+            (
+                Transaction {
+                    version: 2,
+                    lock_time: PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // Arguments sanity checking
+        assert_eq!(control.create_cpfp(&[], 10), Err(CommandError::NoOutpoint));
+        assert_eq!(
+            control.create_cpfp(&[op_a], 0),
+            Err(CommandError::InvalidFeerate(0))
+        );
+        assert_eq!(
+            control.create_cpfp(&[op_a], 10),
+            Err(CommandError::UnknownOutpoint(op_a))
+        );
+
+        // We can only accelerate unconfirmed coins whose parent is in the mempool.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(op_a, 100_000, None), (op_b, 50_000, Some(100))]
+                .iter()
+                .map(|(outpoint, value, block_height)| Coin {
+                    outpoint: *outpoint,
+                    block_height: *block_height,
+                    block_time: block_height.map(|_| 1_000_000),
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            control.create_cpfp(&[op_b], 10),
+            Err(CommandError::AlreadyConfirmed(op_b))
+        );
+        assert_eq!(
+            control.create_cpfp(&[op_a], 10),
+            Err(CommandError::NotInMempool(parent_txid))
+        );
+        ms.shutdown();
+
+        // The parent is 200vb and paid 400 sats of fees. It depends on an unconfirmed transaction
+        // of 100vb which paid 100 sats of fees. The child is 1 in (P2WSH satisfaction through the
+        // primary path), 1 out (P2WSH change). It pays for the package to reach 10sat/vb.
+        let other_parent_txid =
+            Txid::from_str("0b4a4f1ef2bad3e54f4ea76ab0fbbdd2ff30d22d4cb3dcd4e8ea40ba0b87e29c")
+                .unwrap();
+        let (op_c, op_d) = (
+            OutPoint::new(parent_txid, 2),
+            OutPoint::new(other_parent_txid, 0),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        for txid in &[parent_txid, other_parent_txid] {
+            dummy_bitcoind.txs.insert(
+                *txid,
+                (
+                    Transaction {
+                        version: 2,
+                        lock_time: PackedLockTime(0),
+                        input: vec![],
+                        output: vec![],
+                    },
+                    None,
+                ),
+            );
+        }
+        dummy_bitcoind.mempool.insert(
+            parent_txid,
+            MempoolEntry {
+                vsize: 200,
+                fees: bitcoin::Amount::from_sat(400),
+                ancestor_count: 2,
+                ancestor_vsize: 300,
+                ancestor_fees: bitcoin::Amount::from_sat(500),
+            },
+        );
+        dummy_bitcoind.mempool.insert(
+            other_parent_txid,
+            MempoolEntry {
+                vsize: 200,
+                fees: bitcoin::Amount::from_sat(400),
+                ancestor_count: 1,
+                ancestor_vsize: 200,
+                ancestor_fees: bitcoin::Amount::from_sat(400),
+            },
+        );
+        let mut dummy_db = DummyDatabase::new();
+        dummy_db.insert_coins(
+            [(op_a, false), (op_c, true), (op_d, false)]
+                .iter()
+                .map(|(outpoint, is_frozen)| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(100_000),
+                    derivation_index: ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: *is_frozen,
+                })
+                .collect(),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, dummy_db);
+        let control = &ms.handle.control;
+        let psbt = control.create_cpfp(&[op_a], 10).unwrap().psbt;
+        let tx = psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, op_a);
+        assert_eq!(tx.output.len(), 1);
+        assert!(!psbt.outputs[0].bip32_derivation.is_empty());
+        assert_eq!(tx.output[0].value, 100_000 - ((300 + 131) * 10 - 500));
+
+        // Frozen coins can't be spent.
+        assert_eq!(
+            control.create_cpfp(&[op_a, op_c], 10),
+            Err(CommandError::FrozenCoin(op_c))
+        );
+
+        // The unconfirmed ancestors of a parent may be shared with another parent, in which case
+        // we can't compute the size and fees of the package.
+        assert_eq!(
+            control.create_cpfp(&[op_a, op_d], 10),
+            Err(CommandError::UnconfirmedAncestors(parent_txid))
+        );
+        let psbt = control.create_cpfp(&[op_d], 10).unwrap().psbt;
+        assert_eq!(
+            psbt.unsigned_tx.output[0].value,
+            100_000 - ((200 + 131) * 10 - 400)
+        );
+
+        // If the parent already pays for a higher feerate, the child only pays for itself.
+        let psbt = control.create_cpfp(&[op_a], 1).unwrap().psbt;
+        assert_eq!(psbt.unsigned_tx.output[0].value, 100_000 - 131);
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn anti_fee_sniping() {
+        let tip_height = 100_000;
+        let dummy_tx = |n_inputs: usize| Transaction {
+            version: 2,
+            lock_time: PackedLockTime(0),
+            input: vec![
+                TxIn {
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    ..TxIn::default()
+                };
+                n_inputs
+            ],
+            output: vec![],
+        };
+        let is_afs_locktime = |tx: &Transaction| {
+            let lock_time = tx.lock_time.to_u32();
+            lock_time <= tip_height as u32 && lock_time > tip_height as u32 - 100
+        };
+
+        // Without Taproot, if the coin is unconfirmed or too old, or if there are several inputs,
+        // we always use the nLockTime.
+        for (heights, is_taproot) in &[
+            (vec![Some(99_000)], false),
+            (vec![None], true),
+            (vec![Some(10_000)], true),
+            (vec![Some(99_000), Some(99_990)], true),
+        ] {
+            for _ in 0..100 {
+                let mut tx = dummy_tx(heights.len());
+                set_anti_fee_sniping(&mut tx, heights, tip_height, *is_taproot);
+                assert!(is_afs_locktime(&tx));
+                assert!(tx
+                    .input
+                    .iter()
+                    .all(|txin| txin.sequence == Sequence::ENABLE_RBF_NO_LOCKTIME));
+            }
+        }
+
+        // With Taproot and a single input we sometimes use its nSequence instead. It's never more
+        // than the number of confirmations of its coin.
+        let heights = [Some(99_990)];
+        let (mut seen_locktime, mut seen_sequence) = (false, false);
+        for _ in 0..100 {
+            let mut tx = dummy_tx(heights.len());
+            set_anti_fee_sniping(&mut tx, &heights, tip_height, true);
+            if tx.lock_time == PackedLockTime::ZERO {
+                seen_sequence = true;
+                let seq = tx.input[0].sequence.to_consensus_u32();
+                assert!((1..=11).contains(&seq));
+            } else {
+                seen_locktime = true;
+                assert!(is_afs_locktime(&tx));
+                assert_eq!(tx.input[0].sequence, Sequence::ENABLE_RBF_NO_LOCKTIME);
+            }
+        }
+        assert!(seen_locktime && seen_sequence);
+    }
+
+    #[test]
+    fn taproot_spend_consistent_sequences() {
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let desc = descriptors::MultipathDescriptor::new_taproot(
+            dummy_owner_key(),
+            single_recovery_path(
+                descriptors::RecoveryTimelock::Relative(10_000),
+                dummy_heir_key(),
+            ),
+        )
+        .unwrap();
+        let ms = DummyLiana::with_descriptor(dummy_bitcoind, DummyDatabase::new(), desc.clone());
+        let control = &ms.handle.control;
+
+        // Two confirmed coins, recent enough that their nSequence could be used to discourage fee
+        // sniping.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(op_a, 60_000), (op_b, 100_000)]
+                .iter()
+                .map(|(outpoint, value)| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 90, 1_000_000), (op_b, 95, 1_000_000)]);
+
+        // A spend of both coins must always be consistent across inputs so we can analyze it.
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        for _ in 0..50 {
+            let psbt = control
+                .create_spend(
+                    &[(dummy_addr.clone(), 80_000)],
+                    &[op_a, op_b],
+                    1,
+                    None,
+                    false,
+                )
+                .unwrap()
+                .psbt;
+            assert_eq!(psbt.unsigned_tx.input.len(), 2);
+            assert_eq!(
+                psbt.unsigned_tx.input[0].sequence,
+                psbt.unsigned_tx.input[1].sequence
+            );
+            desc.partial_spend_info(&psbt).unwrap();
+        }
+
+        // If a single-input spend used the nSequence to discourage fee sniping, bumping it with
+        // an additional input switches to the nLockTime so the inputs' nSequences stay the same.
+        let psbt = (0..100)
+            .map(|_| {
+                control
+                    .create_spend(&[(dummy_addr.clone(), 10_000)], &[op_a], 1, None, false)
+                    .unwrap()
+                    .psbt
+            })
+            .find(|psbt| psbt.unsigned_tx.lock_time == bitcoin::PackedLockTime::ZERO)
+            .unwrap();
+        let txid = psbt.unsigned_tx.txid();
+        db_conn.store_spend(&psbt);
+        db_conn.spend_coins(&[(op_a, txid)]);
+        let bumped = control.bump_fee(&txid, 600).unwrap().psbt;
+        assert_eq!(bumped.unsigned_tx.input.len(), 2);
+        assert_ne!(bumped.unsigned_tx.lock_time, bitcoin::PackedLockTime::ZERO);
+        assert!(bumped
+            .unsigned_tx
+            .input
+            .iter()
+            .all(|txin| txin.sequence == bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME));
+        desc.partial_spend_info(&bumped).unwrap();
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_spend_coin_selection() {
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b, op_c) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+            bitcoin::OutPoint::new(dummy_txid, 2),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let mut destinations = vec![(dummy_addr, 50_000)];
+
+        // Without any coin, we can't select any.
+        assert_eq!(
+            control.create_spend(&destinations, &[], 1, None, false),
+            Err(CommandError::InsufficientFunds(
+                bitcoin::Amount::from_sat(0),
+                bitcoin::Amount::from_sat(50_000),
+                1
+            ))
+        );
+
+        // Two confirmed coins and an unconfirmed one. Only the former may be selected.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(op_a, 60_000), (op_b, 30_000), (op_c, 200_000)]
+                .iter()
+                .map(|(outpoint, value)| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 100, 1_000_000), (op_b, 100, 1_000_000)]);
+        let res = control
+            .create_spend(&destinations, &[], 1, None, false)
+            .unwrap();
+        assert_eq!(res.coins, vec![op_a]);
+        let tx = &res.psbt.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output, op_a);
+        assert_eq!(tx.output.len(), 2);
+
+        // Once the Spend is stored, its coin won't be selected anymore.
+        db_conn.store_spend(&res.psbt);
+        assert_eq!(
+            control.create_spend(&destinations, &[], 1, None, false),
+            Err(CommandError::InsufficientFunds(
+                bitcoin::Amount::from_sat(30_000),
+                bitcoin::Amount::from_sat(50_000),
+                1
+            ))
+        );
+        destinations[0].1 = 20_000;
+        let res = control
+            .create_spend(&destinations, &[], 1, None, false)
+            .unwrap();
+        assert_eq!(res.coins, vec![op_b]);
+
+        // When sending the remainder to an address, all the available coins are swept.
+        let addr = destinations[0].0.clone();
+        let res = control
+            .create_spend(&[], &[], 1, Some(&addr), false)
+            .unwrap();
+        assert_eq!(res.coins, vec![op_b]);
+        assert_eq!(res.psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(res.psbt.unsigned_tx.output[0].value, 30_000 - 119);
+
         ms.shutdown();
     }
 
     #[test]
-    fn getnewaddress() {
-        let ms = DummyLiana::new(DummyBitcoind::new(), DummyDatabase::new());
-
+    fn freeze_coins() {
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b, op_c) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+            bitcoin::OutPoint::new(dummy_txid, 2),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
         let control = &ms.handle.control;
-        // We can get an address
-        let addr = control.get_new_address().address;
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations = vec![(dummy_addr, 20_000)];
+
+        // Sanity check the outpoints.
         assert_eq!(
-            addr,
-            bitcoin::Address::from_str(
-                "bc1q9ksrc647hx8zp2cewl8p5f487dgux3777yees8rjcx46t4daqzzqt7yga8"
-            )
-            .unwrap()
+            control.freeze_coins(&[], true),
+            Err(CommandError::NoOutpoint)
         );
-        // We won't get the same twice.
-        let addr2 = control.get_new_address().address;
-        assert_ne!(addr, addr2);
+        assert_eq!(
+            control.freeze_coins(&[op_a], true),
+            Err(CommandError::UnknownOutpoint(op_a))
+        );
+
+        // Two confirmed coins and a spent one. The spent one can't be frozen.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[(op_a, 60_000), (op_b, 30_000), (op_c, 10_000)]
+                .iter()
+                .map(|(outpoint, value)| Coin {
+                    outpoint: *outpoint,
+                    block_height: None,
+                    block_time: None,
+                    amount: bitcoin::Amount::from_sat(*value),
+                    derivation_index: bip32::ChildNumber::from(13),
+                    is_change: false,
+                    spend_txid: None,
+                    spend_block: None,
+                    is_frozen: false,
+                })
+                .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[(op_a, 100, 1_000_000), (op_b, 100, 1_000_000)]);
+        db_conn.spend_coins(&[(op_c, dummy_txid)]);
+        assert_eq!(
+            control.freeze_coins(&[op_a, op_c], true),
+            Err(CommandError::AlreadySpent(op_c))
+        );
+
+        // Freeze the largest coin. It is reported as such and isn't selected anymore.
+        control.freeze_coins(&[op_a], true).unwrap();
+        let frozen: Vec<_> = control
+            .list_coins()
+            .coins
+            .into_iter()
+            .filter(|c| c.is_frozen)
+            .map(|c| c.outpoint)
+            .collect();
+        assert_eq!(frozen, vec![op_a]);
+        let res = control
+            .create_spend(&destinations, &[], 1, None, false)
+            .unwrap();
+        assert_eq!(res.coins, vec![op_b]);
+
+        // It can't be spent explicitly either, unless asked to.
+        assert_eq!(
+            control.create_spend(&destinations, &[op_a], 1, None, false),
+            Err(CommandError::FrozenCoin(op_a))
+        );
+        let res = control
+            .create_spend(&destinations, &[op_a], 1, None, true)
+            .unwrap();
+        assert_eq!(res.coins, vec![op_a]);
+
+        // Once unfrozen it's available again.
+        control.freeze_coins(&[op_a], false).unwrap();
+        assert!(control.list_coins().coins.iter().all(|c| !c.is_frozen));
+        control
+            .create_spend(&destinations, &[op_a], 1, None, false)
+            .unwrap();
 
         ms.shutdown();
     }
 
     #[test]
-    fn create_spend() {
+    fn create_recovery_spend() {
         let dummy_op = bitcoin::OutPoint::from_str(
             "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
         )
@@ -895,34 +4258,27 @@ mod tests {
         );
         let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
         let control = &ms.handle.control;
-
-        // Arguments sanity checking
         let dummy_addr =
             bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
-        let dummy_value = 10_000;
-        let mut destinations: HashMap<bitcoin::Address, u64> = [(dummy_addr.clone(), dummy_value)]
-            .iter()
-            .cloned()
-            .collect();
+        let destinations = vec![(dummy_addr, 20_000)];
+
+        // Coins must be given, and the recovery path must exist.
         assert_eq!(
-            control.create_spend(&destinations, &[], 1),
+            control.create_recovery_spend(&destinations, &[], 1, None),
             Err(CommandError::NoOutpoint)
         );
+        let timelock = descriptors::RecoveryTimelock::Relative(42);
         assert_eq!(
-            control.create_spend(&HashMap::new(), &[dummy_op], 1),
-            Err(CommandError::NoDestination)
-        );
-        assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 0),
-            Err(CommandError::InvalidFeerate(0))
+            control.create_recovery_spend(&destinations, &[dummy_op], 1, Some(timelock)),
+            Err(CommandError::UnknownRecoveryTimelock(timelock))
         );
-
-        // The coin doesn't exist. If we create a new unspent one at this outpoint with a much
-        // higher value, we'll get a Spend transaction with a change output.
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1),
+            control.create_recovery_spend(&destinations, &[dummy_op], 1, None),
             Err(CommandError::UnknownOutpoint(dummy_op))
         );
+
+        // The tip is at height 100 and the timelock is 10_000 blocks. Neither an unconfirmed
+        // coin nor a coin confirmed at height 1 can be spent through the recovery path.
         let mut db_conn = control.db().lock().unwrap().connection();
         db_conn.new_unspent_coins(&[Coin {
             outpoint: dummy_op,
@@ -933,90 +4289,208 @@ mod tests {
             is_change: false,
             spend_txid: None,
             spend_block: None,
+            is_frozen: false,
         }]);
-        let res = control.create_spend(&destinations, &[dummy_op], 1).unwrap();
-        assert!(res.psbt.inputs[0].non_witness_utxo.is_some());
-        let tx = res.psbt.unsigned_tx;
-        assert_eq!(tx.input.len(), 1);
-        assert_eq!(tx.input[0].previous_output, dummy_op);
-        assert_eq!(tx.output.len(), 2);
-        assert_eq!(tx.output[0].script_pubkey, dummy_addr.script_pubkey());
-        assert_eq!(tx.output[0].value, dummy_value);
-
-        // Transaction is 1 in (P2WSH satisfaction), 2 outs. At 1sat/vb, it's 171 sats fees.
-        // At 2sats/vb, it's twice that.
-        assert_eq!(tx.output[1].value, 89_829);
-        let res = control.create_spend(&destinations, &[dummy_op], 2).unwrap();
-        let tx = res.psbt.unsigned_tx;
-        assert_eq!(tx.output[1].value, 89_658);
-
-        // If we ask for a too high feerate, or a too large/too small output, it'll fail.
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 10_000),
-            Err(CommandError::InsufficientFunds(
-                bitcoin::Amount::from_sat(100_000),
-                bitcoin::Amount::from_sat(10_000),
-                10_000
-            ))
+            control.create_recovery_spend(&destinations, &[dummy_op], 1, None),
+            Err(CommandError::RecoveryNotMatured(dummy_op))
         );
-        *destinations.get_mut(&dummy_addr).unwrap() = 100_001;
+        db_conn.confirm_coins(&[(dummy_op, 1, 1_000_000)]);
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1),
-            Err(CommandError::InsufficientFunds(
-                bitcoin::Amount::from_sat(100_000),
-                bitcoin::Amount::from_sat(100_001),
-                1
-            ))
+            control.create_recovery_spend(&destinations, &[dummy_op], 1, None),
+            Err(CommandError::RecoveryNotMatured(dummy_op))
+        );
+
+        // Through the primary path it can be spent though.
+        control
+            .create_spend(&destinations, &[dummy_op], 1, None, false)
+            .unwrap();
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn create_refresh() {
+        let dummy_txid = bitcoin::Txid::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810",
+        )
+        .unwrap();
+        let (op_a, op_b, op_c, op_d) = (
+            bitcoin::OutPoint::new(dummy_txid, 0),
+            bitcoin::OutPoint::new(dummy_txid, 1),
+            bitcoin::OutPoint::new(dummy_txid, 2),
+            bitcoin::OutPoint::new(dummy_txid, 3),
+        );
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
         );
-        *destinations.get_mut(&dummy_addr).unwrap() = 4_500;
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+
+        // Either coins or a number of blocks must be given.
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1),
-            Err(CommandError::InvalidOutputValue(bitcoin::Amount::from_sat(
-                4_500
-            )))
+            control.create_refresh(&[], None, 1),
+            Err(CommandError::NoOutpoint)
         );
 
-        // If we ask to create an output for an address from another network, it will fail.
-        let invalid_addr = bitcoin::Address {
-            network: bitcoin::Network::Testnet,
-            payload: dummy_addr.payload.clone(),
-        };
-        let invalid_destinations: HashMap<bitcoin::Address, u64> =
-            [(invalid_addr.clone(), dummy_value)]
-                .iter()
-                .cloned()
-                .collect();
+        // The tip is at height 100 and the timelock is 10_000 blocks. The recovery path for the
+        // first coin becomes available at height 10_001, at 10_050 for the second one. The third
+        // one is unconfirmed and the last one is frozen.
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(
+            &[
+                (op_a, 60_000),
+                (op_b, 30_000),
+                (op_c, 20_000),
+                (op_d, 10_000),
+            ]
+            .iter()
+            .map(|(outpoint, value)| Coin {
+                outpoint: *outpoint,
+                block_height: None,
+                block_time: None,
+                amount: bitcoin::Amount::from_sat(*value),
+                derivation_index: bip32::ChildNumber::from(13),
+                is_change: false,
+                spend_txid: None,
+                spend_block: None,
+                is_frozen: false,
+            })
+            .collect::<Vec<_>>(),
+        );
+        db_conn.confirm_coins(&[
+            (op_a, 1, 1_000_000),
+            (op_b, 50, 1_000_000),
+            (op_d, 1, 1_000_000),
+        ]);
+        control.freeze_coins(&[op_d], true).unwrap();
         assert_eq!(
-            control.create_spend(&invalid_destinations, &[dummy_op], 1),
-            Err(CommandError::AddressNetwork(
-                invalid_addr,
-                bitcoin::Network::Bitcoin
-            ))
+            control.create_refresh(&[], Some(9_900), 1),
+            Err(CommandError::NoExpiringCoin(9_900))
         );
 
-        // If we ask for a large, but valid, output we won't get a change output. 95_000 because we
-        // won't create an output lower than 5k sats.
-        *destinations.get_mut(&dummy_addr).unwrap() = 95_000;
-        let res = control.create_spend(&destinations, &[dummy_op], 1).unwrap();
-        let tx = res.psbt.unsigned_tx;
+        // The first coin is consolidated into a change output, and the transaction is stored.
+        let res = control.create_refresh(&[], Some(9_901), 1).unwrap();
+        assert_eq!(res.coins, vec![op_a]);
+        let tx = &res.psbt.unsigned_tx;
         assert_eq!(tx.input.len(), 1);
-        assert_eq!(tx.input[0].previous_output, dummy_op);
+        assert_eq!(tx.input[0].previous_output, op_a);
         assert_eq!(tx.output.len(), 1);
-        assert_eq!(tx.output[0].script_pubkey, dummy_addr.script_pubkey());
-        assert_eq!(tx.output[0].value, 95_000);
+        assert!(tx.output[0].script_pubkey.is_v0_p2wsh());
+        assert!((59_800..60_000).contains(&tx.output[0].value));
+        assert_eq!(db_conn.spend_tx(&tx.txid()), Some(res.psbt.clone()));
 
-        // Now if we mark the coin as spent, we won't create another Spend transaction containing
-        // it.
-        db_conn.spend_coins(&[(
-            dummy_op,
-            bitcoin::Txid::from_str(
-                "ef78f79ba747813887747cf8582897a48f1a09f1ca04d2cd3d6fcfdcbb5e0797",
+        // Now that it is spent by a draft, it isn't refreshed again.
+        let res = control.create_refresh(&[], Some(9_950), 1).unwrap();
+        assert_eq!(res.coins, vec![op_b]);
+
+        // Coins may be given explicitly, but not frozen ones.
+        let res = control.create_refresh(&[op_c], None, 1).unwrap();
+        assert_eq!(res.coins, vec![op_c]);
+        assert_eq!(
+            control.create_refresh(&[op_d], None, 1),
+            Err(CommandError::FrozenCoin(op_d))
+        );
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn analyze_psbt() {
+        let dummy_op = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
+        )
+        .unwrap();
+        let mut dummy_bitcoind = DummyBitcoind::new();
+        dummy_bitcoind.txs.insert(
+            dummy_op.txid,
+            (
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: bitcoin::PackedLockTime(0),
+                    input: vec![],
+                    output: vec![],
+                },
+                None,
+            ),
+        );
+        let ms = DummyLiana::new(dummy_bitcoind, DummyDatabase::new());
+        let control = &ms.handle.control;
+        let mut db_conn = control.db().lock().unwrap().connection();
+        db_conn.new_unspent_coins(&[Coin {
+            outpoint: dummy_op,
+            block_height: None,
+            block_time: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_frozen: false,
+        }]);
+        let dummy_addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+        let destinations = vec![(dummy_addr.clone(), 10_000)];
+        let mut psbt = control
+            .create_spend(&destinations, &[dummy_op], 1, None, false)
+            .unwrap()
+            .psbt;
+
+        // The input is ours and unsigned. Only the primary path is available.
+        let res = control.analyze_psbt(&psbt).unwrap();
+        assert_eq!(res.inputs.len(), 1);
+        assert!(res.inputs[0].is_mine);
+        assert_eq!(
+            res.inputs[0].amount,
+            Some(bitcoin::Amount::from_sat(100_000))
+        );
+        assert_eq!(res.inputs[0].paths.len(), 1);
+        let primary_path = &res.inputs[0].paths[0];
+        assert_eq!(primary_path.path, descriptors::SpendingPath::Primary);
+        assert_eq!(primary_path.threshold, 1);
+        assert!(primary_path.signed.is_empty());
+        assert_eq!(
+            primary_path.missing,
+            vec![bip32::Fingerprint::from_str("aabbccdd").unwrap()]
+        );
+        assert_eq!(res.outputs.len(), 2);
+        assert_eq!(res.outputs[0].address, Some(dummy_addr));
+        assert!(!res.outputs[0].is_mine);
+        assert_eq!(res.fee, Some(bitcoin::Amount::from_sat(162)));
+        assert_eq!(res.feerate_vb, Some(1));
+        assert!(res.inconsistency.is_none());
+
+        // If an input isn't ours and its value is unknown, we can't compute the fees.
+        psbt.unsigned_tx.input.push(bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::from_str(
+                "ef78f79ba747813887747cf8582897a48f1a09f1ca04d2cd3d6fcfdcbb5e0797:1",
             )
             .unwrap(),
-        )]);
+            ..bitcoin::TxIn::default()
+        });
+        psbt.inputs.push(PsbtIn::default());
+        let res = control.analyze_psbt(&psbt).unwrap();
+        assert!(!res.inputs[1].is_mine);
+        assert!(res.inputs[1].amount.is_none());
+        assert!(res.inputs[1].paths.is_empty());
+        assert!(res.fee.is_none());
+        assert!(res.feerate_vb.is_none());
+
+        // The PSBT must not be malformed.
+        psbt.inputs.pop();
         assert_eq!(
-            control.create_spend(&destinations, &[dummy_op], 1),
-            Err(CommandError::AlreadySpent(dummy_op))
+            control.analyze_psbt(&psbt),
+            Err(CommandError::MalformedPsbt)
         );
 
         ms.shutdown();
@@ -1058,6 +4532,7 @@ mod tests {
                 is_change: false,
                 spend_txid: None,
                 spend_block: None,
+                is_frozen: false,
             },
             Coin {
                 outpoint: dummy_op_b,
@@ -1068,6 +4543,7 @@ mod tests {
                 is_change: false,
                 spend_txid: None,
                 spend_block: None,
+                is_frozen: false,
             },
         ]);
 
@@ -1078,33 +4554,21 @@ mod tests {
             bitcoin::Address::from_str("bc1q39srgatmkp6k2ne3l52yhkjprdvunvspqydmkx").unwrap();
         let dummy_value_a = 50_000;
         let dummy_value_b = 60_000;
-        let destinations_a: HashMap<bitcoin::Address, u64> =
-            [(dummy_addr_a.clone(), dummy_value_a)]
-                .iter()
-                .cloned()
-                .collect();
-        let destinations_b: HashMap<bitcoin::Address, u64> =
-            [(dummy_addr_b.clone(), dummy_value_b)]
-                .iter()
-                .cloned()
-                .collect();
-        let destinations_c: HashMap<bitcoin::Address, u64> =
-            [(dummy_addr_a, dummy_value_a), (dummy_addr_b, dummy_value_b)]
-                .iter()
-                .cloned()
-                .collect();
+        let destinations_a = vec![(dummy_addr_a.clone(), dummy_value_a)];
+        let destinations_b = vec![(dummy_addr_b.clone(), dummy_value_b)];
+        let destinations_c = vec![(dummy_addr_a, dummy_value_a), (dummy_addr_b, dummy_value_b)];
         let mut psbt_a = control
-            .create_spend(&destinations_a, &[dummy_op_a], 1)
+            .create_spend(&destinations_a, &[dummy_op_a], 1, None, false)
             .unwrap()
             .psbt;
         let txid_a = psbt_a.unsigned_tx.txid();
         let psbt_b = control
-            .create_spend(&destinations_b, &[dummy_op_b], 10)
+            .create_spend(&destinations_b, &[dummy_op_b], 10, None, false)
             .unwrap()
             .psbt;
         let txid_b = psbt_b.unsigned_tx.txid();
         let psbt_c = control
-            .create_spend(&destinations_c, &[dummy_op_a, dummy_op_b], 100)
+            .create_spend(&destinations_c, &[dummy_op_a, dummy_op_b], 100, None, false)
             .unwrap()
             .psbt;
         let txid_c = psbt_c.unsigned_tx.txid();
@@ -1239,6 +4703,7 @@ mod tests {
                 derivation_index: ChildNumber::from(0),
                 amount: bitcoin::Amount::from_sat(100_000_000),
                 spend_txid: Some(spend_tx.txid()),
+                is_frozen: false,
             },
             // Deposit 2
             Coin {
@@ -1253,6 +4718,7 @@ mod tests {
                 derivation_index: ChildNumber::from(1),
                 amount: bitcoin::Amount::from_sat(2000),
                 spend_txid: None,
+                is_frozen: false,
             },
             // This coin is a change output.
             Coin {
@@ -1264,6 +4730,7 @@ mod tests {
                 derivation_index: ChildNumber::from(2),
                 amount: bitcoin::Amount::from_sat(100_000_000 - 4000 - 1000),
                 spend_txid: None,
+                is_frozen: false,
             },
             // Deposit 3
             Coin {
@@ -1278,6 +4745,7 @@ mod tests {
                 derivation_index: ChildNumber::from(3),
                 amount: bitcoin::Amount::from_sat(3000),
                 spend_txid: None,
+                is_frozen: false,
             },
         ]);
 
@@ -1493,6 +4961,114 @@ mod tests {
         assert!(txs.contains(&tx2));
         assert!(txs.contains(&tx3));
 
+        // Transactions are returned along with their label.
+        let mut items = HashMap::new();
+        items.insert(LabelItem::Txid(tx2.txid()), Some("rent".to_string()));
+        control.update_labels(&items).unwrap();
+        let transactions = control
+            .list_transactions(&[tx1.txid(), tx2.txid()])
+            .transactions;
+        assert_eq!(transactions[0].label, None);
+        assert_eq!(transactions[1].label, Some("rent".to_string()));
+
+        ms.shutdown();
+    }
+
+    #[test]
+    fn labels() {
+        let dummy_op = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0",
+        )
+        .unwrap();
+        let mut db = DummyDatabase::new();
+        db.insert_coins(vec![Coin {
+            outpoint: dummy_op,
+            block_height: None,
+            block_time: None,
+            amount: bitcoin::Amount::from_sat(100_000),
+            derivation_index: bip32::ChildNumber::from(13),
+            is_change: false,
+            spend_txid: None,
+            spend_block: None,
+            is_frozen: false,
+        }]);
+        let ms = DummyLiana::new(DummyBitcoind::new(), db);
+        let control = &ms.handle.control;
+        let addr =
+            bitcoin::Address::from_str("bc1qnsexk3gnuyayu92fc3tczvc7k62u22a22ua2kv").unwrap();
+
+        // We can label coins, addresses and transactions. Labeled coins are listed along with
+        // their label.
+        let mut items = HashMap::new();
+        items.insert(
+            LabelItem::OutPoint(dummy_op),
+            Some("invoice 42".to_string()),
+        );
+        items.insert(LabelItem::Address(addr.clone()), Some("Bob".to_string()));
+        items.insert(LabelItem::Txid(dummy_op.txid), Some("from Bob".to_string()));
+        control.update_labels(&items).unwrap();
+        assert_eq!(
+            control.list_coins().coins[0].label,
+            Some("invoice 42".to_string())
+        );
+        let labels = control.get_labels(&items.keys().cloned().collect()).labels;
+        assert_eq!(labels.len(), 3);
+        assert_eq!(labels[&dummy_op.to_string()], "invoice 42");
+        assert_eq!(labels[&addr.to_string()], "Bob");
+
+        // We can't label an address for another network.
+        let invalid_addr = bitcoin::Address {
+            network: bitcoin::Network::Testnet,
+            payload: addr.payload.clone(),
+        };
+        let mut invalid_items = HashMap::new();
+        invalid_items.insert(
+            LabelItem::Address(invalid_addr.clone()),
+            Some("".to_string()),
+        );
+        assert_eq!(
+            control.update_labels(&invalid_items),
+            Err(CommandError::AddressNetwork(
+                invalid_addr,
+                bitcoin::Network::Bitcoin
+            ))
+        );
+
+        // Export the labels, remove them, and import them back.
+        let export = control.export_labels().labels;
+        assert_eq!(export.lines().count(), 3);
+        let deletion = items.keys().map(|item| (item.clone(), None)).collect();
+        control.update_labels(&deletion).unwrap();
+        assert!(control.list_coins().coins[0].label.is_none());
+        let bip329 = format!(
+            "{}\n{}\n{}\n",
+            export,
+            r#"{"type":"input","ref":"3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:0","label":"ignored"}"#,
+            r#"{"type":"tx","ref":"3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810"}"#
+        );
+        assert_eq!(
+            control.import_labels(&bip329),
+            Ok(ImportLabelsResult {
+                imported: 3,
+                skipped: 2
+            })
+        );
+        assert_eq!(control.export_labels().labels.lines().count(), 3);
+        assert_eq!(
+            control.list_coins().coins[0].label,
+            Some("invoice 42".to_string())
+        );
+
+        // Malformed records are refused.
+        assert!(matches!(
+            control.import_labels("{\"type\":\"tx\",\"ref\":\"deadbeef\",\"label\":\"a\"}"),
+            Err(CommandError::MalformedLabels(1, _))
+        ));
+        assert!(matches!(
+            control.import_labels("\nnot json"),
+            Err(CommandError::MalformedLabels(2, _))
+        ));
+
         ms.shutdown();
     }
 }
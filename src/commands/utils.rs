@@ -15,6 +15,28 @@ where
     Ok(bitcoin::Amount::from_sat(a))
 }
 
+/// Serialize an optional amount as sats
+pub fn ser_optional_amount<S: Serializer>(
+    amount: &Option<bitcoin::Amount>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match amount {
+        Some(amount) => s.serialize_some(&amount.to_sat()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Deserialize an optional amount from sats
+pub fn deser_optional_amount_from_sats<'de, D>(
+    deserializer: D,
+) -> Result<Option<bitcoin::Amount>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let a = Option::<u64>::deserialize(deserializer)?;
+    Ok(a.map(bitcoin::Amount::from_sat))
+}
+
 pub fn ser_base64<S, T>(t: T, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -124,7 +124,7 @@ impl std::fmt::Display for ConfigError {
             Self::ReadingFile(e) => write!(f, "Failed to read configuration file: {}", e),
             Self::UnexpectedDescriptor(desc) => write!(
                 f,
-                "Unexpected descriptor '{}'. We only support wsh() descriptors for now.",
+                "Unexpected descriptor '{}'. We only support wsh() and tr() descriptors.",
                 desc
             ),
             Self::Unexpected(e) => write!(f, "Configuration error: {}", e),
@@ -11,7 +11,12 @@ use crate::{
     },
 };
 
-use std::{collections::HashMap, sync};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync,
+};
 
 use miniscript::bitcoin::{
     self, secp256k1,
@@ -98,6 +103,9 @@ pub trait DatabaseConnection {
     /// Mark a set of coins as spent by a specified txid at a specified block time.
     fn confirm_spend(&mut self, outpoints: &[(bitcoin::OutPoint, bitcoin::Txid, i32, u32)]);
 
+    /// Mark a set of coins as frozen, or unfreeze them.
+    fn freeze_coins(&mut self, outpoints: &[bitcoin::OutPoint], frozen: bool);
+
     /// Get specific coins from the database.
     fn coins_by_outpoints(
         &mut self,
@@ -109,6 +117,12 @@ pub trait DatabaseConnection {
     /// Insert a new Spend transaction or replace an existing one.
     fn store_spend(&mut self, psbt: &Psbt);
 
+    /// Store a Spend transaction replacing an existing one, and mark the latter as replaced.
+    fn replace_spend(&mut self, replaced_txid: &bitcoin::Txid, psbt: &Psbt);
+
+    /// Get the txid of the Spend transaction which replaced this one, if any.
+    fn spend_replacement(&mut self, txid: &bitcoin::Txid) -> Option<bitcoin::Txid>;
+
     /// List all existing Spend transactions.
     fn list_spend(&mut self) -> Vec<Psbt>;
 
@@ -120,6 +134,15 @@ pub trait DatabaseConnection {
 
     /// Retrieve a limited list of txids that where deposited or spent between the start and end timestamps (inclusive bounds)
     fn list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid>;
+
+    /// Set the label of these items, or remove it if None.
+    fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>);
+
+    /// Get the label of these items, for those which have one.
+    fn labels(&mut self, items: &HashSet<LabelItem>) -> HashMap<LabelItem, String>;
+
+    /// List all the labels, by order of creation.
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)>;
 }
 
 impl DatabaseConnection for SqliteConn {
@@ -208,6 +231,10 @@ impl DatabaseConnection for SqliteConn {
         self.confirm_spend(outpoints)
     }
 
+    fn freeze_coins(&mut self, outpoints: &[bitcoin::OutPoint], frozen: bool) {
+        self.freeze_coins(outpoints, frozen)
+    }
+
     fn derivation_index_by_address(
         &mut self,
         address: &bitcoin::Address,
@@ -234,6 +261,15 @@ impl DatabaseConnection for SqliteConn {
         self.store_spend(psbt)
     }
 
+    fn replace_spend(&mut self, replaced_txid: &bitcoin::Txid, psbt: &Psbt) {
+        self.replace_spend(replaced_txid, psbt)
+    }
+
+    fn spend_replacement(&mut self, txid: &bitcoin::Txid) -> Option<bitcoin::Txid> {
+        self.db_spend(txid)
+            .and_then(|db_spend| db_spend.replaced_by)
+    }
+
     fn list_spend(&mut self) -> Vec<Psbt> {
         self.list_spend()
             .into_iter()
@@ -252,6 +288,59 @@ impl DatabaseConnection for SqliteConn {
     fn list_txids(&mut self, start: u32, end: u32, limit: u64) -> Vec<bitcoin::Txid> {
         self.db_list_txids(start, end, limit)
     }
+
+    fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
+        self.update_labels(items)
+    }
+
+    fn labels(&mut self, items: &HashSet<LabelItem>) -> HashMap<LabelItem, String> {
+        self.db_labels(items)
+            .into_iter()
+            .map(|db_label| (db_label.item, db_label.value))
+            .collect()
+    }
+
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)> {
+        self.db_list_labels()
+            .into_iter()
+            .map(|db_label| (db_label.item, db_label.value))
+            .collect()
+    }
+}
+
+/// Something we can attach a label to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LabelItem {
+    Address(bitcoin::Address),
+    Txid(bitcoin::Txid),
+    OutPoint(bitcoin::OutPoint),
+}
+
+impl fmt::Display for LabelItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Address(addr) => write!(f, "{}", addr),
+            Self::Txid(txid) => write!(f, "{}", txid),
+            Self::OutPoint(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+impl FromStr for LabelItem {
+    type Err = String;
+
+    /// Parse an outpoint as `txid:vout`, a txid, or an address. These formats don't overlap.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(op) = bitcoin::OutPoint::from_str(s) {
+            Ok(Self::OutPoint(op))
+        } else if let Ok(txid) = bitcoin::Txid::from_str(s) {
+            Ok(Self::Txid(txid))
+        } else if let Ok(addr) = bitcoin::Address::from_str(s) {
+            Ok(Self::Address(addr))
+        } else {
+            Err(format!("Invalid label item '{}'.", s))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -279,6 +368,8 @@ pub struct Coin {
     pub is_change: bool,
     pub spend_txid: Option<bitcoin::Txid>,
     pub spend_block: Option<SpendBlock>,
+    /// Whether the user asked for this coin to not be spent.
+    pub is_frozen: bool,
 }
 
 impl std::convert::From<DbCoin> for Coin {
@@ -292,6 +383,7 @@ impl std::convert::From<DbCoin> for Coin {
             is_change,
             spend_txid,
             spend_block,
+            is_frozen,
             ..
         } = db_coin;
         Coin {
@@ -303,6 +395,7 @@ impl std::convert::From<DbCoin> for Coin {
             is_change,
             spend_txid,
             spend_block: spend_block.map(SpendBlock::from),
+            is_frozen,
         }
     }
 }
@@ -13,15 +13,23 @@ use crate::{
     bitcoin::BlockChainTip,
     database::{
         sqlite::{
-            schema::{DbAddress, DbCoin, DbSpendTransaction, DbTip, DbWallet},
-            utils::{create_fresh_db, db_exec, db_query, db_tx_query, LOOK_AHEAD_LIMIT},
+            schema::{DbAddress, DbCoin, DbLabel, DbSpendTransaction, DbTip, DbWallet},
+            utils::{
+                create_fresh_db, db_exec, db_query, db_tx_query, maybe_apply_migration,
+                LOOK_AHEAD_LIMIT,
+            },
         },
-        Coin, CoinType,
+        Coin, CoinType, LabelItem,
     },
     descriptors::MultipathDescriptor,
 };
 
-use std::{cmp, convert::TryInto, fmt, io, path};
+use std::{
+    cmp,
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+    fmt, io, path,
+};
 
 use miniscript::bitcoin::{
     self,
@@ -31,7 +39,7 @@ use miniscript::bitcoin::{
     util::{bip32, psbt::PartiallySignedTransaction as Psbt},
 };
 
-const DB_VERSION: i64 = 0;
+const DB_VERSION: i64 = 3;
 
 #[derive(Debug)]
 pub enum SqliteDbError {
@@ -107,6 +115,7 @@ impl SqliteDb {
         if !db_path.exists() {
             return Err(SqliteDbError::FileNotFound(db_path));
         }
+        maybe_apply_migration(&db_path)?;
 
         Ok(SqliteDb { db_path })
     }
@@ -126,8 +135,8 @@ impl SqliteDb {
     ) -> Result<(), SqliteDbError> {
         let mut conn = self.connection()?;
 
-        // Check if there database isn't from the future.
-        // NOTE: we'll do migration there eventually. Until then be strict on the check.
+        // Check if there database isn't from the future. Older databases were upgraded when
+        // opening them, therefore be strict on the check.
         let db_version = conn.db_version();
         if db_version != DB_VERSION {
             return Err(SqliteDbError::UnsupportedVersion(db_version));
@@ -399,6 +408,25 @@ impl SqliteConn {
         .expect("Database must be available")
     }
 
+    /// Mark a set of coins as frozen, or unfreeze them.
+    pub fn freeze_coins<'a>(
+        &mut self,
+        outpoints: impl IntoIterator<Item = &'a bitcoin::OutPoint>,
+        frozen: bool,
+    ) {
+        db_exec(&mut self.conn, |db_tx| {
+            for outpoint in outpoints {
+                db_tx.execute(
+                    "UPDATE coins SET is_frozen = ?1 WHERE txid = ?2 AND vout = ?3",
+                    rusqlite::params![frozen, outpoint.txid.to_vec(), outpoint.vout,],
+                )?;
+            }
+
+            Ok(())
+        })
+        .expect("Database must be available")
+    }
+
     /// Mark the Spend transaction of a given set of coins as being confirmed at a given
     /// block.
     pub fn confirm_spend<'a>(
@@ -484,6 +512,26 @@ impl SqliteConn {
         .expect("Db must not fail");
     }
 
+    /// Insert a Spend transaction replacing an existing one, and mark the latter as replaced.
+    pub fn replace_spend(&mut self, replaced_txid: &bitcoin::Txid, psbt: &Psbt) {
+        let txid = psbt.unsigned_tx.txid().to_vec();
+        let psbt = encode::serialize(psbt);
+
+        db_exec(&mut self.conn, |db_tx| {
+            db_tx.execute(
+                "INSERT into spend_transactions (psbt, txid) VALUES (?1, ?2) \
+                 ON CONFLICT DO UPDATE SET psbt=excluded.psbt",
+                rusqlite::params![psbt, txid],
+            )?;
+            db_tx.execute(
+                "UPDATE spend_transactions SET replaced_by_txid = ?1 WHERE txid = ?2",
+                rusqlite::params![txid, replaced_txid.to_vec()],
+            )?;
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
     pub fn list_spend(&mut self) -> Vec<DbSpendTransaction> {
         db_query(
             &mut self.conn,
@@ -537,6 +585,49 @@ impl SqliteConn {
         .expect("Db must not fail");
     }
 
+    /// Set the label of these items, or remove it if None.
+    pub fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
+        db_exec(&mut self.conn, |db_tx| {
+            for (item, value) in items {
+                if let Some(value) = value {
+                    db_tx.execute(
+                        "INSERT INTO labels (wallet_id, item, value) VALUES (?1, ?2, ?3) \
+                         ON CONFLICT DO UPDATE SET value=excluded.value",
+                        rusqlite::params![WALLET_ID, item.to_string(), value],
+                    )?;
+                } else {
+                    db_tx.execute(
+                        "DELETE FROM labels WHERE item = ?1",
+                        rusqlite::params![item.to_string()],
+                    )?;
+                }
+            }
+            Ok(())
+        })
+        .expect("Db must not fail");
+    }
+
+    pub fn db_labels(&mut self, items: &HashSet<LabelItem>) -> Vec<DbLabel> {
+        // NOTE: the string representation of an item never contains a quote.
+        let items: Vec<String> = items.iter().map(|item| format!("'{}'", item)).collect();
+        let query = format!("SELECT * FROM labels WHERE item IN ({})", items.join(", "));
+
+        db_query(&mut self.conn, &query, rusqlite::params![], |row| {
+            row.try_into()
+        })
+        .expect("Db must not fail")
+    }
+
+    pub fn db_list_labels(&mut self) -> Vec<DbLabel> {
+        db_query(
+            &mut self.conn,
+            "SELECT * FROM labels ORDER BY id",
+            rusqlite::params![],
+            |row| row.try_into(),
+        )
+        .expect("Db must not fail")
+    }
+
     /// Unconfirm all data that was marked as being confirmed *after* the given chain
     /// tip, and set it as our new best block seen.
     ///
@@ -701,6 +792,7 @@ mod tests {
                 is_change: false,
                 spend_txid: None,
                 spend_block: None,
+                is_frozen: false,
             };
             conn.new_unspent_coins(&[coin_a]);
             assert_eq!(conn.coins(CoinType::All)[0].outpoint, coin_a.outpoint);
@@ -727,6 +819,7 @@ mod tests {
                 is_change: true,
                 spend_txid: None,
                 spend_block: None,
+                is_frozen: false,
             };
             conn.new_unspent_coins(&[coin_b]);
             let outpoints: HashSet<bitcoin::OutPoint> = conn
@@ -817,6 +910,14 @@ mod tests {
             assert!(coin.spend_block.is_some());
             assert_eq!(coin.spend_block.as_ref().unwrap().time, time);
             assert_eq!(coin.spend_block.unwrap().height, height);
+
+            // Coins aren't frozen by default, but we can freeze and unfreeze them.
+            assert!(!conn.db_coins(&[coin_b.outpoint])[0].is_frozen);
+            conn.freeze_coins(&[coin_b.outpoint], true);
+            assert!(conn.db_coins(&[coin_b.outpoint])[0].is_frozen);
+            assert!(!conn.db_coins(&[coin_a.outpoint])[0].is_frozen);
+            conn.freeze_coins(&[coin_b.outpoint], false);
+            assert!(!conn.db_coins(&[coin_b.outpoint])[0].is_frozen);
         }
 
         fs::remove_dir_all(tmp_dir).unwrap();
@@ -954,6 +1055,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -967,6 +1069,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -988,6 +1091,7 @@ mod tests {
                         height: 101_199,
                         time: 1_231_678,
                     }),
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1001,6 +1105,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1022,6 +1127,7 @@ mod tests {
                         height: 101_105,
                         time: 1_201_678,
                     }),
+                    is_frozen: false,
                 },
             ];
             conn.new_unspent_coins(&coins);
@@ -1165,6 +1271,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1178,6 +1285,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1199,6 +1307,7 @@ mod tests {
                         height: 101_199,
                         time: 1_123_000,
                     }),
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1212,6 +1321,7 @@ mod tests {
                     is_change: false,
                     spend_txid: None,
                     spend_block: None,
+                    is_frozen: false,
                 },
                 Coin {
                     outpoint: bitcoin::OutPoint::from_str(
@@ -1233,6 +1343,7 @@ mod tests {
                         height: 101_105,
                         time: 1_126_000,
                     }),
+                    is_frozen: false,
                 },
             ];
             conn.new_unspent_coins(&coins);
@@ -1297,4 +1408,123 @@ mod tests {
 
         fs::remove_dir_all(tmp_dir).unwrap();
     }
+
+    #[test]
+    fn sqlite_spend_replacement() {
+        let (tmp_dir, options, secp, db) = dummy_db();
+        let dummy_psbt = |lock_time: u32| {
+            Psbt::from_unsigned_tx(bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(lock_time),
+                input: vec![bitcoin::TxIn::default()],
+                output: vec![],
+            })
+            .unwrap()
+        };
+        let (psbt_a, psbt_b) = (dummy_psbt(0), dummy_psbt(1));
+        let (txid_a, txid_b) = (psbt_a.unsigned_tx.txid(), psbt_b.unsigned_tx.txid());
+
+        {
+            let mut conn = db.connection().unwrap();
+            conn.store_spend(&psbt_a);
+            assert!(conn.db_spend(&txid_a).unwrap().replaced_by.is_none());
+
+            // Replacing it stores the new one and marks the former as replaced.
+            conn.replace_spend(&txid_a, &psbt_b);
+            assert_eq!(conn.db_spend(&txid_a).unwrap().replaced_by, Some(txid_b));
+            let db_spend_b = conn.db_spend(&txid_b).unwrap();
+            assert_eq!(db_spend_b.psbt, psbt_b);
+            assert!(db_spend_b.replaced_by.is_none());
+        }
+
+        // A database at version 0 is upgraded when opened.
+        let db_path = db.db_path.clone();
+        {
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "ALTER TABLE spend_transactions DROP COLUMN replaced_by_txid;\
+                 DROP TABLE labels;\
+                 ALTER TABLE coins DROP COLUMN is_frozen;\
+                 UPDATE version SET version = 0;",
+            )
+            .unwrap();
+        }
+        let db = SqliteDb::new(db_path, None, &secp).unwrap();
+        db.sanity_check(bitcoin::Network::Bitcoin, &options.main_descriptor)
+            .unwrap();
+        {
+            let mut conn = db.connection().unwrap();
+            assert_eq!(conn.db_version(), DB_VERSION);
+            assert_eq!(conn.list_spend().len(), 2);
+            assert!(conn.db_spend(&txid_a).unwrap().replaced_by.is_none());
+            conn.replace_spend(&txid_a, &psbt_b);
+            assert_eq!(conn.db_spend(&txid_a).unwrap().replaced_by, Some(txid_b));
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    #[test]
+    fn sqlite_labels() {
+        let (tmp_dir, options, secp, db) = dummy_db();
+        let txid = bitcoin::Txid::from_str(
+            "0c62a990d20d54429e70859292e82374ba6b1b951a3ab60f26bb65fee5724ff7",
+        )
+        .unwrap();
+        let outpoint = bitcoin::OutPoint::new(txid, 1);
+        let address = options
+            .main_descriptor
+            .receive_descriptor()
+            .derive(0.into(), &secp)
+            .address(options.bitcoind_network);
+        let (item_txid, item_op, item_addr) = (
+            LabelItem::Txid(txid),
+            LabelItem::OutPoint(outpoint),
+            LabelItem::Address(address),
+        );
+
+        {
+            let mut conn = db.connection().unwrap();
+            assert!(conn.db_list_labels().is_empty());
+
+            // We can set labels, and get them back.
+            let mut items = HashMap::new();
+            items.insert(item_txid.clone(), Some("payroll".to_string()));
+            items.insert(item_op.clone(), Some("change from payroll".to_string()));
+            conn.update_labels(&items);
+            let labels = conn.db_labels(&[item_txid.clone()].iter().cloned().collect());
+            assert_eq!(labels.len(), 1);
+            assert_eq!(labels[0].item, item_txid);
+            assert_eq!(labels[0].value, "payroll");
+            let labels = conn.db_labels(
+                &[item_op.clone(), item_addr.clone()]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            );
+            assert_eq!(labels.len(), 1);
+            assert_eq!(labels[0].item, item_op);
+
+            // Updating a label overwrites it, and we can delete labels.
+            let mut items = HashMap::new();
+            items.insert(item_txid.clone(), Some("salaries".to_string()));
+            items.insert(item_op.clone(), None);
+            items.insert(item_addr.clone(), Some("cold storage".to_string()));
+            conn.update_labels(&items);
+            let labels: Vec<(LabelItem, String)> = conn
+                .db_list_labels()
+                .into_iter()
+                .map(|l| (l.item, l.value))
+                .collect();
+            assert_eq!(
+                labels,
+                vec![
+                    (item_txid, "salaries".to_string()),
+                    (item_addr, "cold storage".to_string())
+                ]
+            );
+        }
+
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
 }
@@ -1,4 +1,4 @@
-use crate::descriptors::MultipathDescriptor;
+use crate::{database::LabelItem, descriptors::MultipathDescriptor};
 
 use std::{convert::TryFrom, str::FromStr};
 
@@ -41,6 +41,7 @@ CREATE TABLE wallets (
  *
  * The 'spend_block_height' and 'spend_block.time' are only present if the spending
  * transaction for this coin exists and was confirmed.
+ * The 'is_frozen' field is set by the user for the coins which must not be spent.
  */
 CREATE TABLE coins (
     id INTEGER PRIMARY KEY NOT NULL,
@@ -55,6 +56,7 @@ CREATE TABLE coins (
     spend_txid BLOB,
     spend_block_height INTEGER,
     spend_block_time INTEGER,
+    is_frozen BOOLEAN NOT NULL DEFAULT 0 CHECK (is_frozen IN (0,1)),
     UNIQUE (txid, vout),
     FOREIGN KEY (wallet_id) REFERENCES wallets (id)
         ON UPDATE RESTRICT
@@ -70,11 +72,30 @@ CREATE TABLE addresses (
     derivation_index INTEGER NOT NULL UNIQUE
 );
 
-/* Transactions we created that spend some of our coins. */
+/* Transactions we created that spend some of our coins.
+ *
+ * The 'replaced_by_txid' field is only present if the transaction was replaced by another one
+ * (for instance to bump its fees), in which case it must not be broadcast anymore.
+ */
 CREATE TABLE spend_transactions (
     id INTEGER PRIMARY KEY NOT NULL,
     psbt BLOB UNIQUE NOT NULL,
-    txid BLOB UNIQUE NOT NULL
+    txid BLOB UNIQUE NOT NULL,
+    replaced_by_txid BLOB
+);
+
+/* Labels attached to an address, a transaction or a coin by the user.
+ *
+ * The 'item' is the address, the txid or the 'txid:vout' outpoint of the coin.
+ */
+CREATE TABLE labels (
+    id INTEGER PRIMARY KEY NOT NULL,
+    wallet_id INTEGER NOT NULL,
+    item TEXT UNIQUE NOT NULL,
+    value TEXT NOT NULL,
+    FOREIGN KEY (wallet_id) REFERENCES wallets (id)
+        ON UPDATE RESTRICT
+        ON DELETE RESTRICT
 );
 ";
 
@@ -165,6 +186,7 @@ pub struct DbCoin {
     pub is_change: bool,
     pub spend_txid: Option<bitcoin::Txid>,
     pub spend_block: Option<DbSpendBlock>,
+    pub is_frozen: bool,
 }
 
 impl TryFrom<&rusqlite::Row<'_>> for DbCoin {
@@ -197,6 +219,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbCoin {
             height,
             time: spend_time.expect("Must be there if height is"),
         });
+        let is_frozen: bool = row.get(12)?;
 
         Ok(DbCoin {
             id,
@@ -209,6 +232,7 @@ impl TryFrom<&rusqlite::Row<'_>> for DbCoin {
             is_change,
             spend_txid,
             spend_block,
+            is_frozen,
         })
     }
 }
@@ -250,6 +274,7 @@ pub struct DbSpendTransaction {
     pub id: i64,
     pub psbt: Psbt,
     pub txid: bitcoin::Txid,
+    pub replaced_by: Option<bitcoin::Txid>,
 }
 
 impl TryFrom<&rusqlite::Row<'_>> for DbSpendTransaction {
@@ -265,6 +290,44 @@ impl TryFrom<&rusqlite::Row<'_>> for DbSpendTransaction {
         let txid: bitcoin::Txid = encode::deserialize(&txid).expect("We only store valid txids");
         assert_eq!(txid, psbt.unsigned_tx.txid());
 
-        Ok(DbSpendTransaction { id, psbt, txid })
+        let replaced_by: Option<Vec<u8>> = row.get(3)?;
+        let replaced_by: Option<bitcoin::Txid> =
+            replaced_by.map(|txid| encode::deserialize(&txid).expect("We only store valid txids"));
+
+        Ok(DbSpendTransaction {
+            id,
+            psbt,
+            txid,
+            replaced_by,
+        })
+    }
+}
+
+/// A row in the "labels" table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbLabel {
+    pub id: i64,
+    pub wallet_id: i64,
+    pub item: LabelItem,
+    pub value: String,
+}
+
+impl TryFrom<&rusqlite::Row<'_>> for DbLabel {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> Result<Self, Self::Error> {
+        let id: i64 = row.get(0)?;
+        let wallet_id: i64 = row.get(1)?;
+
+        let item: String = row.get(2)?;
+        let item = LabelItem::from_str(&item).expect("We only store valid label items");
+        let value: String = row.get(3)?;
+
+        Ok(DbLabel {
+            id,
+            wallet_id,
+            item,
+            value,
+        })
     }
 }
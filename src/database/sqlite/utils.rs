@@ -134,3 +134,87 @@ pub fn create_fresh_db(
 
     Ok(())
 }
+
+// Upgrade the database from version 0 to version 1: add the column recording which transaction
+// replaced a Spend transaction.
+fn migrate_v0_to_v1(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |tx| {
+        tx.execute(
+            "ALTER TABLE spend_transactions ADD COLUMN replaced_by_txid BLOB",
+            rusqlite::params![],
+        )?;
+        tx.execute("UPDATE version SET version = 1", rusqlite::params![])?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+// Upgrade the database from version 1 to version 2: add the table storing the labels.
+fn migrate_v1_to_v2(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |tx| {
+        tx.execute(
+            "CREATE TABLE labels (
+                id INTEGER PRIMARY KEY NOT NULL,
+                wallet_id INTEGER NOT NULL,
+                item TEXT UNIQUE NOT NULL,
+                value TEXT NOT NULL,
+                FOREIGN KEY (wallet_id) REFERENCES wallets (id)
+                    ON UPDATE RESTRICT
+                    ON DELETE RESTRICT
+            )",
+            rusqlite::params![],
+        )?;
+        tx.execute("UPDATE version SET version = 2", rusqlite::params![])?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+// Upgrade the database from version 2 to version 3: add the flag for frozen coins. Note the column
+// is added last, as in the schema of a fresh database.
+fn migrate_v2_to_v3(conn: &mut rusqlite::Connection) -> Result<(), SqliteDbError> {
+    db_exec(conn, |tx| {
+        tx.execute(
+            "ALTER TABLE coins ADD COLUMN is_frozen BOOLEAN NOT NULL DEFAULT 0 CHECK (is_frozen IN (0,1))",
+            rusqlite::params![],
+        )?;
+        tx.execute("UPDATE version SET version = 3", rusqlite::params![])?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// Check the database version and if necessary apply the migrations to upgrade it to the current
+/// one. A database from the future is left untouched.
+pub fn maybe_apply_migration(db_path: &path::Path) -> Result<(), SqliteDbError> {
+    let mut conn = rusqlite::Connection::open(db_path)?;
+
+    let version: i64 = db_query(
+        &mut conn,
+        "SELECT version FROM version",
+        rusqlite::params![],
+        |row| row.get(0),
+    )?
+    .pop()
+    .expect("There is always a row in the version table");
+    if version == 0 {
+        log::info!("Upgrading database from version 0 to version 1.");
+        migrate_v0_to_v1(&mut conn)?;
+        log::info!("Migration from version 0 to version 1 successful.");
+    }
+    if version <= 1 {
+        log::info!("Upgrading database from version 1 to version 2.");
+        migrate_v1_to_v2(&mut conn)?;
+        log::info!("Migration from version 1 to version 2 successful.");
+    }
+    if version <= 2 {
+        log::info!("Upgrading database from version 2 to version 3.");
+        migrate_v2_to_v3(&mut conn)?;
+        log::info!("Migration from version 2 to version 3 successful.");
+    }
+
+    Ok(())
+}
@@ -2,18 +2,19 @@ use miniscript::{
     bitcoin::{
         self,
         blockdata::transaction::Sequence,
-        hashes::{hash160, ripemd160, sha256},
+        hashes::{hash160, ripemd160, sha256, Hash},
         secp256k1,
         util::{
             bip32,
-            psbt::{Input as PsbtIn, Psbt},
+            psbt::{Input as PsbtIn, Output as PsbtOut, Psbt},
+            taproot::{LeafVersion, TapLeafHash},
         },
     },
     descriptor, hash256,
     miniscript::{decode::Terminal, Miniscript},
-    policy::{Liftable, Semantic as SemanticPolicy},
-    translate_hash_clone, ForEachKey, MiniscriptKey, ScriptContext, ToPublicKey, TranslatePk,
-    Translator,
+    policy::{Concrete as ConcretePolicy, Liftable, Semantic as SemanticPolicy},
+    translate_hash_clone, ForEachKey, MiniscriptKey, Preimage32, Satisfier, ScriptContext,
+    ToPublicKey, TranslatePk, Translator,
 };
 
 use std::{
@@ -26,6 +27,12 @@ use serde::{Deserialize, Serialize};
 
 const WITNESS_FACTOR: usize = 4;
 
+// The expected time between two blocks, used to approximate the duration of relative timelocks.
+const AVG_BLOCK_TIME_SECS: u64 = 600;
+
+/// nLockTime values below this are block heights, above are UNIX timestamps.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
 // Convert a size in weight units to a size in virtual bytes, rounding up.
 fn wu_to_vb(vb: usize) -> usize {
     (vb + WITNESS_FACTOR - 1)
@@ -47,6 +54,14 @@ pub enum LianaDescError {
     InsanePsbt,
     /// Not all inputs' sequence the same, not all inputs signed with the same key, ..
     InconsistentPsbt,
+    /// A Liana descriptor needs at least one timelocked recovery path.
+    NoRecoveryPath,
+    /// Only recovery paths may be conditioned on the knowledge of a hash preimage.
+    PrimaryPathHashLock,
+    /// The wallet policy does not follow BIP388.
+    InvalidWalletPolicy(&'static str),
+    /// The Miniscript policy can't be compiled into a Liana descriptor.
+    IncompatiblePolicy(&'static str),
 }
 
 impl std::fmt::Display for LianaDescError {
@@ -71,7 +86,11 @@ impl std::fmt::Display for LianaDescError {
             Self::InvalidMultiThresh(thresh) => write!(f, "Invalid threshold value '{}'. The threshold must be > to 0 and <= to the number of keys.", thresh),
             Self::InvalidMultiKeys(n_keys) => write!(f, "Invalid number of keys '{}'. Between 2 and 20 keys must be given to use multiple keys in a specific path.", n_keys),
             Self::InsanePsbt => write!(f, "Analyzed PSBT is empty or malformed."),
-            Self::InconsistentPsbt => write!(f, "Analyzed PSBT is inconsistent across inputs.")
+            Self::InconsistentPsbt => write!(f, "Analyzed PSBT is inconsistent across inputs."),
+            Self::NoRecoveryPath => write!(f, "At least one timelocked recovery path is required."),
+            Self::PrimaryPathHashLock => write!(f, "The primary spending path can't require a hash preimage."),
+            Self::InvalidWalletPolicy(reason) => write!(f, "Invalid wallet policy: {}", reason),
+            Self::IncompatiblePolicy(reason) => write!(f, "Incompatible policy: {}", reason),
         }
     }
 }
@@ -204,6 +223,18 @@ fn csv_check(csv_value: u32) -> Result<(), LianaDescError> {
     Err(LianaDescError::InsaneTimelock(csv_value))
 }
 
+// We require the absolute locktime to:
+//  - be positive (Miniscript requires it not to be 0)
+//  - fit in a 4-bytes script number (Miniscript requires it to be < 2**31)
+//
+// It may either be a block height or a UNIX timestamp, as per the nLockTime semantics.
+fn cltv_check(cltv_value: u32) -> Result<(), LianaDescError> {
+    if cltv_value > 0 && cltv_value < 0x80_00_00_00 {
+        return Ok(());
+    }
+    Err(LianaDescError::InsaneTimelock(cltv_value))
+}
+
 // We require the descriptor key to:
 //  - Be deriveable (to contain a wildcard)
 //  - Be multipath (to contain a step in the derivation path with multiple indexes)
@@ -222,11 +253,18 @@ fn is_valid_desc_key(key: &descriptor::DescriptorPublicKey) -> bool {
             // no unlikely (and easily fixable) while users shooting themselves in the foot by
             // forgetting to provide the origin is so likely that it's worth ruling out xpubs
             // without origin entirely.
+            // The last step is usually <0;1>. A signer reused across spending paths uses the
+            // next pairs of indexes (<2;3>, <4;5>, ..) for its other occurrences.
             xpub.origin.is_some()
                 && xpub.wildcard == descriptor::Wildcard::Unhardened
                 && der_paths.len() == 2
-                && der_paths[0][len - 1] == 0.into()
-                && der_paths[1][len - 1] == 1.into()
+                && match (der_paths[0][len - 1], der_paths[1][len - 1]) {
+                    (
+                        bip32::ChildNumber::Normal { index: receive },
+                        bip32::ChildNumber::Normal { index: change },
+                    ) => receive % 2 == 0 && change == receive + 1,
+                    _ => false,
+                }
         }
     }
 }
@@ -245,13 +283,51 @@ fn key_fingerprint(key: &descriptor::DescriptorPublicKey) -> Option<bip32::Finge
     }
 }
 
-/// The keys in one of the two spending paths of a Liana descriptor.
+// The "Nothing Up My Sleeve" point suggested in BIP341, for which nobody knows the discrete
+// logarithm. Used as the internal key of Taproot descriptors whose primary path can't be a key
+// spend.
+const NUMS_PUBKEY: [u8; 33] = [
+    0x02, 0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a,
+    0x5e, 0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a,
+    0xc0,
+];
+
+// Get a multipath xpub whose public key is the NUMS point. Since we only ever derive it at
+// unhardened indexes, nobody knows the private key of any of its children either. The chain code
+// is the hash of the public key, so anyone can check it's unspendable.
+fn unspendable_key(network: bitcoin::Network) -> descriptor::DescriptorPublicKey {
+    let xkey = bip32::ExtendedPubKey {
+        network,
+        depth: 0,
+        parent_fingerprint: Default::default(),
+        child_number: bip32::ChildNumber::Normal { index: 0 },
+        public_key: secp256k1::PublicKey::from_slice(&NUMS_PUBKEY).expect("Valid point"),
+        chain_code: bip32::ChainCode::from(&sha256::Hash::hash(&NUMS_PUBKEY)[..]),
+    };
+    format!("{}/<0;1>/*", xkey)
+        .parse()
+        .expect("Valid multipath xpub")
+}
+
+// Whether this key is the unspendable key used as internal key in Taproot descriptors.
+fn is_unspendable_key(key: &descriptor::DescriptorPublicKey) -> bool {
+    match key {
+        descriptor::DescriptorPublicKey::MultiXPub(ref xpub) => {
+            xpub.origin.is_none() && xpub.xkey.public_key.serialize() == NUMS_PUBKEY
+        }
+        _ => false,
+    }
+}
+
+/// The keys in one of the spending paths of a Liana descriptor.
 /// May either be a single key, or between 2 and 20 keys along with a threshold (between two and
-/// the number of keys).
+/// the number of keys). The keys of a recovery path may additionally be required to reveal the
+/// preimage of a SHA256 hash.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LianaDescKeys {
     thresh: Option<usize>,
     keys: Vec<descriptor::DescriptorPublicKey>,
+    sha256_lock: Option<sha256::Hash>,
 }
 
 impl LianaDescKeys {
@@ -259,6 +335,7 @@ impl LianaDescKeys {
         LianaDescKeys {
             thresh: None,
             keys: vec![key],
+            sha256_lock: None,
         }
     }
 
@@ -275,13 +352,26 @@ impl LianaDescKeys {
         Ok(LianaDescKeys {
             thresh: Some(thresh),
             keys,
+            sha256_lock: None,
         })
     }
 
+    /// Also require the preimage of this SHA256 hash to be revealed for spending through this
+    /// path. Only valid for a recovery path.
+    pub fn with_sha256_lock(mut self, hash: sha256::Hash) -> LianaDescKeys {
+        self.sha256_lock = Some(hash);
+        self
+    }
+
     pub fn keys(&self) -> &Vec<descriptor::DescriptorPublicKey> {
         &self.keys
     }
 
+    /// The hash whose preimage must be revealed alongside the signatures, if any.
+    pub fn sha256_lock(&self) -> Option<&sha256::Hash> {
+        self.sha256_lock.as_ref()
+    }
+
     pub fn into_miniscript(
         mut self,
         as_hash: bool,
@@ -304,6 +394,122 @@ impl LianaDescKeys {
             .expect("Well typed")
         }
     }
+
+    /// Get the Miniscript for these keys in a Taproot leaf. A multisig is a multi_a().
+    pub fn into_tap_miniscript(
+        mut self,
+    ) -> Miniscript<descriptor::DescriptorPublicKey, miniscript::Tap> {
+        if let Some(thresh) = self.thresh {
+            assert!(self.keys.len() >= 2 && self.keys.len() <= 20);
+            Miniscript::from_ast(Terminal::MultiA(thresh, self.keys))
+                .expect("multi_a is a valid Miniscript")
+        } else {
+            assert_eq!(self.keys.len(), 1);
+            let key = self.keys.pop().expect("Length was just asserted");
+            Miniscript::from_ast(Terminal::Check(sync::Arc::from(
+                Miniscript::from_ast(Terminal::PkK(key)).expect("pk_k is a valid Miniscript"),
+            )))
+            .expect("Well typed")
+        }
+    }
+
+    // Get the key if this is a single key as opposed to a multisig.
+    fn single_key(&self) -> Option<&descriptor::DescriptorPublicKey> {
+        if self.thresh.is_none() {
+            self.keys.first()
+        } else {
+            None
+        }
+    }
+}
+
+// Build a balanced tree out of the given tapleaves, preserving their order.
+fn balanced_taptree(
+    mut leaves: Vec<descriptor::TapTree<descriptor::DescriptorPublicKey>>,
+) -> descriptor::TapTree<descriptor::DescriptorPublicKey> {
+    assert!(!leaves.is_empty());
+    if leaves.len() == 1 {
+        return leaves.pop().expect("Length was just asserted");
+    }
+    let right_leaves = leaves.split_off(leaves.len() / 2);
+    descriptor::TapTree::Tree(
+        balanced_taptree(leaves).into(),
+        balanced_taptree(right_leaves).into(),
+    )
+}
+
+// A satisfier providing dummy signatures of the maximum size for a set of keys, and assuming
+// any timelock is satisfied and any hash preimage is known. Used to estimate the size of a
+// satisfaction through a single spending path, as keys are never reused across spending paths.
+struct PathSatisfier {
+    keys: HashSet<bitcoin::PublicKey>,
+    // Whether a signature for the internal key of a Taproot descriptor can be provided.
+    key_spend: bool,
+}
+
+impl PathSatisfier {
+    fn dummy_ecdsa_sig() -> bitcoin::EcdsaSig {
+        // A high R and a low S make for a 71 bytes DER-encoded signature, 72 with the sighash type.
+        let mut compact_sig = [0x7f; 64];
+        compact_sig[..32].copy_from_slice(&[0x80; 32]);
+        bitcoin::EcdsaSig {
+            sig: secp256k1::ecdsa::Signature::from_compact(&compact_sig)
+                .expect("Valid compact signature"),
+            hash_ty: bitcoin::EcdsaSighashType::All,
+        }
+    }
+
+    fn dummy_schnorr_sig() -> bitcoin::SchnorrSig {
+        // 65 bytes with the explicit sighash type.
+        bitcoin::SchnorrSig {
+            sig: secp256k1::schnorr::Signature::from_slice(&[0x01; 64])
+                .expect("Valid signature length"),
+            hash_ty: bitcoin::SchnorrSighashType::All,
+        }
+    }
+}
+
+impl Satisfier<DerivedPublicKey> for PathSatisfier {
+    fn lookup_ecdsa_sig(&self, pk: &DerivedPublicKey) -> Option<bitcoin::EcdsaSig> {
+        if self.keys.contains(&pk.key) {
+            Some(Self::dummy_ecdsa_sig())
+        } else {
+            None
+        }
+    }
+
+    fn lookup_tap_key_spend_sig(&self) -> Option<bitcoin::SchnorrSig> {
+        if self.key_spend {
+            Some(Self::dummy_schnorr_sig())
+        } else {
+            None
+        }
+    }
+
+    fn lookup_tap_leaf_script_sig(
+        &self,
+        pk: &DerivedPublicKey,
+        _: &TapLeafHash,
+    ) -> Option<bitcoin::SchnorrSig> {
+        if self.keys.contains(&pk.key) {
+            Some(Self::dummy_schnorr_sig())
+        } else {
+            None
+        }
+    }
+
+    fn check_older(&self, _: Sequence) -> bool {
+        true
+    }
+
+    fn check_after(&self, _: bitcoin::LockTime) -> bool {
+        true
+    }
+
+    fn lookup_sha256(&self, _: &sha256::Hash) -> Option<Preimage32> {
+        // The size of a preimage is fixed. Only the path's keys determine which branch is used.
+        Some([0; 32])
+    }
 }
 
 /// An [InheritanceDescriptor] that contains multipath keys for (and only for) the receive keychain
@@ -340,105 +546,155 @@ fn is_single_key_or_multisig(policy: &&SemanticPolicy<descriptor::DescriptorPubl
     }
 }
 
-impl str::FromStr for MultipathDescriptor {
-    type Err = LianaDescError;
+// The timelock of a recovery path as found in its semantic policy, not sanity checked yet.
+enum PolicyTimelock {
+    Older(u32),
+    After(u32),
+}
 
-    fn from_str(s: &str) -> Result<MultipathDescriptor, Self::Err> {
-        let wsh_desc = descriptor::Wsh::<descriptor::DescriptorPublicKey>::from_str(s)
-            .map_err(LianaDescError::Miniscript)?;
-        let ms = match wsh_desc.as_inner() {
-            descriptor::WshInner::Ms(ms) => ms,
-            _ => return Err(LianaDescError::IncompatibleDesc),
-        };
-        let invalid_key = ms.iter_pk().find_map(|pk| {
-            if is_valid_desc_key(&pk) {
-                None
-            } else {
-                Some(pk)
+impl PolicyTimelock {
+    fn checked(self) -> Result<RecoveryTimelock, LianaDescError> {
+        match self {
+            PolicyTimelock::Older(csv_value) => {
+                csv_check(csv_value)?;
+                Ok(RecoveryTimelock::Relative(
+                    u16::try_from(csv_value).expect("Just checked"),
+                ))
+            }
+            PolicyTimelock::After(cltv_value) => {
+                cltv_check(cltv_value)?;
+                Ok(RecoveryTimelock::Absolute(cltv_value))
             }
-        });
-        if let Some(key) = invalid_key {
-            return Err(LianaDescError::InvalidKey(key.into()));
-        }
-
-        // Semantic of the Miniscript must be either the owner now, or the heir after
-        // a timelock.
-        let policy = ms
-            .lift()
-            .expect("Lifting can't fail on a Miniscript")
-            .normalized();
-        let subs = match policy {
-            SemanticPolicy::Threshold(1, subs) => Some(subs),
-            _ => None,
         }
-        .ok_or(LianaDescError::IncompatibleDesc)?;
-        if subs.len() != 2 {
-            return Err(LianaDescError::IncompatibleDesc);
+    }
+}
+
+// Information about a recovery spending path as found in its semantic policy.
+struct RecoveryPolicyInfo<'a> {
+    timelock: PolicyTimelock,
+    sha256_lock: Option<sha256::Hash>,
+    keys: &'a SemanticPolicy<descriptor::DescriptorPublicKey>,
+}
+
+// Get the timelock, the optional hash lock and the policy for the set of keys of a recovery
+// spending path.
+// A recovery path is always a 2-of-2 between a (relative or absolute) timelock and a set of keys,
+// or a 3-of-3 if the knowledge of a SHA256 preimage is also required.
+// Returns None if the given policy isn't a recovery path.
+fn recovery_path_info(
+    policy: &SemanticPolicy<descriptor::DescriptorPublicKey>,
+) -> Option<RecoveryPolicyInfo> {
+    let subs = match policy {
+        SemanticPolicy::Threshold(k, subs) if (*k == 2 || *k == 3) && subs.len() == *k => subs,
+        _ => return None,
+    };
+    let (mut timelock, mut sha256_lock, mut keys) = (None, None, None);
+    for sub in subs {
+        match sub {
+            SemanticPolicy::Older(csv) if timelock.is_none() => {
+                timelock = Some(PolicyTimelock::Older(csv.to_consensus_u32()))
+            }
+            SemanticPolicy::After(lock_time) if timelock.is_none() => {
+                timelock = Some(PolicyTimelock::After(lock_time.to_u32()))
+            }
+            SemanticPolicy::Sha256(hash) if sha256_lock.is_none() => sha256_lock = Some(*hash),
+            sub if keys.is_none() && is_single_key_or_multisig(&sub) => keys = Some(sub),
+            _ => return None,
         }
+    }
+    Some(RecoveryPolicyInfo {
+        timelock: timelock?,
+        sha256_lock,
+        keys: keys?,
+    })
+}
+
+// Get the semantic policy of each of the spending paths of a Liana descriptor. For a Taproot
+// descriptor, the key path is left out if the internal key is unspendable.
+// Returns None if the descriptor can't be a Liana descriptor.
+fn spending_paths(
+    desc: &descriptor::Descriptor<descriptor::DescriptorPublicKey>,
+) -> Option<Vec<SemanticPolicy<descriptor::DescriptorPublicKey>>> {
+    let policy = match desc {
+        descriptor::Descriptor::Wsh(wsh_desc) => match wsh_desc.as_inner() {
+            descriptor::WshInner::Ms(ms) => ms.lift(),
+            _ => return None,
+        },
+        descriptor::Descriptor::Tr(tr_desc) => tr_desc.lift(),
+        _ => return None,
+    }
+    .ok()?
+    .normalized();
 
-        // Non-timelocked spending path may be either a single key check or a multisig.
-        subs.iter()
-            .find(is_single_key_or_multisig)
-            .ok_or(LianaDescError::IncompatibleDesc)?;
+    match policy {
+        SemanticPolicy::Threshold(1, subs) => Some(
+            subs.into_iter()
+                .filter(|sub| !matches!(sub, SemanticPolicy::Key(key) if is_unspendable_key(key)))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
 
-        // The recovery spending path is always a 2-of-2 between a timelock and a set of
-        // keys.
-        let recov_subs = subs
-            .iter()
-            .find_map(|s| match s {
-                SemanticPolicy::Threshold(2, subs) => {
-                    if subs.len() == 2
-                        && subs
-                            .iter()
-                            .any(|sub| matches!(sub, SemanticPolicy::Older(_)))
-                    {
-                        Some(subs)
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            })
-            .ok_or(LianaDescError::IncompatibleDesc)?;
-        if recov_subs.len() != 2 {
+// Check a descriptor is a valid Liana descriptor.
+fn check_liana_desc(
+    multi_desc: &descriptor::Descriptor<descriptor::DescriptorPublicKey>,
+) -> Result<(), LianaDescError> {
+    // Check all the keys are valid. The only exception is the internal key of a Taproot
+    // descriptor, which may be an unspendable key if the primary path isn't the key path.
+    let keys: Vec<descriptor::DescriptorPublicKey> = match *multi_desc {
+        descriptor::Descriptor::Wsh(ref wsh_desc) => match wsh_desc.as_inner() {
+            descriptor::WshInner::Ms(ms) => ms.iter_pk().collect(),
+            _ => return Err(LianaDescError::IncompatibleDesc),
+        },
+        descriptor::Descriptor::Tr(ref tr_desc) => tr_desc
+            .iter_scripts()
+            .flat_map(|(_, ms)| ms.iter_pk())
+            .chain(Some(tr_desc.internal_key().clone()).filter(|k| !is_unspendable_key(k)))
+            .collect(),
+        _ => return Err(LianaDescError::IncompatibleDesc),
+    };
+    if let Some(key) = keys.into_iter().find(|pk| !is_valid_desc_key(pk)) {
+        return Err(LianaDescError::InvalidKey(key.into()));
+    }
+
+    // Semantic of the Miniscript must be either the owner now, or any of the heirs after their
+    // respective timelock.
+    let subs = spending_paths(multi_desc).ok_or(LianaDescError::IncompatibleDesc)?;
+    if subs.len() < 2 {
+        return Err(LianaDescError::IncompatibleDesc);
+    }
+
+    // Exactly one spending path must be non-timelocked. It may be either a single key check
+    // or a multisig.
+    if subs.iter().filter(is_single_key_or_multisig).count() != 1 {
+        return Err(LianaDescError::IncompatibleDesc);
+    }
+
+    // All the other spending paths must be recovery paths. No two recovery paths may have the
+    // same timelock.
+    let mut timelocks = HashSet::with_capacity(subs.len() - 1);
+    for sub in subs.iter().filter(|sub| !is_single_key_or_multisig(sub)) {
+        let path = recovery_path_info(sub).ok_or(LianaDescError::IncompatibleDesc)?;
+        if !timelocks.insert(path.timelock.checked()?) {
             return Err(LianaDescError::IncompatibleDesc);
         }
-        // Must be timelocked
-        let csv_value = recov_subs
-            .iter()
-            .find_map(|s| match s {
-                SemanticPolicy::Older(csv) => Some(csv),
-                _ => None,
-            })
-            .ok_or(LianaDescError::IncompatibleDesc)?;
-        csv_check(csv_value.to_consensus_u32())?;
-        // The timelocked spending path may have a single key check or a multisig.
-        recov_subs
-            .iter()
-            .find(is_single_key_or_multisig)
-            .ok_or(LianaDescError::IncompatibleDesc)?;
+    }
 
-        // All good, construct the multipath descriptor.
-        let multi_desc = descriptor::Descriptor::Wsh(wsh_desc);
+    Ok(())
+}
 
-        // Compute the receive and change "sub" descriptors right away. According to our pubkey
-        // check above, there must be only two of those, 0 and 1.
-        // We use /0/* for receiving and /1/* for change.
-        // FIXME: don't rely on into_single_descs()'s ordering.
-        let mut singlepath_descs = multi_desc
-            .clone()
-            .into_single_descriptors()
-            .expect("Can't error, all paths have the same length")
-            .into_iter();
-        assert_eq!(singlepath_descs.len(), 2);
-        let receive_desc = InheritanceDescriptor(singlepath_descs.next().expect("First of 2"));
-        let change_desc = InheritanceDescriptor(singlepath_descs.next().expect("Second of 2"));
+impl str::FromStr for MultipathDescriptor {
+    type Err = LianaDescError;
 
-        Ok(MultipathDescriptor {
-            multi_desc,
-            receive_desc,
-            change_desc,
-        })
+    fn from_str(s: &str) -> Result<MultipathDescriptor, Self::Err> {
+        let multi_desc = s
+            .parse::<descriptor::Descriptor<descriptor::DescriptorPublicKey>>()
+            .map_err(LianaDescError::Miniscript)?;
+        check_liana_desc(&multi_desc)?;
+
+        // All good, construct the multipath descriptor.
+        Ok(MultipathDescriptor::from_multi_desc(multi_desc))
     }
 }
 
@@ -454,6 +710,57 @@ impl PartialEq<descriptor::Descriptor<descriptor::DescriptorPublicKey>> for Inhe
     }
 }
 
+/// The timelock of a recovery path, which identifies it within a descriptor.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryTimelock {
+    /// A relative timelock (`older()`), in number of blocks.
+    Relative(u16),
+    /// An absolute timelock (`after()`), either a block height or a UNIX timestamp following the
+    /// nLockTime semantics.
+    Absolute(u32),
+}
+
+impl RecoveryTimelock {
+    // Get the Miniscript fragment for this timelock.
+    fn into_terminal<Ctx: ScriptContext>(self) -> Terminal<descriptor::DescriptorPublicKey, Ctx> {
+        match self {
+            Self::Relative(csv_value) => Terminal::Older(Sequence::from_height(csv_value)),
+            Self::Absolute(cltv_value) => Terminal::After(bitcoin::PackedLockTime(cltv_value)),
+        }
+    }
+}
+
+// Get the Miniscript for the timelock of a recovery path, along with its hash lock if any. That
+// is either `older()`/`after()` or `and_v(v:sha256(),older())`/`and_v(v:sha256(),after())`.
+fn recovery_lock_miniscript<Ctx: ScriptContext>(
+    timelock: RecoveryTimelock,
+    sha256_lock: Option<sha256::Hash>,
+) -> Miniscript<descriptor::DescriptorPublicKey, Ctx> {
+    let timelock = Miniscript::from_ast(timelock.into_terminal()).expect("Well typed");
+    if let Some(hash) = sha256_lock {
+        let hash_check = Miniscript::from_ast(Terminal::Verify(
+            Miniscript::from_ast(Terminal::Sha256(hash))
+                .expect("Well typed")
+                .into(),
+        ))
+        .expect("Well typed");
+        Miniscript::from_ast(Terminal::AndV(hash_check.into(), timelock.into()))
+            .expect("Well typed")
+    } else {
+        timelock
+    }
+}
+
+impl fmt::Display for RecoveryTimelock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Relative(csv) => write!(f, "older({})", csv),
+            Self::Absolute(cltv) => write!(f, "after({})", cltv),
+        }
+    }
+}
+
 /// Information about a single spending path in the descriptor.
 #[derive(Debug, Eq, PartialEq, Clone, Ord, PartialOrd, Hash)]
 pub enum PathInfo {
@@ -483,6 +790,14 @@ impl PathInfo {
         }
     }
 
+    /// Get the keys that can be used to provide a signature for this path.
+    pub fn keys(&self) -> Vec<&descriptor::DescriptorPublicKey> {
+        match self {
+            PathInfo::Single(key) => vec![key],
+            PathInfo::Multi(_, keys) => keys.iter().collect(),
+        }
+    }
+
     /// Get the required number of keys for spending through this path, and the set of keys
     /// that can be used to provide a signature for this path.
     pub fn thresh_fingerprints(&self) -> (usize, HashSet<bip32::Fingerprint>) {
@@ -527,23 +842,33 @@ impl PathInfo {
             threshold,
             sigs_count,
             signed_pubkeys,
+            has_preimage: None,
         }
     }
 }
 
 /// Information about the descriptor: how many keys are present in each path, what's the timelock
-/// of the recovery path, what's the threshold if there are multiple keys, etc..
+/// of each recovery path, what's the threshold if there are multiple keys, etc..
 #[derive(Debug, Eq, PartialEq, Clone, Ord, PartialOrd, Hash)]
 pub struct LianaDescInfo {
     primary_path: PathInfo,
-    recovery_path: (u16, PathInfo),
+    recovery_paths: BTreeMap<u16, PathInfo>,
+    abs_recovery_paths: BTreeMap<u32, PathInfo>,
+    sha256_locks: BTreeMap<RecoveryTimelock, sha256::Hash>,
 }
 
 impl LianaDescInfo {
-    fn new(primary_path: PathInfo, recovery_path: (u16, PathInfo)) -> LianaDescInfo {
+    fn new(
+        primary_path: PathInfo,
+        recovery_paths: BTreeMap<u16, PathInfo>,
+        abs_recovery_paths: BTreeMap<u32, PathInfo>,
+        sha256_locks: BTreeMap<RecoveryTimelock, sha256::Hash>,
+    ) -> LianaDescInfo {
         LianaDescInfo {
             primary_path,
-            recovery_path,
+            recovery_paths,
+            abs_recovery_paths,
+            sha256_locks,
         }
     }
 
@@ -551,9 +876,224 @@ impl LianaDescInfo {
         &self.primary_path
     }
 
-    /// Timelock and path info for the recovery path.
-    pub fn recovery_path(&self) -> (u16, &PathInfo) {
-        (self.recovery_path.0, &self.recovery_path.1)
+    /// Path info for each recovery path with a relative timelock, indexed by (and ordered by
+    /// increasing) timelock.
+    pub fn recovery_paths(&self) -> &BTreeMap<u16, PathInfo> {
+        &self.recovery_paths
+    }
+
+    /// Path info for each recovery path with an absolute timelock, indexed by (and ordered by
+    /// increasing) nLockTime value.
+    pub fn abs_recovery_paths(&self) -> &BTreeMap<u32, PathInfo> {
+        &self.abs_recovery_paths
+    }
+
+    /// Get the path info of the recovery path with this timelock, if there is one.
+    pub fn recovery_path(&self, timelock: RecoveryTimelock) -> Option<&PathInfo> {
+        match timelock {
+            RecoveryTimelock::Relative(csv) => self.recovery_paths.get(&csv),
+            RecoveryTimelock::Absolute(cltv) => self.abs_recovery_paths.get(&cltv),
+        }
+    }
+
+    /// Whether there is a recovery path with this timelock.
+    pub fn has_recovery_path(&self, timelock: RecoveryTimelock) -> bool {
+        self.recovery_path(timelock).is_some()
+    }
+
+    /// Get the hash whose preimage must be revealed to spend through the recovery path with this
+    /// timelock, if there is such a path and it has a hash lock.
+    pub fn sha256_lock(&self, timelock: RecoveryTimelock) -> Option<&sha256::Hash> {
+        self.sha256_locks.get(&timelock)
+    }
+}
+
+/// A spending path of a Liana descriptor.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendingPath {
+    /// The path available at any time.
+    Primary,
+    /// A recovery path, identified by its timelock.
+    Recovery(RecoveryTimelock),
+}
+
+/// Explanation of a single spending path of a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathExplanation {
+    pub path: SpendingPath,
+    /// The number of signatures needed to spend through this path.
+    pub threshold: usize,
+    /// The master fingerprints of the keys which can sign for this path.
+    pub fingerprints: Vec<bip32::Fingerprint>,
+    /// For a relative timelock, the approximate time in seconds after a coin is confirmed until
+    /// it can be spent through this path.
+    pub approx_duration_secs: Option<u64>,
+    /// The hash whose preimage must also be revealed to spend through this path, if any.
+    pub sha256_lock: Option<sha256::Hash>,
+}
+
+/// Explanation of a key, identified by its master fingerprint, in a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyExplanation {
+    pub fingerprint: bip32::Fingerprint,
+    /// The spending paths this key can sign for.
+    pub paths: Vec<SpendingPath>,
+}
+
+/// A structured explanation of the spending policy of a descriptor. Its `Display` implementation
+/// gives a human-readable description, one line per spending path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyExplanation {
+    pub primary_path: PathExplanation,
+    /// The recovery paths, relatively timelocked ones first.
+    pub recovery_paths: Vec<PathExplanation>,
+    pub keys: Vec<KeyExplanation>,
+}
+
+impl PathExplanation {
+    fn new(
+        path: SpendingPath,
+        path_info: &PathInfo,
+        sha256_lock: Option<sha256::Hash>,
+    ) -> PathExplanation {
+        let (threshold, fingerprints) = path_info.thresh_fingerprints();
+        let mut fingerprints: Vec<_> = fingerprints.into_iter().collect();
+        fingerprints.sort_unstable();
+        let approx_duration_secs = match path {
+            SpendingPath::Recovery(RecoveryTimelock::Relative(csv)) => {
+                Some(u64::from(csv) * AVG_BLOCK_TIME_SECS)
+            }
+            _ => None,
+        };
+        PathExplanation {
+            path,
+            threshold,
+            fingerprints,
+            approx_duration_secs,
+            sha256_lock,
+        }
+    }
+}
+
+// Format a duration approximately, in the most appropriate unit.
+fn approx_duration_str(secs: u64) -> String {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    let (value, unit) = if secs < 2 * HOUR {
+        (secs / 60, "minutes")
+    } else if secs < 2 * DAY {
+        (secs / HOUR, "hours")
+    } else if secs < 60 * DAY {
+        (secs / DAY, "days")
+    } else if secs < 2 * 365 * DAY {
+        (secs / (30 * DAY), "months")
+    } else {
+        (secs / (365 * DAY), "years")
+    };
+    format!("~{} {}", value, unit)
+}
+
+// Format a UNIX timestamp as a UTC date.
+fn utc_date_str(timestamp: u32) -> String {
+    // From Howard Hinnant's civil_from_days algorithm.
+    let days = i64::from(timestamp) / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+impl fmt::Display for PathExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fingerprints = self
+            .fingerprints
+            .iter()
+            .map(|fg| fg.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.threshold == 1 && self.fingerprints.len() == 1 {
+            write!(f, "{} can spend", fingerprints)?;
+        } else {
+            write!(f, "{} of {{{}}} can spend", self.threshold, fingerprints)?;
+        }
+        match self.path {
+            SpendingPath::Primary => write!(f, " anytime"),
+            SpendingPath::Recovery(RecoveryTimelock::Relative(csv)) => write!(
+                f,
+                " {} blocks ({}) after a coin is confirmed",
+                csv,
+                approx_duration_str(u64::from(csv) * AVG_BLOCK_TIME_SECS)
+            ),
+            SpendingPath::Recovery(RecoveryTimelock::Absolute(cltv)) => {
+                if cltv < LOCKTIME_THRESHOLD {
+                    write!(f, " from block height {}", cltv)
+                } else {
+                    write!(f, " from {} (UTC)", utc_date_str(cltv))
+                }
+            }
+        }?;
+        if let Some(hash) = self.sha256_lock {
+            write!(f, ", revealing the preimage of {}", hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PolicyExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.primary_path)?;
+        for path in self.recovery_paths.iter() {
+            write!(f, "\n{}", path)?;
+        }
+        Ok(())
+    }
+}
+
+impl LianaDescInfo {
+    /// Get a structured explanation of the spending policy of this descriptor.
+    pub fn explanation(&self) -> PolicyExplanation {
+        let primary_path = PathExplanation::new(SpendingPath::Primary, &self.primary_path, None);
+        let recovery_paths: Vec<PathExplanation> = self
+            .recovery_paths
+            .iter()
+            .map(|(csv, path_info)| (RecoveryTimelock::Relative(*csv), path_info))
+            .chain(
+                self.abs_recovery_paths
+                    .iter()
+                    .map(|(cltv, path_info)| (RecoveryTimelock::Absolute(*cltv), path_info)),
+            )
+            .map(|(timelock, path_info)| {
+                PathExplanation::new(
+                    SpendingPath::Recovery(timelock),
+                    path_info,
+                    self.sha256_lock(timelock).copied(),
+                )
+            })
+            .collect();
+
+        let mut keys_paths: BTreeMap<bip32::Fingerprint, Vec<SpendingPath>> = BTreeMap::new();
+        for path in std::iter::once(&primary_path).chain(recovery_paths.iter()) {
+            for fingerprint in path.fingerprints.iter() {
+                keys_paths.entry(*fingerprint).or_default().push(path.path);
+            }
+        }
+        let keys = keys_paths
+            .into_iter()
+            .map(|(fingerprint, paths)| KeyExplanation { fingerprint, paths })
+            .collect();
+
+        PolicyExplanation {
+            primary_path,
+            recovery_paths,
+            keys,
+        }
     }
 }
 
@@ -567,6 +1107,9 @@ pub struct PathSpendInfo {
     /// The keys for which a signature was provided and the number (always >=1) of
     /// signatures provided for this key.
     pub signed_pubkeys: HashMap<bip32::Fingerprint, usize>,
+    /// For a path with a hash lock, whether a valid preimage was provided. None if this path
+    /// doesn't require a preimage.
+    pub has_preimage: Option<bool>,
 }
 
 /// Information about a partial spend of Liana coins
@@ -574,9 +1117,12 @@ pub struct PathSpendInfo {
 pub struct PartialSpendInfo {
     /// Number of signatures present for the primary path
     primary_path: PathSpendInfo,
-    /// Number of signatures present for the recovery path, only present if the path is available
-    /// in the first place.
-    recovery_path: Option<PathSpendInfo>,
+    /// Number of signatures present for each recovery path, indexed by timelock. Only the paths
+    /// available in the first place are present.
+    recovery_paths: BTreeMap<u16, PathSpendInfo>,
+    /// Number of signatures present for each absolutely timelocked recovery path, indexed by
+    /// nLockTime value. Only the paths available in the first place are present.
+    abs_recovery_paths: BTreeMap<u32, PathSpendInfo>,
 }
 
 impl PartialSpendInfo {
@@ -585,70 +1131,131 @@ impl PartialSpendInfo {
         &self.primary_path
     }
 
-    /// Get the number of signatures present for the recovery path. Only present if the path is
-    /// available in the first place.
-    pub fn recovery_path(&self) -> &Option<PathSpendInfo> {
-        &self.recovery_path
+    /// Get the number of signatures present for each recovery path, indexed by timelock. Only the
+    /// paths available in the first place are present.
+    pub fn recovery_paths(&self) -> &BTreeMap<u16, PathSpendInfo> {
+        &self.recovery_paths
+    }
+
+    /// Get the number of signatures present for each absolutely timelocked recovery path, indexed
+    /// by nLockTime value. Only the paths available in the first place are present.
+    pub fn abs_recovery_paths(&self) -> &BTreeMap<u32, PathSpendInfo> {
+        &self.abs_recovery_paths
     }
 }
 
-impl MultipathDescriptor {
-    pub fn new(
-        owner_keys: LianaDescKeys,
-        heir_keys: LianaDescKeys,
-        timelock: u16,
-    ) -> Result<MultipathDescriptor, LianaDescError> {
-        // We require the locktime to:
-        //  - not be disabled
-        //  - be in number of blocks
-        //  - be 'clean' / minimal, ie all bits without consensus meaning should be 0
-        //  - be positive (Miniscript requires it not to be 0)
-        //
-        // All this is achieved through asking for a 16-bit integer.
-        if timelock == 0 {
-            return Err(LianaDescError::InsaneTimelock(timelock as u32));
-        }
-        let timelock = Sequence::from_height(timelock);
+// Sanity check the keys and timelocks of the spending paths of a Liana descriptor.
+fn check_spending_paths(
+    owner_keys: &LianaDescKeys,
+    recovery_paths: &BTreeMap<RecoveryTimelock, LianaDescKeys>,
+) -> Result<(), LianaDescError> {
+    if recovery_paths.is_empty() {
+        return Err(LianaDescError::NoRecoveryPath);
+    }
+    if owner_keys.sha256_lock().is_some() {
+        return Err(LianaDescError::PrimaryPathHashLock);
+    }
 
-        // Check all keys are valid according to our standard (this checks all are multipath keys).
-        let all_keys = owner_keys.keys().iter().chain(heir_keys.keys().iter());
-        if let Some(key) = all_keys.clone().find(|k| !is_valid_desc_key(k)) {
-            return Err(LianaDescError::InvalidKey((*key).clone().into()));
+    // Requiring a 16-bit integer for the relative timelocks already rules out most insane values,
+    // but they still must not be null.
+    for timelock in recovery_paths.keys() {
+        match *timelock {
+            RecoveryTimelock::Relative(csv_value) => csv_check(csv_value.into())?,
+            RecoveryTimelock::Absolute(cltv_value) => cltv_check(cltv_value)?,
         }
+    }
 
-        // Check for key duplicates. They are invalid in (nonmalleable) miniscripts.
-        let mut key_set = HashSet::new();
-        for key in all_keys {
-            let xpub = match key {
-                descriptor::DescriptorPublicKey::MultiXPub(ref multi_xpub) => multi_xpub.xkey,
-                _ => unreachable!("Just checked it was a multixpub above"),
-            };
-            if key_set.contains(&xpub) {
+    let all_keys = owner_keys
+        .keys()
+        .iter()
+        .chain(recovery_paths.values().flat_map(|keys| keys.keys().iter()));
+    check_keys(all_keys)
+}
+
+// Sanity check all the keys of a Liana descriptor.
+fn check_keys<'a>(
+    all_keys: impl Iterator<Item = &'a descriptor::DescriptorPublicKey> + Clone,
+) -> Result<(), LianaDescError> {
+    // Check all keys are valid according to our standard (this checks all are multipath keys).
+    if let Some(key) = all_keys.clone().find(|k| !is_valid_desc_key(k)) {
+        return Err(LianaDescError::InvalidKey((*key).clone().into()));
+    }
+
+    // Check for key duplicates. They are invalid in (nonmalleable) miniscripts. The same xpub may
+    // only be reused under different derivation paths.
+    let mut key_set = HashSet::new();
+    for key in all_keys {
+        let xpub = match key {
+            descriptor::DescriptorPublicKey::MultiXPub(ref multi_xpub) => multi_xpub,
+            _ => unreachable!("Just checked it was a multixpub above"),
+        };
+        for der_path in xpub.derivation_paths.paths() {
+            if !key_set.insert((xpub.xkey, der_path.clone())) {
                 return Err(LianaDescError::DuplicateKey(key.clone().into()));
             }
-            key_set.insert(xpub);
         }
-        assert!(!key_set.is_empty());
+    }
+    assert!(!key_set.is_empty());
 
-        // Create the timelocked spending path. If there is a single key we make it a pk_h() in
-        // order to save on the script size (since we assume the timelocked recovery path will
-        // seldom be used).
-        let heir_timelock = Terminal::Older(timelock);
-        let heir_branch = Miniscript::from_ast(Terminal::AndV(
-            Miniscript::from_ast(Terminal::Verify(heir_keys.into_miniscript(true).into()))
-                .expect("Well typed")
-                .into(),
-            Miniscript::from_ast(heir_timelock)
-                .expect("Well typed")
-                .into(),
-        ))
-        .expect("Well typed");
+    Ok(())
+}
 
-        // Combine the timelocked spending path with the simple "primary" path. For the primary key
-        // we don't use a pkh since it's the one that will likely always be used.
+// Whether the given policy may be satisfied without any timelock.
+fn has_timelock_free_path(policy: &SemanticPolicy<descriptor::DescriptorPublicKey>) -> bool {
+    match policy {
+        SemanticPolicy::Unsatisfiable | SemanticPolicy::After(..) | SemanticPolicy::Older(..) => {
+            false
+        }
+        SemanticPolicy::Threshold(k, subs) => {
+            subs.iter()
+                .filter(|sub| has_timelock_free_path(sub))
+                .count()
+                >= *k
+        }
+        _ => true,
+    }
+}
+
+impl MultipathDescriptor {
+    /// Create a P2WSH Liana descriptor from the keys of the primary spending path and one or more
+    /// recovery paths, each with its own set of keys and (relative or absolute) timelock.
+    /// Recovery paths may also require a hash preimage (see [LianaDescKeys::with_sha256_lock]).
+    pub fn new(
+        owner_keys: LianaDescKeys,
+        recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys>,
+    ) -> Result<MultipathDescriptor, LianaDescError> {
+        check_spending_paths(&owner_keys, &recovery_paths)?;
+
+        // Create the timelocked spending paths. If there is a single key we make it a pk_h() in
+        // order to save on the script size (since we assume the timelocked recovery paths will
+        // seldom be used).
+        // The recovery paths are combined in a chain of or_i() such as the ones with a relative
+        // timelock come first, by increasing timelock, followed by the ones with an absolute
+        // timelock. We iterate in reverse order to build the chain from its end.
+        let mut heirs_branch = None;
+        for (timelock, heir_keys) in recovery_paths.into_iter().rev() {
+            let heir_lock = recovery_lock_miniscript(timelock, heir_keys.sha256_lock);
+            let heir_branch = Miniscript::from_ast(Terminal::AndV(
+                Miniscript::from_ast(Terminal::Verify(heir_keys.into_miniscript(true).into()))
+                    .expect("Well typed")
+                    .into(),
+                heir_lock.into(),
+            ))
+            .expect("Well typed");
+            heirs_branch = Some(if let Some(next_branch) = heirs_branch {
+                Miniscript::from_ast(Terminal::OrI(heir_branch.into(), next_branch.into()))
+                    .expect("Well typed")
+            } else {
+                heir_branch
+            });
+        }
+        let heirs_branch = heirs_branch.expect("Checked there is at least one recovery path");
+
+        // Combine the timelocked spending paths with the simple "primary" path. For the primary key
+        // we don't use a pkh since it's the one that will likely always be used.
         let tl_miniscript = Miniscript::from_ast(Terminal::OrD(
             owner_keys.into_miniscript(false).into(),
-            heir_branch.into(),
+            heirs_branch.into(),
         ))
         .expect("Well typed");
         miniscript::Segwitv0::check_local_validity(&tl_miniscript)
@@ -657,8 +1264,140 @@ impl MultipathDescriptor {
             descriptor::Wsh::new(tl_miniscript).expect("Must pass sanity checks"),
         );
 
-        // Compute the receive and change "sub" descriptors right away. According to our pubkey
-        // check above, there must be only two of those, 0 and 1.
+        Ok(MultipathDescriptor::from_multi_desc(multi_desc))
+    }
+
+    /// Create a Taproot Liana descriptor from the keys of the primary spending path and one or
+    /// more recovery paths, each with its own set of keys and (relative or absolute) timelock.
+    /// Recovery paths may also require a hash preimage (see [LianaDescKeys::with_sha256_lock]).
+    ///
+    /// If the primary path is a single key, it is used as the internal key so spending through
+    /// the primary path is a key spend. Otherwise the internal key is an unspendable key and the
+    /// primary path is a tapleaf alongside the recovery paths.
+    pub fn new_taproot(
+        owner_keys: LianaDescKeys,
+        recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys>,
+    ) -> Result<MultipathDescriptor, LianaDescError> {
+        check_spending_paths(&owner_keys, &recovery_paths)?;
+
+        // One leaf per recovery path, in the same order as for P2WSH descriptors.
+        let mut leaves: Vec<descriptor::TapTree<descriptor::DescriptorPublicKey>> = recovery_paths
+            .into_iter()
+            .map(|(timelock, heir_keys)| {
+                let heir_lock = recovery_lock_miniscript(timelock, heir_keys.sha256_lock);
+                let heir_leaf = Miniscript::from_ast(Terminal::AndV(
+                    Miniscript::from_ast(Terminal::Verify(heir_keys.into_tap_miniscript().into()))
+                        .expect("Well typed")
+                        .into(),
+                    heir_lock.into(),
+                ))
+                .expect("Well typed");
+                descriptor::TapTree::Leaf(heir_leaf.into())
+            })
+            .collect();
+
+        // The primary path is the key path if possible. Otherwise it's the first leaf.
+        let internal_key = if let Some(owner_key) = owner_keys.single_key() {
+            owner_key.clone()
+        } else {
+            let network = match owner_keys.keys()[0] {
+                descriptor::DescriptorPublicKey::MultiXPub(ref multi_xpub) => {
+                    multi_xpub.xkey.network
+                }
+                _ => unreachable!("Checked it was a multixpub above"),
+            };
+            leaves.insert(
+                0,
+                descriptor::TapTree::Leaf(owner_keys.into_tap_miniscript().into()),
+            );
+            unspendable_key(network)
+        };
+
+        let tr_desc = descriptor::Tr::new(internal_key, Some(balanced_taptree(leaves)))
+            .expect("Must pass sanity checks");
+        Ok(MultipathDescriptor::from_multi_desc(
+            descriptor::Descriptor::Tr(tr_desc),
+        ))
+    }
+
+    /// Compile a Miniscript policy into a Liana descriptor, using a P2WSH descriptor or a Taproot
+    /// one if `is_taproot` is set. For instance a policy for a 2-of-2 which becomes spendable by a
+    /// third key after a timelock:
+    /// `or(99@thresh(2,pk(A/<0;1>/*),pk(B/<0;1>/*)),and(pk(C/<0;1>/*),older(4032)))`.
+    ///
+    /// The policy must be a disjunction between a primary, timelock-free, spending path and one or
+    /// more recovery paths with a (relative or absolute) timelock. All keys must be valid Liana
+    /// keys.
+    pub fn from_policy(
+        policy_str: &str,
+        is_taproot: bool,
+    ) -> Result<MultipathDescriptor, LianaDescError> {
+        let policy = policy_str
+            .parse::<ConcretePolicy<descriptor::DescriptorPublicKey>>()
+            .map_err(LianaDescError::Miniscript)?;
+        let keys = policy.keys();
+        if keys.is_empty() {
+            return Err(LianaDescError::IncompatiblePolicy(
+                "There must be at least one key.",
+            ));
+        }
+        check_keys(keys.iter().copied())?;
+
+        // Sanity check the timelocks and make sure there is a primary path which doesn't need one.
+        let sem_policy = policy.lift().map_err(LianaDescError::Miniscript)?;
+        let (rel_timelocks, abs_timelocks) = (
+            sem_policy.relative_timelocks(),
+            sem_policy.absolute_timelocks(),
+        );
+        if rel_timelocks.is_empty() && abs_timelocks.is_empty() {
+            return Err(LianaDescError::NoRecoveryPath);
+        }
+        for csv_value in rel_timelocks {
+            csv_check(csv_value)?;
+        }
+        for cltv_value in abs_timelocks {
+            cltv_check(cltv_value)?;
+        }
+        if !has_timelock_free_path(&sem_policy.normalized()) {
+            return Err(LianaDescError::IncompatiblePolicy(
+                "There must be a spending path without timelock.",
+            ));
+        }
+
+        let multi_desc = if is_taproot {
+            // The compiler uses the most likely key to spend alone as the internal key if there
+            // is one, and the unspendable key otherwise.
+            let network = match *keys[0] {
+                descriptor::DescriptorPublicKey::MultiXPub(ref multi_xpub) => {
+                    multi_xpub.xkey.network
+                }
+                _ => unreachable!("Checked it was a multixpub above"),
+            };
+            policy
+                .compile_tr(Some(unspendable_key(network)))
+                .map_err(LianaDescError::Miniscript)?
+        } else {
+            let ms = policy
+                .compile::<miniscript::Segwitv0>()
+                .map_err(|e| LianaDescError::Miniscript(e.into()))?;
+            descriptor::Descriptor::new_wsh(ms).map_err(LianaDescError::Miniscript)?
+        };
+
+        // Finally, make sure the compiled descriptor has the shape of a Liana descriptor.
+        check_liana_desc(&multi_desc).map_err(|e| match e {
+            LianaDescError::IncompatibleDesc => LianaDescError::IncompatiblePolicy(
+                "The policy must be a choice between a set of keys without timelock and one or more sets of keys with distinct timelocks.",
+            ),
+            e => e,
+        })?;
+        Ok(MultipathDescriptor::from_multi_desc(multi_desc))
+    }
+
+    // Compute the receive and change "sub" descriptors of this multipath descriptor. The keys
+    // must have been checked already: there must be only two derivation paths, 0 and 1.
+    fn from_multi_desc(
+        multi_desc: descriptor::Descriptor<descriptor::DescriptorPublicKey>,
+    ) -> MultipathDescriptor {
         // We use /0/* for receiving and /1/* for change.
         // FIXME: don't rely on into_single_descs()'s ordering.
         let mut singlepath_descs = multi_desc
@@ -670,11 +1409,16 @@ impl MultipathDescriptor {
         let receive_desc = InheritanceDescriptor(singlepath_descs.next().expect("First of 2"));
         let change_desc = InheritanceDescriptor(singlepath_descs.next().expect("Second of 2"));
 
-        Ok(MultipathDescriptor {
+        MultipathDescriptor {
             multi_desc,
             receive_desc,
             change_desc,
-        })
+        }
+    }
+
+    /// Whether this is a Taproot descriptor.
+    pub fn is_taproot(&self) -> bool {
+        matches!(self.multi_desc, descriptor::Descriptor::Tr(..))
     }
 
     /// Whether all xpubs contained in this descriptor are for the passed expected network.
@@ -698,87 +1442,119 @@ impl MultipathDescriptor {
         &self.change_desc
     }
 
+    /// Get the BIP388 wallet policy for this descriptor. Keys are replaced by placeholders
+    /// numbered by order of first appearance in the descriptor. A key reused across spending paths
+    /// (same xpub and origin) gets a single placeholder with a different derivation for each use.
+    pub fn to_wallet_policy(&self) -> WalletPolicy {
+        let secp = secp256k1::Secp256k1::verification_only();
+
+        let mut keys_info = Vec::new();
+        self.multi_desc.for_each_key(|key| {
+            let info = match key {
+                descriptor::DescriptorPublicKey::MultiXPub(xpub) => key_info(xpub, &secp),
+                _ => unreachable!("All keys in a Liana descriptor are multixpubs"),
+            };
+            if !keys_info.contains(&info) {
+                keys_info.push(info);
+            }
+            true
+        });
+        let template = self
+            .multi_desc
+            .translate_pk(&mut PlaceholderTranslator {
+                keys_info: &keys_info,
+                secp: &secp,
+            })
+            .expect("Translation never fails")
+            .to_string();
+        let template = template
+            .split('#')
+            .next()
+            .expect("Always at least one element")
+            .to_string();
+
+        WalletPolicy {
+            template,
+            keys_info,
+        }
+    }
+
+    /// Parse a Liana descriptor from a BIP388 wallet policy.
+    pub fn from_wallet_policy(
+        policy: &WalletPolicy,
+    ) -> Result<MultipathDescriptor, LianaDescError> {
+        policy.descriptor_string().parse()
+    }
+
     /// Parse information about this descriptor
     pub fn info(&self) -> LianaDescInfo {
-        // Get the Miniscript
-        let wsh_desc = match &self.multi_desc {
-            descriptor::Descriptor::Wsh(desc) => desc,
-            _ => unreachable!(),
-        };
-        let ms = match wsh_desc.as_inner() {
-            descriptor::WshInner::Ms(ms) => ms,
-            _ => unreachable!(),
-        };
+        // Get the semantic policy of each of the spending paths
+        let subs = spending_paths(&self.multi_desc)
+            .expect("The policy is always 'one of the primary or the recovery paths'");
+        assert!(subs.len() >= 2);
 
-        // Lift the semantic policy from the Miniscript
-        let policy = ms
-            .lift()
-            .expect("Lifting can't fail on a Miniscript")
-            .normalized();
-        let subs = match policy {
-            SemanticPolicy::Threshold(1, subs) => subs,
-            _ => unreachable!("The policy is always 'one of the primary or the recovery path'"),
-        };
-        // For now we only ever allow a single recovery path.
-        assert_eq!(subs.len(), 2);
-
-        // Fetch the primary, non-timelocked path from the two sub-policies. Then parse information
+        // Fetch the primary, non-timelocked path from the sub-policies. Then parse information
         // about it.
         let prim_path_pos = subs
             .iter()
             .position(|sub| is_single_key_or_multisig(&sub))
-            .expect(
-                "One of the two available paths must always be a set of keys without timelock.",
-            );
+            .expect("One of the available paths must always be a set of keys without timelock.");
         let primary_path = PathInfo::from_single_key_or_multisig(subs[prim_path_pos].clone())
             .expect("Must always be a set of keys without timelock");
 
-        // Since there is only two subs, the timelocked recovery path must be the other sub. From
-        // the recovery sub policy fetch the timelock policy on the one hand, and the set of keys
-        // on the other one.
-        let reco_path_pos = prim_path_pos ^ 1;
-        let reco_subs = match subs[reco_path_pos] {
-            SemanticPolicy::Threshold(2, ref subs) => subs,
-            _ => unreachable!(
-                "The recovery path policy must be two subs: a timelock + a set of keys."
-            ),
-        };
-        let (csv_pos, csv) = reco_subs
-            .iter()
-            .enumerate()
-            .find_map(|(i, s)| match s {
-                SemanticPolicy::Older(csv) => Some((
-                    i,
-                    u16::try_from(csv.0).expect("Must always be a 'clean' block height"),
-                )),
-                _ => None,
-            })
-            .expect("A relative timelock policy is always present in the recovery path.");
-        let recovery_keys = PathInfo::from_single_key_or_multisig(reco_subs[csv_pos ^ 1].clone())
-            .expect("Must always be a set of keys alongside the timelock");
-        let recovery_path = (csv, recovery_keys);
+        // All the other subs are timelocked recovery paths. From each recovery sub policy fetch the
+        // timelock policy on the one hand, and the set of keys on the other one.
+        // Some recovery paths may also have a hash lock.
+        let (mut recovery_paths, mut abs_recovery_paths) = (BTreeMap::new(), BTreeMap::new());
+        let mut sha256_locks = BTreeMap::new();
+        for (_, sub) in subs.iter().enumerate().filter(|(i, _)| *i != prim_path_pos) {
+            let path = recovery_path_info(sub).expect(
+                "The recovery path policy must be a timelock + a set of keys + maybe a hash lock.",
+            );
+            let keys = PathInfo::from_single_key_or_multisig(path.keys.clone())
+                .expect("Must always be a set of keys alongside the timelock");
+            let timelock = path
+                .timelock
+                .checked()
+                .expect("Timelocks are checked at creation and parsing");
+            if let Some(hash) = path.sha256_lock {
+                sha256_locks.insert(timelock, hash);
+            }
+            match timelock {
+                RecoveryTimelock::Relative(csv_value) => recovery_paths.insert(csv_value, keys),
+                RecoveryTimelock::Absolute(cltv_value) => {
+                    abs_recovery_paths.insert(cltv_value, keys)
+                }
+            };
+        }
 
-        LianaDescInfo::new(primary_path, recovery_path)
+        LianaDescInfo::new(
+            primary_path,
+            recovery_paths,
+            abs_recovery_paths,
+            sha256_locks,
+        )
     }
 
-    /// Get the value (in blocks) of the relative timelock for the heir's spending path.
-    pub fn timelock_value(&self) -> u32 {
-        // TODO: make it return a u16
-        self.info().recovery_path.0 as u32
+    /// Get the value (in blocks) of the relative timelock of the first recovery path to become
+    /// available, that is the lowest one. None if all the recovery paths have an absolute
+    /// timelock.
+    pub fn first_timelock_value(&self) -> Option<u16> {
+        self.info().recovery_paths.keys().next().copied()
     }
 
     /// Get the maximum size in WU of a satisfaction for this descriptor.
     pub fn max_sat_weight(&self) -> usize {
         self.multi_desc
             .max_satisfaction_weight()
-            .expect("Cannot fail for P2WSH")
+            .expect("Cannot fail for P2WSH or P2TR")
     }
 
     /// Get the maximum size in vbytes (rounded up) of a satisfaction for this descriptor.
     pub fn max_sat_vbytes(&self) -> usize {
         self.multi_desc
             .max_satisfaction_weight()
-            .expect("Cannot fail for P2WSH")
+            .expect("Cannot fail for P2WSH or P2TR")
             .checked_add(WITNESS_FACTOR - 1)
             .unwrap()
             .checked_div(WITNESS_FACTOR)
@@ -792,6 +1568,61 @@ impl MultipathDescriptor {
         32 + 4 + 4 + 1 + wu_to_vb(self.max_sat_weight())
     }
 
+    /// Get the size in WU of a satisfaction through the given spending path of this descriptor.
+    /// The signatures are assumed to be of the maximum size.
+    ///
+    /// # Panics
+    /// - If the given path isn't one of the spending paths of this descriptor.
+    pub fn path_sat_weight(&self, path: &PathInfo) -> usize {
+        let secp = secp256k1::Secp256k1::verification_only();
+
+        // Derive all the descriptors at the same index for the keys to match.
+        let index = 0;
+        let keys: HashSet<bitcoin::PublicKey> = path
+            .keys()
+            .into_iter()
+            .map(|key| {
+                key.clone()
+                    .into_single_keys()
+                    .into_iter()
+                    .next()
+                    .expect("Multipath keys always have at least two paths")
+                    .at_derivation_index(index)
+                    .expect("Single path key")
+                    .derive_public_key(&secp)
+                    .expect("Never derives at a hardened index")
+            })
+            .collect();
+        let key_spend = match self.multi_desc {
+            descriptor::Descriptor::Tr(ref tr) => path.keys().contains(&tr.internal_key()),
+            _ => false,
+        };
+        let satisfier = PathSatisfier { keys, key_spend };
+
+        let der_desc = self.receive_desc.derive(index.into(), &secp);
+        let (witness, script_sig) = der_desc
+            .0
+            .get_satisfaction(satisfier)
+            .expect("The path must be part of this descriptor");
+        let witness_weight = bitcoin::VarInt(witness.len() as u64).len()
+            + witness
+                .iter()
+                .map(|elem| bitcoin::VarInt(elem.len() as u64).len() + elem.len())
+                .sum::<usize>();
+        let script_sig_size = bitcoin::VarInt(script_sig.len() as u64).len() + script_sig.len();
+
+        script_sig_size * WITNESS_FACTOR + witness_weight
+    }
+
+    /// Get the size in vbytes (rounded up) of a satisfaction through the given spending path of
+    /// this descriptor.
+    ///
+    /// # Panics
+    /// - If the given path isn't one of the spending paths of this descriptor.
+    pub fn path_sat_vbytes(&self, path: &PathInfo) -> usize {
+        wu_to_vb(self.path_sat_weight(path))
+    }
+
     /// Get some information about a PSBT input spending Liana coins.
     /// This analysis assumes that:
     /// - The PSBT input actually spend a Liana coin for this descriptor. Otherwise the analysis will be off.
@@ -800,29 +1631,81 @@ impl MultipathDescriptor {
         &self,
         psbt_in: &PsbtIn,
         txin: &bitcoin::TxIn,
+        lock_time: bitcoin::PackedLockTime,
     ) -> PartialSpendInfo {
-        // Get the identifier of all the keys that signed this transaction.
+        let desc_info = self.info();
+
+        // Get the identifier of all the keys that signed this transaction. For Taproot, those are
+        // the signatures for a script path spend as well as the one for a key path spend.
+        let tap_key_sig_pubkey = psbt_in
+            .tap_key_sig
+            .as_ref()
+            .and(psbt_in.tap_internal_key.as_ref());
         let pubkeys_signed = psbt_in
             .partial_sigs
             .iter()
-            .filter_map(|(pk, _)| psbt_in.bip32_derivation.get(&pk.inner).map(|(fg, _)| *fg));
+            .filter_map(|(pk, _)| psbt_in.bip32_derivation.get(&pk.inner).map(|(fg, _)| *fg))
+            .chain(
+                psbt_in
+                    .tap_script_sigs
+                    .keys()
+                    .map(|(pk, _)| pk)
+                    .chain(tap_key_sig_pubkey)
+                    .filter_map(|pk| psbt_in.tap_key_origins.get(pk).map(|(_, (fg, _))| *fg)),
+            );
+
+        // For the recovery paths with a hash lock, whether a valid preimage is present.
+        let has_preimage = |timelock: RecoveryTimelock| {
+            desc_info.sha256_lock(timelock).map(|hash| {
+                psbt_in
+                    .sha256_preimages
+                    .get(hash)
+                    .map(|preimage| sha256::Hash::hash(preimage) == *hash)
+                    .unwrap_or(false)
+            })
+        };
 
         // Determine the structure of the descriptor. Then compute the spend info for the primary
-        // and recovery paths. Only provide the spend info for the recovery path if it is available
-        // (ie if the nSequence is >= to the chosen CSV value).
-        let desc_info = self.info();
+        // and recovery paths. Only provide the spend info for the recovery paths that are available
+        // (ie if the nSequence is >= to their CSV value, or the nLockTime is >= to their CLTV
+        // value and of the same type).
         let primary_path = desc_info.primary_path.spend_info(pubkeys_signed.clone());
-        let recovery_path = if txin.sequence.is_height_locked()
-            && txin.sequence.0 >= desc_info.recovery_path.0 as u32
-        {
-            Some(desc_info.recovery_path.1.spend_info(pubkeys_signed))
+        let recovery_paths = if txin.sequence.is_height_locked() {
+            desc_info
+                .recovery_paths
+                .iter()
+                .filter(|(timelock, _)| txin.sequence.0 >= **timelock as u32)
+                .map(|(timelock, path_info)| {
+                    let mut spend_info = path_info.spend_info(pubkeys_signed.clone());
+                    spend_info.has_preimage = has_preimage(RecoveryTimelock::Relative(*timelock));
+                    (*timelock, spend_info)
+                })
+                .collect()
         } else {
-            None
+            BTreeMap::new()
+        };
+        let abs_recovery_paths = if txin.sequence.enables_absolute_lock_time() {
+            desc_info
+                .abs_recovery_paths
+                .iter()
+                .filter(|(timelock, _)| {
+                    (**timelock < LOCKTIME_THRESHOLD) == (lock_time.0 < LOCKTIME_THRESHOLD)
+                        && lock_time.0 >= **timelock
+                })
+                .map(|(timelock, path_info)| {
+                    let mut spend_info = path_info.spend_info(pubkeys_signed.clone());
+                    spend_info.has_preimage = has_preimage(RecoveryTimelock::Absolute(*timelock));
+                    (*timelock, spend_info)
+                })
+                .collect()
+        } else {
+            BTreeMap::new()
         };
 
         PartialSpendInfo {
             primary_path,
-            recovery_path,
+            recovery_paths,
+            abs_recovery_paths,
         }
     }
 
@@ -857,12 +1740,13 @@ impl MultipathDescriptor {
                 .expect("We checked at least one is present."),
             txins.next().expect("We checked at least one is present."),
         );
-        let spend_info = self.partial_spend_info_txin(first_psbt_in, first_txin);
+        let lock_time = psbt.unsigned_tx.lock_time;
+        let spend_info = self.partial_spend_info_txin(first_psbt_in, first_txin, lock_time);
         for (psbt_in, txin) in psbt_ins.zip(txins) {
             // TODO: maybe it's better to not error if one of the input has more, or different
             // signatures? Instead of erroring we could ignore the superfluous data?
             if txin.sequence != first_txin.sequence
-                || spend_info != self.partial_spend_info_txin(psbt_in, txin)
+                || spend_info != self.partial_spend_info_txin(psbt_in, txin, lock_time)
             {
                 return Err(LianaDescError::InconsistentPsbt);
             }
@@ -872,6 +1756,265 @@ impl MultipathDescriptor {
     }
 }
 
+/// A BIP388 wallet policy: a descriptor template with key placeholders (`@0/<0;1>/*`,
+/// `@1/<0;1>/*`, ..) and the information about the keys they refer to.
+///
+/// It is serialized as a JSON object with a `template` string and a `keys_info` list of strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletPolicy {
+    template: String,
+    keys_info: Vec<descriptor::DescriptorPublicKey>,
+}
+
+// The serialized form of a wallet policy.
+#[derive(Serialize, Deserialize)]
+struct WalletPolicyJson {
+    template: String,
+    keys_info: Vec<String>,
+}
+
+// Find the placeholders in a wallet policy template. Returns the position of each placeholder
+// (without its derivation steps) along with the index of the key it refers to.
+fn key_placeholders(template: &str) -> Result<Vec<(usize, usize, usize)>, LianaDescError> {
+    let mut placeholders = Vec::new();
+    let mut cursor = 0;
+    while let Some(offset) = template[cursor..].find('@') {
+        let start = cursor + offset;
+        let end = template[start + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map(|offset| start + 1 + offset)
+            .unwrap_or_else(|| template.len());
+        let index = template[start + 1..end]
+            .parse::<usize>()
+            .map_err(|_| LianaDescError::InvalidWalletPolicy("invalid key placeholder"))?;
+        if !template[end..].starts_with("/**") && !template[end..].starts_with("/<") {
+            return Err(LianaDescError::InvalidWalletPolicy(
+                "key placeholders must be followed by '/**' or '/<M;N>/*'",
+            ));
+        }
+        placeholders.push((start, end, index));
+        cursor = end;
+    }
+    Ok(placeholders)
+}
+
+// The derivation steps following a key placeholder, with the '/**' shorthand expanded.
+fn placeholder_derivation(template_rest: &str) -> &str {
+    if template_rest.starts_with("/**") {
+        "/<0;1>/*"
+    } else {
+        let end = template_rest
+            .find("/*")
+            .map(|pos| pos + 2)
+            .unwrap_or_else(|| template_rest.len());
+        &template_rest[..end]
+    }
+}
+
+impl WalletPolicy {
+    /// Create a wallet policy from a descriptor template (without checksum) and the information
+    /// about the keys (xpubs with an optional origin but without derivation steps) referred to by
+    /// its placeholders.
+    pub fn new(
+        template: String,
+        keys_info: Vec<descriptor::DescriptorPublicKey>,
+    ) -> Result<WalletPolicy, LianaDescError> {
+        for key in keys_info.iter() {
+            match key {
+                descriptor::DescriptorPublicKey::XPub(xpub)
+                    if xpub.derivation_path.is_master()
+                        && xpub.wildcard == descriptor::Wildcard::None => {}
+                _ => return Err(LianaDescError::InvalidKey(key.clone().into())),
+            }
+        }
+
+        // Placeholders must refer to existing keys, by order of first appearance. All the keys
+        // must be used. A key may be reused, but only with a different derivation.
+        let mut n_keys_used = 0;
+        let mut key_exprs = HashSet::new();
+        for (_, end, index) in key_placeholders(&template)? {
+            if index > n_keys_used || index >= keys_info.len() {
+                return Err(LianaDescError::InvalidWalletPolicy(
+                    "key placeholders must be numbered by order of first appearance",
+                ));
+            }
+            if index == n_keys_used {
+                n_keys_used += 1;
+            }
+            if !key_exprs.insert((index, placeholder_derivation(&template[end..]))) {
+                return Err(LianaDescError::InvalidWalletPolicy(
+                    "a key may not be used twice with the same derivation",
+                ));
+            }
+        }
+        if n_keys_used != keys_info.len() {
+            return Err(LianaDescError::InvalidWalletPolicy(
+                "all keys must be used in the template",
+            ));
+        }
+
+        Ok(WalletPolicy {
+            template,
+            keys_info,
+        })
+    }
+
+    /// The descriptor template, with key placeholders.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The keys referred to by the placeholders of the template.
+    pub fn keys_info(&self) -> &[descriptor::DescriptorPublicKey] {
+        &self.keys_info
+    }
+
+    // Get the descriptor string by replacing the placeholders with the keys.
+    fn descriptor_string(&self) -> String {
+        let mut desc_str = String::with_capacity(self.template.len());
+        let mut cursor = 0;
+        for (start, end, index) in key_placeholders(&self.template).expect("Checked at creation") {
+            desc_str.push_str(&self.template[cursor..start]);
+            desc_str.push_str(&self.keys_info[index].to_string());
+            cursor = end;
+        }
+        desc_str.push_str(&self.template[cursor..]);
+
+        desc_str.replace("/**", "/<0;1>/*")
+    }
+}
+
+impl fmt::Display for WalletPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = serde_json::to_string(&WalletPolicyJson::from(self)).map_err(|_| fmt::Error)?;
+        write!(f, "{}", json)
+    }
+}
+
+impl str::FromStr for WalletPolicy {
+    type Err = LianaDescError;
+
+    fn from_str(s: &str) -> Result<WalletPolicy, Self::Err> {
+        let json: WalletPolicyJson = serde_json::from_str(s)
+            .map_err(|_| LianaDescError::InvalidWalletPolicy("invalid JSON encoding"))?;
+        WalletPolicy::try_from(json)
+    }
+}
+
+impl From<&WalletPolicy> for WalletPolicyJson {
+    fn from(policy: &WalletPolicy) -> WalletPolicyJson {
+        WalletPolicyJson {
+            template: policy.template.clone(),
+            keys_info: policy.keys_info.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+}
+
+impl TryFrom<WalletPolicyJson> for WalletPolicy {
+    type Error = LianaDescError;
+
+    fn try_from(json: WalletPolicyJson) -> Result<WalletPolicy, Self::Error> {
+        let keys_info = json
+            .keys_info
+            .iter()
+            .map(|key| key.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| LianaDescError::InvalidWalletPolicy("invalid key information"))?;
+        WalletPolicy::new(json.template, keys_info)
+    }
+}
+
+impl Serialize for WalletPolicy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WalletPolicyJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WalletPolicy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<WalletPolicy, D::Error> {
+        let json = WalletPolicyJson::deserialize(deserializer)?;
+        WalletPolicy::try_from(json).map_err(serde::de::Error::custom)
+    }
+}
+
+// The key information for a multipath xpub in a BIP388 wallet policy. Derivation steps before the
+// multipath step are moved to the key origin.
+fn key_info(
+    xpub: &descriptor::DescriptorMultiXKey<bip32::ExtendedPubKey>,
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+) -> descriptor::DescriptorPublicKey {
+    let der_path = &xpub.derivation_paths.paths()[0];
+    let prefix = bip32::DerivationPath::from(&der_path[..der_path.len() - 1]);
+    let (xkey, origin) = if prefix.is_master() {
+        (xpub.xkey, xpub.origin.clone())
+    } else {
+        let xkey = xpub
+            .xkey
+            .derive_pub(secp, &prefix)
+            .expect("Hardened derivation steps are disallowed for xpubs");
+        let (fingerprint, origin_path) = xpub
+            .origin
+            .clone()
+            .unwrap_or_else(|| (xpub.xkey.fingerprint(), bip32::DerivationPath::master()));
+        (xkey, Some((fingerprint, origin_path.extend(prefix))))
+    };
+
+    descriptor::DescriptorPublicKey::XPub(descriptor::DescriptorXKey {
+        origin,
+        xkey,
+        derivation_path: bip32::DerivationPath::master(),
+        wildcard: descriptor::Wildcard::None,
+    })
+}
+
+// Replace the keys of a Liana descriptor by their placeholder in a wallet policy template, along
+// with the multipath step of their derivation.
+struct PlaceholderTranslator<'a, C: secp256k1::Verification> {
+    keys_info: &'a [descriptor::DescriptorPublicKey],
+    secp: &'a secp256k1::Secp256k1<C>,
+}
+
+impl<'a, C: secp256k1::Verification> Translator<descriptor::DescriptorPublicKey, String, ()>
+    for PlaceholderTranslator<'a, C>
+{
+    fn pk(&mut self, pk: &descriptor::DescriptorPublicKey) -> Result<String, ()> {
+        let xpub = match pk {
+            descriptor::DescriptorPublicKey::MultiXPub(xpub) => xpub,
+            _ => unreachable!("All keys in a Liana descriptor are multixpubs"),
+        };
+        let info = key_info(xpub, self.secp);
+        let index = self
+            .keys_info
+            .iter()
+            .position(|key| *key == info)
+            .expect("All keys were collected");
+        let der_paths = xpub.derivation_paths.paths();
+        let len = der_paths[0].len();
+        Ok(format!(
+            "@{}/<{};{}>/*",
+            index,
+            der_paths[0][len - 1],
+            der_paths[1][len - 1]
+        ))
+    }
+
+    fn sha256(&mut self, sha256: &sha256::Hash) -> Result<String, ()> {
+        Ok(sha256.to_string())
+    }
+
+    fn hash256(&mut self, hash256: &hash256::Hash) -> Result<String, ()> {
+        Ok(hash256.to_string())
+    }
+
+    fn ripemd160(&mut self, ripemd160: &ripemd160::Hash) -> Result<String, ()> {
+        Ok(ripemd160.to_string())
+    }
+
+    fn hash160(&mut self, hash160: &hash160::Hash) -> Result<String, ()> {
+        Ok(hash160.to_string())
+    }
+}
+
 impl InheritanceDescriptor {
     /// Derive this descriptor at a given index for a receiving address.
     ///
@@ -934,21 +2077,39 @@ impl InheritanceDescriptor {
 /// Map of a raw public key to the xpub used to derive it and its derivation path
 pub type Bip32Deriv = BTreeMap<secp256k1::PublicKey, (bip32::Fingerprint, bip32::DerivationPath)>;
 
+/// Map of an x-only public key to the hashes of the tapleaves it's used in and to the xpub used to
+/// derive it along with its derivation path.
+pub type TapKeyOrigins = BTreeMap<
+    secp256k1::XOnlyPublicKey,
+    (
+        Vec<TapLeafHash>,
+        (bip32::Fingerprint, bip32::DerivationPath),
+    ),
+>;
+
 impl DerivedInheritanceDescriptor {
     pub fn address(&self, network: bitcoin::Network) -> bitcoin::Address {
         self.0
             .address(network)
-            .expect("A P2WSH always has an address")
+            .expect("A P2WSH or P2TR always has an address")
     }
 
     pub fn script_pubkey(&self) -> bitcoin::Script {
         self.0.script_pubkey()
     }
 
+    /// Get the witness script of a P2WSH descriptor.
+    ///
+    /// # Panics
+    /// - If this is a Taproot descriptor.
     pub fn witness_script(&self) -> bitcoin::Script {
         self.0.explicit_script().expect("Not a Taproot descriptor")
     }
 
+    /// Get the derivation paths of all the keys in a P2WSH descriptor.
+    ///
+    /// # Panics
+    /// - If this is a Taproot descriptor.
     pub fn bip32_derivations(&self) -> Bip32Deriv {
         let ms = match self.0 {
             descriptor::Descriptor::Wsh(ref wsh) => match wsh.as_inner() {
@@ -957,7 +2118,7 @@ impl DerivedInheritanceDescriptor {
                     unreachable!("None of our descriptors is a sorted multi")
                 }
             },
-            _ => unreachable!("All our descriptors are always P2WSH"),
+            _ => unreachable!("Not a P2WSH descriptor"),
         };
 
         // For DerivedPublicKey, Pk::Hash == Self.
@@ -965,11 +2126,81 @@ impl DerivedInheritanceDescriptor {
             .map(|k| (k.key.inner, (k.origin.0, k.origin.1)))
             .collect()
     }
+
+    /// Get the derivation paths of all the keys in a Taproot descriptor, along with the hashes
+    /// of the tapleaves they are used in.
+    ///
+    /// # Panics
+    /// - If this is not a Taproot descriptor.
+    pub fn tap_key_origins(&self) -> TapKeyOrigins {
+        let tr = match self.0 {
+            descriptor::Descriptor::Tr(ref tr) => tr,
+            _ => unreachable!("Not a Taproot descriptor"),
+        };
+
+        let internal_key = tr.internal_key();
+        let mut origins = TapKeyOrigins::new();
+        origins.insert(
+            internal_key.to_x_only_pubkey(),
+            (Vec::new(), internal_key.origin.clone()),
+        );
+        for (_, ms) in tr.iter_scripts() {
+            let leaf_hash = TapLeafHash::from_script(&ms.encode(), LeafVersion::TapScript);
+            for pk in ms.iter_pk() {
+                origins
+                    .entry(pk.to_x_only_pubkey())
+                    .or_insert_with(|| (Vec::new(), pk.origin.clone()))
+                    .0
+                    .push(leaf_hash);
+            }
+        }
+
+        origins
+    }
+
+    /// Fill in the information needed by signers to spend a coin for this descriptor in the
+    /// given PSBT input.
+    pub fn update_psbt_in(&self, psbt_in: &mut PsbtIn) {
+        match self.0 {
+            descriptor::Descriptor::Tr(ref tr) => {
+                let spend_info = tr.spend_info();
+                psbt_in.tap_internal_key = Some(spend_info.internal_key());
+                psbt_in.tap_merkle_root = spend_info.merkle_root();
+                for (_, ms) in tr.iter_scripts() {
+                    let leaf = (ms.encode(), LeafVersion::TapScript);
+                    let control_block = spend_info
+                        .control_block(&leaf)
+                        .expect("The leaf is part of the tree");
+                    psbt_in.tap_scripts.insert(control_block, leaf);
+                }
+                psbt_in.tap_key_origins = self.tap_key_origins();
+            }
+            _ => {
+                psbt_in.witness_script = Some(self.witness_script());
+                psbt_in.bip32_derivation = self.bip32_derivations();
+            }
+        }
+    }
+
+    /// Fill in the information needed by signers to recognize the given PSBT output as paying to
+    /// this descriptor.
+    pub fn update_psbt_out(&self, psbt_out: &mut PsbtOut) {
+        match self.0 {
+            descriptor::Descriptor::Tr(ref tr) => {
+                psbt_out.tap_internal_key = Some(tr.internal_key().to_x_only_pubkey());
+                psbt_out.tap_key_origins = self.tap_key_origins();
+            }
+            _ => {
+                psbt_out.bip32_derivation = self.bip32_derivations();
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutils::single_recovery_path;
 
     use std::str::FromStr;
 
@@ -978,7 +2209,7 @@ mod tests {
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
         let timelock = 52560;
-        assert_eq!(MultipathDescriptor::new(owner_key.clone(), heir_key.clone(), timelock).unwrap().to_string(), "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(52560))))#g7vk9r5l");
+        assert_eq!(MultipathDescriptor::new(owner_key.clone(), single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key.clone())).unwrap().to_string(), "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(52560))))#g7vk9r5l");
 
         // A decaying multisig after 6 months. Note we can't duplicate the keys, so different ones
         // are used. In practice they would both be controlled by the same entity.
@@ -1000,40 +2231,64 @@ mod tests {
             ],
         )
         .unwrap();
-        assert_eq!(MultipathDescriptor::new(primary_keys, recovery_keys, 26352).unwrap().to_string(), "wsh(or_d(multi(3,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[aabb0011/10/4893]xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<0;1>/*,[abcdef01]xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<0;1>/*),and_v(v:multi(2,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*,[abcdef01]xpub6AA2N8RALRYgLD6jT1iXYCEDkndTeZndMtWPbtNX6sY5dPiLtf2T88ahdxrGXMUPoNadgR86sFhBXWQVgifPzDYbY9ZtwK4gqzx4y5Da1DW/<0;1>/*,[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(26352))))#s0zsa6uc");
+        assert_eq!(MultipathDescriptor::new(primary_keys, single_recovery_path(RecoveryTimelock::Relative(26352), recovery_keys)).unwrap().to_string(), "wsh(or_d(multi(3,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[aabb0011/10/4893]xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<0;1>/*,[abcdef01]xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<0;1>/*),and_v(v:multi(2,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*,[abcdef01]xpub6AA2N8RALRYgLD6jT1iXYCEDkndTeZndMtWPbtNX6sY5dPiLtf2T88ahdxrGXMUPoNadgR86sFhBXWQVgifPzDYbY9ZtwK4gqzx4y5Da1DW/<0;1>/*,[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(26352))))#s0zsa6uc");
 
         // We prevent footguns with timelocks by requiring a u16. Note how the following wouldn't
         // compile:
-        //MultipathDescriptor::new(owner_key.clone(), heir_key.clone(), 0x00_01_0f_00).unwrap_err();
-        //MultipathDescriptor::new(owner_key.clone(), heir_key.clone(), (1 << 31) + 1).unwrap_err();
-        //MultipathDescriptor::new(owner_key, heir_key, (1 << 22) + 1).unwrap_err();
+        //MultipathDescriptor::new(owner_key.clone(), single_recovery_path(RecoveryTimelock::Relative(0x00_01_0f_00), heir_key.clone())).unwrap_err();
+        //MultipathDescriptor::new(owner_key.clone(), single_recovery_path(RecoveryTimelock::Relative((1 << 31) + 1), heir_key.clone())).unwrap_err();
+        //MultipathDescriptor::new(owner_key, single_recovery_path(RecoveryTimelock::Relative((1 << 22) + 1), heir_key)).unwrap_err();
 
         // You can't use a null timelock in Miniscript.
-        MultipathDescriptor::new(owner_key, heir_key, 0).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(0), heir_key),
+        )
+        .unwrap_err();
 
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabb0011/10/4893]xpub661MyMwAqRbcFG59fiikD8UV762quhruT8K8bdjqy6N2o3LG7yohoCdLg1m2HAY1W6rfBrtauHkBhbfA4AQ3iazaJj5wVPhwgaRCHBW2DBg/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/24/32/<0;1>/*").unwrap());
         let timelock = 57600;
-        assert_eq!(MultipathDescriptor::new(owner_key.clone(), heir_key, timelock).unwrap().to_string(), "wsh(or_d(pk([aabb0011/10/4893]xpub661MyMwAqRbcFG59fiikD8UV762quhruT8K8bdjqy6N2o3LG7yohoCdLg1m2HAY1W6rfBrtauHkBhbfA4AQ3iazaJj5wVPhwgaRCHBW2DBg/<0;1>/*),and_v(v:pkh([abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/24/32/<0;1>/*),older(57600))))#ak4cm093");
+        assert_eq!(MultipathDescriptor::new(owner_key.clone(), single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key)).unwrap().to_string(), "wsh(or_d(pk([aabb0011/10/4893]xpub661MyMwAqRbcFG59fiikD8UV762quhruT8K8bdjqy6N2o3LG7yohoCdLg1m2HAY1W6rfBrtauHkBhbfA4AQ3iazaJj5wVPhwgaRCHBW2DBg/<0;1>/*),and_v(v:pkh([abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/24/32/<0;1>/*),older(57600))))#ak4cm093");
 
         // We can't pass a raw key, an xpub that is not deriveable, only hardened derivable,
         // without both the change and receive derivation paths, or with more than 2 different
         // derivation paths.
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/0/<0;1>/354").unwrap());
-        MultipathDescriptor::new(owner_key.clone(), heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/0/<0;1>/*'").unwrap());
-        MultipathDescriptor::new(owner_key.clone(), heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let heir_key = LianaDescKeys::from_single(
             descriptor::DescriptorPublicKey::from_str(
                 "[abcdef01]02e24913be26dbcfdf8e8e94870b28725cdae09b448b6c127767bf0154e3a3c8e5",
             )
             .unwrap(),
         );
-        MultipathDescriptor::new(owner_key.clone(), heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/0/*'").unwrap());
-        MultipathDescriptor::new(owner_key.clone(), heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/<0;1;2>/*'").unwrap());
-        MultipathDescriptor::new(owner_key, heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
 
         // And it's checked even in a multisig. For instance:
         let primary_keys = LianaDescKeys::from_multi(
@@ -1052,18 +2307,50 @@ mod tests {
             ],
         )
         .unwrap();
-        MultipathDescriptor::new(primary_keys, recovery_keys, 26352).unwrap_err();
+        MultipathDescriptor::new(
+            primary_keys,
+            single_recovery_path(RecoveryTimelock::Relative(26352), recovery_keys),
+        )
+        .unwrap_err();
 
         // You can't pass duplicate keys, even if they are encoded differently.
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
-        MultipathDescriptor::new(owner_key, heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[00aabb44]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
-        MultipathDescriptor::new(owner_key, heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[00aabb44]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[11223344/2/98]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
-        MultipathDescriptor::new(owner_key, heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
+
+        // The same key may be reused under different derivation paths, though.
+        let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<2;3>/*").unwrap());
+        MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap();
+        // As long as the multipath step is a pair of consecutive indexes starting at an even one.
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<1;2>/*").unwrap());
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
 
         // You can't pass duplicate keys, even across multisigs.
         let primary_keys = LianaDescKeys::from_multi(
@@ -1084,13 +2371,580 @@ mod tests {
             ],
         )
         .unwrap();
-        MultipathDescriptor::new(primary_keys, recovery_keys, 26352).unwrap_err();
+        MultipathDescriptor::new(
+            primary_keys,
+            single_recovery_path(RecoveryTimelock::Relative(26352), recovery_keys),
+        )
+        .unwrap_err();
 
         // No origin in one of the keys
         let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
         let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
         let timelock = 52560;
-        MultipathDescriptor::new(owner_key, heir_key, timelock).unwrap_err();
+        MultipathDescriptor::new(
+            owner_key,
+            single_recovery_path(RecoveryTimelock::Relative(timelock), heir_key),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn multiple_recovery_paths() {
+        let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let second_heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*").unwrap());
+
+        // The recovery paths are ordered by timelock, regardless of the insertion order.
+        let recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys> = [
+            (RecoveryTimelock::Relative(52560), second_heir_key.clone()),
+            (RecoveryTimelock::Relative(26352), heir_key.clone()),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let desc = MultipathDescriptor::new(owner_key.clone(), recovery_paths).unwrap();
+        let desc_str = "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),or_i(and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352)),and_v(v:pkh([aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(52560)))))#7hjdvqfc";
+        assert_eq!(desc.to_string(), desc_str);
+        roundtrip(desc_str);
+
+        // We can get the information about each of the recovery paths.
+        assert_eq!(desc.first_timelock_value(), Some(26352));
+        let info = desc.info();
+        assert_eq!(
+            info.recovery_paths().keys().copied().collect::<Vec<_>>(),
+            vec![26352, 52560]
+        );
+        assert_eq!(
+            info.recovery_paths()[&52560].thresh_fingerprints(),
+            (
+                1,
+                [bip32::Fingerprint::from_str("aabb0011").unwrap()]
+                    .iter()
+                    .copied()
+                    .collect()
+            )
+        );
+
+        // At least one recovery path is required.
+        MultipathDescriptor::new(owner_key.clone(), BTreeMap::new()).unwrap_err();
+
+        // Keys can't be reused across recovery paths.
+        let recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys> = [
+            (RecoveryTimelock::Relative(52560), heir_key.clone()),
+            (RecoveryTimelock::Relative(26352), heir_key),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        MultipathDescriptor::new(owner_key, recovery_paths).unwrap_err();
+
+        // Two recovery paths with the same timelock are refused at parsing time.
+        MultipathDescriptor::from_str("wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),or_i(and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352)),and_v(v:pkh([aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(26352)))))").unwrap_err();
+    }
+
+    #[test]
+    fn absolute_timelock_recovery_path() {
+        let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let second_heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*").unwrap());
+
+        // Relative and absolute timelocks may be mixed. The relative ones come first.
+        let recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys> = [
+            (RecoveryTimelock::Absolute(800_000), second_heir_key.clone()),
+            (RecoveryTimelock::Relative(26352), heir_key.clone()),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let desc = MultipathDescriptor::new(owner_key.clone(), recovery_paths).unwrap();
+        let desc_str = "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),or_i(and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352)),and_v(v:pkh([aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),after(800000)))))#yruwlns2";
+        assert_eq!(desc.to_string(), desc_str);
+        roundtrip(desc_str);
+        let info = desc.info();
+        assert_eq!(
+            info.recovery_paths().keys().copied().collect::<Vec<_>>(),
+            vec![26352]
+        );
+        assert_eq!(
+            info.abs_recovery_paths()
+                .keys()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![800_000]
+        );
+        assert!(info.has_recovery_path(RecoveryTimelock::Absolute(800_000)));
+        assert!(!info.has_recovery_path(RecoveryTimelock::Relative(800)));
+
+        // The absolute timelock must be non-null and fit in 31 bits.
+        for insane_tl in &[0, 0x80_00_00_00, u32::MAX] {
+            let recovery_paths =
+                single_recovery_path(RecoveryTimelock::Absolute(*insane_tl), heir_key.clone());
+            assert!(matches!(
+                MultipathDescriptor::new(owner_key.clone(), recovery_paths),
+                Err(LianaDescError::InsaneTimelock(tl)) if tl == *insane_tl
+            ));
+        }
+
+        // The absolute recovery path is only available once nLockTime is set to a value of the
+        // same type at least as high as the timelock, and nSequence doesn't disable it.
+        let mut psbt: Psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
+        psbt.unsigned_tx.lock_time = bitcoin::PackedLockTime(799_999);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert!(spend_info.abs_recovery_paths().is_empty());
+        psbt.unsigned_tx.lock_time = bitcoin::PackedLockTime(800_000);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert_eq!(spend_info.abs_recovery_paths()[&800_000].threshold, 1);
+        assert!(spend_info.recovery_paths().is_empty());
+        psbt.unsigned_tx.lock_time = bitcoin::PackedLockTime(LOCKTIME_THRESHOLD + 1);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert!(spend_info.abs_recovery_paths().is_empty());
+        psbt.unsigned_tx.lock_time = bitcoin::PackedLockTime(800_000);
+        psbt.unsigned_tx.input[0].sequence = Sequence::MAX;
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert!(spend_info.abs_recovery_paths().is_empty());
+    }
+
+    #[test]
+    fn hash_lock_recovery_path() {
+        let owner_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap());
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let preimage = vec![0x42; 32];
+        let hash = sha256::Hash::hash(&preimage);
+        let timelock = RecoveryTimelock::Relative(26352);
+
+        // The hash lock is an additional condition of the recovery path, both in P2WSH and in
+        // Taproot descriptors.
+        let recovery_paths =
+            single_recovery_path(timelock, heir_key.clone().with_sha256_lock(hash));
+        let desc = MultipathDescriptor::new(owner_key.clone(), recovery_paths.clone()).unwrap();
+        let desc_str = "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),and_v(v:sha256(425ed4e4a36b30ea21b90e21c712c649e8214c29b7eaf68089d1039c6e55384c),older(26352)))))#7mgx2ck2";
+        assert_eq!(desc.to_string(), desc_str);
+        roundtrip(desc_str);
+        let info = desc.info();
+        assert_eq!(info.sha256_lock(timelock), Some(&hash));
+        assert_eq!(info.explanation().recovery_paths[0].sha256_lock, Some(hash));
+        let tr_desc =
+            MultipathDescriptor::new_taproot(owner_key.clone(), recovery_paths.clone()).unwrap();
+        let tr_desc_str = "tr([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,and_v(v:pk([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),and_v(v:sha256(425ed4e4a36b30ea21b90e21c712c649e8214c29b7eaf68089d1039c6e55384c),older(26352))))#ah0w6wup";
+        assert_eq!(tr_desc.to_string(), tr_desc_str);
+        roundtrip(tr_desc_str);
+        assert_eq!(tr_desc.info().sha256_lock(timelock), Some(&hash));
+
+        // Revealing the preimage adds a 32 bytes witness element to the satisfaction.
+        let no_lock_desc = MultipathDescriptor::new(
+            owner_key.clone(),
+            single_recovery_path(timelock, heir_key.clone()),
+        )
+        .unwrap();
+        assert!(no_lock_desc.info().sha256_lock(timelock).is_none());
+        let recov_path = info.recovery_path(timelock).unwrap();
+        assert_eq!(
+            desc.path_sat_weight(recov_path),
+            no_lock_desc.path_sat_weight(recov_path) + 1 + 32
+        );
+
+        // The primary path can't have a hash lock.
+        assert!(matches!(
+            MultipathDescriptor::new(owner_key.with_sha256_lock(hash), recovery_paths),
+            Err(LianaDescError::PrimaryPathHashLock)
+        ));
+
+        // The spend info tells whether a valid preimage is present for the recovery path.
+        let mut psbt: Psbt = psbt_from_str("cHNidP8BAHECAAAAAUSHuliRtuCX1S6JxRuDRqDCKkWfKmWL5sV9ukZ/wzvfAAAAAAD9////AogTAAAAAAAAFgAUIxe7UY6LJ6y5mFBoWTOoVispDmdwFwAAAAAAABYAFKqO83TK+t/KdpAt21z2HGC7/Z2FAAAAAAABASsQJwAAAAAAACIAIIIySQjGCTeyx/rKUQx8qobjhJeNCiVCliBJPdyRX6XKAQVBIQI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDKxzZHapFNYASzIYkEdH9bJz6nnqUG3uBB8kiK1asmgiBgI2cqWpc9UAW2gZt2WkKjvi8KoMCui00pRlL6wG32uKDAz1rML9AAAAAG8AAAAiBgMLcbOxsfLe6+3r1UcjQo77HY0As8OKE4l37yj0/qhIyQyKZPKpAAAAAG8AAAAAAAA=");
+        psbt.unsigned_tx.input[0].sequence = Sequence::from_height(26352);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert_eq!(spend_info.primary_path().has_preimage, None);
+        assert_eq!(
+            spend_info.recovery_paths()[&26352].has_preimage,
+            Some(false)
+        );
+        psbt.inputs[0].sha256_preimages.insert(hash, vec![0x43; 32]);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert_eq!(
+            spend_info.recovery_paths()[&26352].has_preimage,
+            Some(false)
+        );
+        psbt.inputs[0].sha256_preimages.insert(hash, preimage);
+        let spend_info = desc.partial_spend_info(&psbt).unwrap();
+        assert_eq!(spend_info.recovery_paths()[&26352].has_preimage, Some(true));
+        assert_eq!(
+            no_lock_desc
+                .partial_spend_info(&psbt)
+                .unwrap()
+                .recovery_paths()[&26352]
+                .has_preimage,
+            None
+        );
+    }
+
+    #[test]
+    fn policy_compilation() {
+        let key_a = "[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*";
+        let key_b = "[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*";
+        let key_c = "[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*";
+        let parse_key = |key: &str| descriptor::DescriptorPublicKey::from_str(key).unwrap();
+
+        // A 2-of-2 primary path and a single key recovery path, both as P2WSH and Taproot.
+        let policy = format!(
+            "or(99@thresh(2,pk({}),pk({})),and(pk({}),older(4032)))",
+            key_a, key_b, key_c
+        );
+        for is_taproot in &[false, true] {
+            let desc = MultipathDescriptor::from_policy(&policy, *is_taproot).unwrap();
+            assert_eq!(desc.is_taproot(), *is_taproot);
+            roundtrip(&desc.to_string());
+            // The compiler may use another fragment than a multisig, but the semantic is the same.
+            let info = desc.info();
+            let mut primary_keys = info.primary_path().keys();
+            primary_keys.sort();
+            let mut expected_keys = vec![parse_key(key_a), parse_key(key_b)];
+            expected_keys.sort();
+            assert_eq!(info.primary_path().thresh_fingerprints().0, 2);
+            assert_eq!(primary_keys, expected_keys.iter().collect::<Vec<_>>());
+            assert_eq!(info.recovery_paths().len(), 1);
+            assert_eq!(
+                info.recovery_paths()[&4032],
+                PathInfo::Single(parse_key(key_c))
+            );
+        }
+
+        // A single primary key is used as the Taproot internal key.
+        let policy = format!(
+            "or(9@pk({}),or(and(pk({}),older(26352)),and(pk({}),after(800000))))",
+            key_a, key_b, key_c
+        );
+        let desc = MultipathDescriptor::from_policy(&policy, true).unwrap();
+        assert!(desc.to_string().starts_with(&format!("tr({},", key_a)));
+        let info = desc.info();
+        assert_eq!(info.primary_path(), &PathInfo::Single(parse_key(key_a)));
+        assert_eq!(
+            info.recovery_paths()[&26352],
+            PathInfo::Single(parse_key(key_b))
+        );
+        assert_eq!(
+            info.abs_recovery_paths()[&800_000],
+            PathInfo::Single(parse_key(key_c))
+        );
+
+        // Policies which don't fit a Liana descriptor are rejected with the reason.
+        assert!(matches!(
+            MultipathDescriptor::from_policy(&format!("or(pk({}),pk({}))", key_a, key_b), false),
+            Err(LianaDescError::NoRecoveryPath)
+        ));
+        assert!(matches!(
+            MultipathDescriptor::from_policy(&format!("and(pk({}),older(4032))", key_a), false),
+            Err(LianaDescError::IncompatiblePolicy(_))
+        ));
+        assert!(matches!(
+            MultipathDescriptor::from_policy(
+                &format!("and(pk({}),or(pk({}),older(4032)))", key_a, key_b),
+                false
+            ),
+            Err(LianaDescError::IncompatiblePolicy(_))
+        ));
+        assert!(matches!(
+            MultipathDescriptor::from_policy(
+                &format!("or(pk({}),and(pk({}),older(4194305)))", key_a, key_b),
+                false
+            ),
+            Err(LianaDescError::InsaneTimelock(4194305))
+        ));
+        assert!(matches!(
+            MultipathDescriptor::from_policy(
+                &format!("or(pk({}),and(pk({}),older(4032)))", key_a, key_a),
+                false
+            ),
+            Err(LianaDescError::DuplicateKey(_))
+        ));
+        let singlepath_key = "[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/0/*";
+        assert!(matches!(
+            MultipathDescriptor::from_policy(
+                &format!("or(pk({}),and(pk({}),older(4032)))", key_a, singlepath_key),
+                false
+            ),
+            Err(LianaDescError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            MultipathDescriptor::from_policy("or(pk(A),and(pk(B),older(4032)))", false),
+            Err(LianaDescError::Miniscript(_))
+        ));
+    }
+
+    #[test]
+    fn taproot_descriptor() {
+        let secp = secp256k1::Secp256k1::verification_only();
+        let owner_key = descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*").unwrap();
+        let second_owner_key = descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*").unwrap();
+        let heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*").unwrap());
+        let second_heir_key = LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*").unwrap());
+
+        // A single primary key is used as the internal key, the recovery paths are tapleaves.
+        let recovery_paths: BTreeMap<RecoveryTimelock, LianaDescKeys> = [
+            (RecoveryTimelock::Relative(26352), heir_key.clone()),
+            (RecoveryTimelock::Relative(52560), second_heir_key),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let desc = MultipathDescriptor::new_taproot(
+            LianaDescKeys::from_single(owner_key.clone()),
+            recovery_paths,
+        )
+        .unwrap();
+        let desc_str = "tr([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,{and_v(v:pk([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352)),and_v(v:pk([aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(52560))})#pr88z9jp";
+        assert_eq!(desc.to_string(), desc_str);
+        roundtrip(desc_str);
+        assert!(desc.is_taproot());
+        let info = desc.info();
+        assert_eq!(
+            info.primary_path().thresh_fingerprints(),
+            (
+                1,
+                [bip32::Fingerprint::from_str("abcdef01").unwrap()]
+                    .iter()
+                    .copied()
+                    .collect()
+            )
+        );
+        assert_eq!(
+            info.recovery_paths().keys().copied().collect::<Vec<_>>(),
+            vec![26352, 52560]
+        );
+
+        // Spending through the primary path is a key spend: a single 65 bytes signature in the
+        // witness. The recovery paths need to reveal a script and a control block.
+        assert_eq!(desc.path_sat_weight(info.primary_path()), 4 + 1 + 1 + 65);
+        let recovery_weight = desc.path_sat_weight(&info.recovery_paths()[&26352]);
+        assert!(recovery_weight > desc.path_sat_weight(info.primary_path()));
+        assert!(recovery_weight <= desc.max_sat_weight());
+
+        // The derived descriptor fills the Taproot fields of PSBT inputs and outputs.
+        let der_desc = desc.receive_descriptor().derive(11.into(), &secp);
+        assert_eq!(
+            der_desc.address(bitcoin::Network::Bitcoin).address_type(),
+            Some(bitcoin::AddressType::P2tr)
+        );
+        let mut psbt_in = PsbtIn::default();
+        der_desc.update_psbt_in(&mut psbt_in);
+        assert!(psbt_in.witness_script.is_none() && psbt_in.bip32_derivation.is_empty());
+        assert!(psbt_in.tap_internal_key.is_some() && psbt_in.tap_merkle_root.is_some());
+        assert_eq!(psbt_in.tap_scripts.len(), 2);
+        assert_eq!(psbt_in.tap_key_origins.len(), 3);
+        let internal_key_origin = &psbt_in.tap_key_origins[&psbt_in.tap_internal_key.unwrap()];
+        assert!(internal_key_origin.0.is_empty());
+        assert_eq!(
+            internal_key_origin.1,
+            (
+                bip32::Fingerprint::from_str("abcdef01").unwrap(),
+                bip32::DerivationPath::from_str("m/0/11").unwrap()
+            )
+        );
+        let mut psbt_out = PsbtOut::default();
+        der_desc.update_psbt_out(&mut psbt_out);
+        assert_eq!(psbt_out.tap_internal_key, psbt_in.tap_internal_key);
+        assert_eq!(psbt_out.tap_key_origins, psbt_in.tap_key_origins);
+
+        // A multisig primary path is a tapleaf, and the internal key is unspendable.
+        let primary_keys = LianaDescKeys::from_multi(2, vec![owner_key, second_owner_key]).unwrap();
+        let recovery_paths = single_recovery_path(RecoveryTimelock::Relative(26352), heir_key);
+        let desc = MultipathDescriptor::new_taproot(primary_keys, recovery_paths).unwrap();
+        let desc_str = "tr(xpub661MyMwAqRbcF8kEVsgkarDZ67CxJxEzh6rgmfXvnTUK9C3tZV5krcXNqeF4YpqAzsWk8USyLW1UoGZSauWoSAL89nsu5TLRnm9xfrCpDb9/<0;1>/*,{multi_a(2,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*),and_v(v:pk([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352))})#csuu9v72";
+        assert_eq!(desc.to_string(), desc_str);
+        roundtrip(desc_str);
+        let info = desc.info();
+        assert_eq!(info.primary_path().thresh_fingerprints().0, 2);
+        assert_eq!(info.recovery_paths().len(), 1);
+        assert!(desc.all_xpubs_net_is(bitcoin::Network::Bitcoin));
+
+        // There can't be two primary paths, one as the key path and the other as a tapleaf.
+        MultipathDescriptor::from_str("tr([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,{multi_a(2,[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*),and_v(v:pk([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352))})").unwrap_err();
+        // The unspendable key may only be used as internal key.
+        MultipathDescriptor::from_str("tr([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,and_v(v:pk(xpub661MyMwAqRbcF8kEVsgkarDZ67CxJxEzh6rgmfXvnTUK9C3tZV5krcXNqeF4YpqAzsWk8USyLW1UoGZSauWoSAL89nsu5TLRnm9xfrCpDb9/<0;1>/*),older(26352)))").unwrap_err();
+    }
+
+    #[test]
+    fn policy_explanation() {
+        let desc = MultipathDescriptor::from_str("wsh(or_d(multi(3,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[aabb0011/10/4893]xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<0;1>/*,[abcdef02]xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<0;1>/*),or_i(and_v(v:pkh([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(12960)),and_v(v:pkh([aabb0012]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),after(1767225600)))))").unwrap();
+        let explanation = desc.info().explanation();
+        assert_eq!(
+            explanation.to_string(),
+            "3 of {aabb0011, abcdef01, abcdef02} can spend anytime\n\
+             abcdef01 can spend 12960 blocks (~3 months) after a coin is confirmed\n\
+             aabb0012 can spend from 2026-01-01 (UTC)"
+        );
+        assert_eq!(
+            explanation.recovery_paths[0].approx_duration_secs,
+            Some(12960 * 600)
+        );
+        assert_eq!(
+            explanation.recovery_paths[1].path,
+            SpendingPath::Recovery(RecoveryTimelock::Absolute(1767225600))
+        );
+        let abcdef01 = explanation
+            .keys
+            .iter()
+            .find(|k| k.fingerprint == bip32::Fingerprint::from_str("abcdef01").unwrap())
+            .unwrap();
+        assert_eq!(
+            abcdef01.paths,
+            vec![
+                SpendingPath::Primary,
+                SpendingPath::Recovery(RecoveryTimelock::Relative(12960))
+            ]
+        );
+        assert_eq!(explanation.keys.len(), 4);
+
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert_eq!(
+            json["recovery_paths"][0]["path"],
+            serde_json::json!({"recovery": {"relative": 12960}})
+        );
+        assert_eq!(json["primary_path"]["path"], serde_json::json!("primary"));
+        assert_eq!(json["primary_path"]["threshold"], serde_json::json!(3));
+
+        assert_eq!(approx_duration_str(600), "~10 minutes");
+        assert_eq!(approx_duration_str(144 * 600), "~24 hours");
+        assert_eq!(approx_duration_str(52560 * 600), "~12 months");
+        assert_eq!(approx_duration_str(3 * 52560 * 600), "~3 years");
+        assert_eq!(utc_date_str(0), "1970-01-01");
+        assert_eq!(utc_date_str(951_782_400), "2000-02-29");
+    }
+
+    #[test]
+    fn wallet_policy() {
+        let secp = secp256k1::Secp256k1::verification_only();
+
+        // A descriptor with multisigs in both paths, and a Taproot one with an unspendable
+        // internal key.
+        let desc_str = "wsh(or_d(multi(3,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[aabb0011/10/4893]xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR/<0;1>/*,[abcdef01]xpub67zuTXF9Ln4731avKTBSawoVVNRuMfmRvkL7kLUaLBRqma9ZqdHBJg9qx8cPUm3oNQMiXT4TmGovXNoQPuwg17RFcVJ8YrnbcooN7pxVJqC/<0;1>/*),and_v(v:multi(2,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*,[abcdef01]xpub6AA2N8RALRYgLD6jT1iXYCEDkndTeZndMtWPbtNX6sY5dPiLtf2T88ahdxrGXMUPoNadgR86sFhBXWQVgifPzDYbY9ZtwK4gqzx4y5Da1DW/<0;1>/*,[aabb0011/10/4893]xpub6AyxexvxizZJffF153evmfqHcE9MV88fCNCAtP3jQjXJHwrAKri71Tq9jWUkPxj9pja4u6AkCPHY7atgxzSEa2HtDwJfrRWKK4fsfQg4o77/<0;1>/*),older(26352))))#s0zsa6uc";
+        let desc = MultipathDescriptor::from_str(desc_str).unwrap();
+        let policy = desc.to_wallet_policy();
+        assert_eq!(policy.template(), "wsh(or_d(multi(3,@0/<0;1>/*,@1/<0;1>/*,@2/<0;1>/*),and_v(v:multi(2,@3/<0;1>/*,@4/<0;1>/*,@5/<0;1>/*),older(26352))))");
+        assert_eq!(policy.keys_info()[1].to_string(), "[aabb0011/10/4893]xpub6Bw79HbNSeS2xXw1sngPE3ehnk1U3iSPCgLYzC9LpN8m9nDuaKLZvkg8QXxL5pDmEmQtYscmUD8B9MkAAZbh6vxPzNXMaLfGQ9Sb3z85qhR");
+        assert_eq!(
+            MultipathDescriptor::from_wallet_policy(&policy)
+                .unwrap()
+                .to_string(),
+            desc_str
+        );
+        let desc_str = "tr(xpub661MyMwAqRbcF8kEVsgkarDZ67CxJxEzh6rgmfXvnTUK9C3tZV5krcXNqeF4YpqAzsWk8USyLW1UoGZSauWoSAL89nsu5TLRnm9xfrCpDb9/<0;1>/*,{multi_a(2,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*,[abcdef01]xpub69cP4Y7S9TWcbSNxmk6CEDBsoaqr3ZEdjHuZcHxEFFKGh569RsJNr2V27XGhsbH9FXgWUEmKXRN7c5wQfq2VPjt31xP9VsYnVUyU8HcVevm/<0;1>/*),and_v(v:pk([abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(26352))})#csuu9v72";
+        let desc = MultipathDescriptor::from_str(desc_str).unwrap();
+        let policy = desc.to_wallet_policy();
+        assert_eq!(
+            policy.template(),
+            "tr(@0/<0;1>/*,{multi_a(2,@1/<0;1>/*,@2/<0;1>/*),and_v(v:pk(@3/<0;1>/*),older(26352))})"
+        );
+        assert_eq!(
+            MultipathDescriptor::from_wallet_policy(&policy)
+                .unwrap()
+                .to_string(),
+            desc_str
+        );
+
+        // The derivation steps before the multipath step are moved to the key origin.
+        let desc = MultipathDescriptor::from_str("wsh(or_d(pk([aabb0011/10/4893]xpub661MyMwAqRbcFG59fiikD8UV762quhruT8K8bdjqy6N2o3LG7yohoCdLg1m2HAY1W6rfBrtauHkBhbfA4AQ3iazaJj5wVPhwgaRCHBW2DBg/<0;1>/*),and_v(v:pkh([abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/24/32/<0;1>/*),older(57600))))#ak4cm093").unwrap();
+        let policy = desc.to_wallet_policy();
+        assert!(policy.keys_info()[1]
+            .to_string()
+            .starts_with("[abcdef01/24/32]xpub"));
+        let policy_desc = MultipathDescriptor::from_wallet_policy(&policy).unwrap();
+        assert_eq!(
+            policy_desc
+                .receive_descriptor()
+                .derive(42.into(), &secp)
+                .address(bitcoin::Network::Bitcoin),
+            desc.receive_descriptor()
+                .derive(42.into(), &secp)
+                .address(bitcoin::Network::Bitcoin)
+        );
+
+        // The short form for the derivation steps is supported too.
+        let template = "wsh(or_d(pk(@0/**),and_v(v:pkh(@1/**),older(57600))))".to_string();
+        let policy_b = WalletPolicy::new(template, policy.keys_info().to_vec()).unwrap();
+        assert_eq!(
+            MultipathDescriptor::from_wallet_policy(&policy_b).unwrap(),
+            policy_desc
+        );
+
+        // Placeholders must be numbered by order of appearance, all keys must be used and the keys
+        // information may not contain derivation steps.
+        let keys_info = policy.keys_info().to_vec();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@1/**),and_v(v:pkh(@0/**),older(57600))))".to_string(),
+            keys_info.clone(),
+        )
+        .unwrap_err();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@2/**),older(57600))))".to_string(),
+            keys_info.clone(),
+        )
+        .unwrap_err();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@0/**),older(57600))))".to_string(),
+            keys_info.clone(),
+        )
+        .unwrap_err();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0),and_v(v:pkh(@1),older(57600))))".to_string(),
+            keys_info,
+        )
+        .unwrap_err();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@1/**),older(57600))))".to_string(),
+            vec![
+                descriptor::DescriptorPublicKey::from_str("[aabb0011/10/4893]xpub661MyMwAqRbcFG59fiikD8UV762quhruT8K8bdjqy6N2o3LG7yohoCdLg1m2HAY1W6rfBrtauHkBhbfA4AQ3iazaJj5wVPhwgaRCHBW2DBg/<0;1>/*").unwrap(),
+                descriptor::DescriptorPublicKey::from_str("[abcdef01]xpub661MyMwAqRbcFfxf71L4Dx4w5TmyNXrBicTEAM7vLzumxangwATWWgdJPb6xH1JHcJH9S3jNZx3fCnkkB1WyqrqGgavj1rehHcbythmruvZ/24").unwrap(),
+            ],
+        )
+        .unwrap_err();
+
+        // A key reused across spending paths gets a single placeholder, with the actual
+        // derivation of each of its occurrences.
+        let desc_str = "wsh(or_d(pk([abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<0;1>/*),and_v(v:multi(2,[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW/<2;3>/*,[abcdef01]xpub688Hn4wScQAAiYJLPg9yH27hUpfZAUnmJejRQBCiwfP5PEDzjWMNW1wChcninxr5gyavFqbbDjdV1aK5USJz8NDVjUy7FRQaaqqXHh5SbXe/<0;1>/*),older(52560))))";
+        let desc = MultipathDescriptor::from_str(desc_str).unwrap();
+        let policy = desc.to_wallet_policy();
+        assert_eq!(
+            policy.template(),
+            "wsh(or_d(pk(@0/<0;1>/*),and_v(v:multi(2,@0/<2;3>/*,@1/<0;1>/*),older(52560))))"
+        );
+        assert_eq!(policy.keys_info().len(), 2);
+        assert_eq!(policy.keys_info()[0].to_string(), "[abcdef01]xpub6Eze7yAT3Y1wGrnzedCNVYDXUqa9NmHVWck5emBaTbXtURbe1NWZbK9bsz1TiVE7Cz341PMTfYgFw1KdLWdzcM1UMFTcdQfCYhhXZ2HJvTW");
+        assert_eq!(
+            MultipathDescriptor::from_wallet_policy(&policy).unwrap(),
+            desc
+        );
+        // But it can't be reused with the same derivation.
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@0/<0;1>/*),older(52560))))".to_string(),
+            vec![policy.keys_info()[0].clone()],
+        )
+        .unwrap_err();
+        WalletPolicy::new(
+            "wsh(or_d(pk(@0/**),and_v(v:pkh(@0/<2;3>/*),older(52560))))".to_string(),
+            vec![policy.keys_info()[0].clone()],
+        )
+        .unwrap();
+
+        // The wallet policy round-trips through its JSON string representation.
+        let policy_str = policy.to_string();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&policy_str).unwrap(),
+            serde_json::json!({
+                "template": policy.template(),
+                "keys_info": [
+                    policy.keys_info()[0].to_string(),
+                    policy.keys_info()[1].to_string(),
+                ],
+            })
+        );
+        assert_eq!(WalletPolicy::from_str(&policy_str).unwrap(), policy);
+        assert_eq!(
+            serde_json::from_value::<WalletPolicy>(serde_json::to_value(&policy).unwrap()).unwrap(),
+            policy
+        );
+        WalletPolicy::from_str(policy.template()).unwrap_err();
+        WalletPolicy::from_str(&policy_str.replace("@1/<0;1>/*", "@2/<0;1>/*")).unwrap_err();
+        WalletPolicy::from_str(&policy_str.replace("xpub6Eze7", "xpub6Eze8")).unwrap_err();
     }
 
     #[test]
@@ -1112,13 +2966,13 @@ mod tests {
     #[test]
     fn inheritance_descriptor_tl_value() {
         let desc = MultipathDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(1),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.timelock_value(), 1);
+        assert_eq!(desc.first_timelock_value(), Some(1));
 
         let desc = MultipathDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(42000),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.timelock_value(), 42000);
+        assert_eq!(desc.first_timelock_value(), Some(42000));
 
         let desc = MultipathDescriptor::from_str("wsh(andor(pk([abcdef01]tpubDEN9WSToTyy9ZQfaYqSKfmVqmq1VVLNtYfj3Vkqh67et57eJ5sTKZQBkHqSwPUsoSskJeaYnPttHe2VrkCsKA27kUaN9SDc5zhqeLzKa1rr/<0;1>/*),older(65535),pk([abcdef01]tpubD8LYfn6njiA2inCoxwM7EuN3cuLVcaHAwLYeups13dpevd3nHLRdK9NdQksWXrhLQVxcUZRpnp5CkJ1FhE61WRAsHxDNAkvGkoQkAeWDYjV/<0;1>/*)))").unwrap();
-        assert_eq!(desc.timelock_value(), 0xffff);
+        assert_eq!(desc.first_timelock_value(), Some(0xffff));
     }
 
     #[test]
@@ -1145,6 +2999,20 @@ mod tests {
             desc.spender_input_size(),
             32 + 4 + 1 + 4 + wu_to_vb(witness_size),
         );
+
+        // The recovery path is the largest satisfaction. Satisfying the primary path doesn't need
+        // the recovery key nor the dissatisfaction of the primary key (an empty vector).
+        let info = desc.info();
+        let recovery_weight = desc.path_sat_weight(&info.recovery_paths()[&2]);
+        assert_eq!(recovery_weight, desc.max_sat_weight());
+        assert_eq!(
+            desc.path_sat_weight(info.primary_path()),
+            recovery_weight - (1 + 33) - 1
+        );
+        assert_eq!(
+            desc.path_sat_vbytes(info.primary_path()),
+            wu_to_vb(recovery_weight - 35)
+        );
     }
 
     #[test]
@@ -1189,7 +3057,7 @@ mod tests {
     fn repro() {
         // A simple descriptor with 1 keys as primary path and 1 recovery key.
         let desc = MultipathDescriptor::from_str("wsh(or_d(pk([f5acc2fd]tpubD6NzVbkrYhZ4YgUx2ZLNt2rLYAMTdYysCRzKoLu2BeSHKvzqPaBDvf17GeBPnExUVPkuBpx4kniP964e2MxyzzazcXLptxLXModSVCVEV1T/<0;1>/*),and_v(v:pkh([8a64f2a9]tpubD6NzVbkrYhZ4WmzFjvQrp7sDa4ECUxTi9oby8K4FZkd3XCBtEdKwUiQyYJaxiJo5y42gyDWEczrFpozEjeLxMPxjf2WtkfcbpUdfvNnozWF/<0;1>/*),older(10))))#d72le4dr").unwrap();
-        let desc_info = desc.info();
+        let recov_timelock = desc.first_timelock_value().unwrap();
         let prim_key_fg = bip32::Fingerprint::from_str("f5acc2fd").unwrap();
         let recov_key_fg = bip32::Fingerprint::from_str("8a64f2a9").unwrap();
 
@@ -1200,25 +3068,24 @@ mod tests {
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
-        assert!(info.recovery_path.is_none());
+        assert!(info.recovery_paths.is_empty());
 
         // If we set the sequence too low we still won't have the recovery path info.
         unsigned_single_psbt.unsigned_tx.input[0].sequence =
-            Sequence::from_height(desc_info.recovery_path.0 - 1);
+            Sequence::from_height(recov_timelock - 1);
         let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
-        assert!(info.recovery_path.is_none());
+        assert!(info.recovery_paths.is_empty());
 
         // Now if we set the sequence at the right value we'll have it.
-        unsigned_single_psbt.unsigned_tx.input[0].sequence =
-            Sequence::from_height(desc_info.recovery_path.0);
+        unsigned_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(recov_timelock);
         let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
-        assert!(info.recovery_path.is_some());
+        assert!(!info.recovery_paths.is_empty());
 
         // Even if it's a bit too high (as long as it's still a block height and activated)
         unsigned_single_psbt.unsigned_tx.input[0].sequence =
-            Sequence::from_height(desc_info.recovery_path.0 + 42);
+            Sequence::from_height(recov_timelock + 42);
         let info = desc.partial_spend_info(&unsigned_single_psbt).unwrap();
-        let recov_info = info.recovery_path.unwrap();
+        let recov_info = &info.recovery_paths[&recov_timelock];
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 0);
         assert!(recov_info.signed_pubkeys.is_empty());
@@ -1233,11 +3100,10 @@ mod tests {
             info.primary_path.signed_pubkeys.len() == 1
                 && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
         );
-        assert!(info.recovery_path.is_none());
+        assert!(info.recovery_paths.is_empty());
 
         // Now enable the recovery path and add a signature for the recovery key.
-        signed_single_psbt.unsigned_tx.input[0].sequence =
-            Sequence::from_height(desc_info.recovery_path.0);
+        signed_single_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(recov_timelock);
         let recov_pubkey = bitcoin::PublicKey {
             compressed: true,
             inner: *signed_single_psbt.inputs[0]
@@ -1265,7 +3131,7 @@ mod tests {
         assert_eq!(info.primary_path.threshold, 1);
         assert_eq!(info.primary_path.sigs_count, 0);
         assert!(info.primary_path.signed_pubkeys.is_empty());
-        let recov_info = info.recovery_path.unwrap();
+        let recov_info = &info.recovery_paths[&recov_timelock];
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 1);
         assert!(
@@ -1286,12 +3152,12 @@ mod tests {
             info.primary_path.signed_pubkeys.len() == 1
                 && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
         );
-        assert!(info.recovery_path.is_none());
+        assert!(info.recovery_paths.is_empty());
 
         // Enable the recovery path, it should show no recovery sig.
         let mut rec_psbt = psbt.clone();
         for txin in rec_psbt.unsigned_tx.input.iter_mut() {
-            txin.sequence = Sequence::from_height(desc_info.recovery_path.0);
+            txin.sequence = Sequence::from_height(recov_timelock);
         }
         let info = desc.partial_spend_info(&rec_psbt).unwrap();
         assert!(rec_psbt
@@ -1304,7 +3170,7 @@ mod tests {
             info.primary_path.signed_pubkeys.len() == 1
                 && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
         );
-        let recov_info = info.recovery_path.unwrap();
+        let recov_info = &info.recovery_paths[&recov_timelock];
         assert_eq!(recov_info.threshold, 1);
         assert_eq!(recov_info.sigs_count, 0);
         assert!(recov_info.signed_pubkeys.is_empty());
@@ -1312,8 +3178,7 @@ mod tests {
         // If the sequence of one of the input is different from the other ones, it'll return
         // an error since the analysis is on the whole transaction.
         let mut inconsistent_psbt = psbt.clone();
-        inconsistent_psbt.unsigned_tx.input[0].sequence =
-            Sequence::from_height(desc_info.recovery_path.0 + 1);
+        inconsistent_psbt.unsigned_tx.input[0].sequence = Sequence::from_height(recov_timelock + 1);
         assert!(desc
             .partial_spend_info(&inconsistent_psbt)
             .unwrap_err()
@@ -1342,7 +3207,7 @@ mod tests {
             info.primary_path.signed_pubkeys.len() == 1
                 && info.primary_path.signed_pubkeys.contains_key(&prim_key_fg)
         );
-        assert!(info.recovery_path.is_none());
+        assert!(info.recovery_paths.is_empty());
     }
 
     // TODO: test error conditions of deserialization.
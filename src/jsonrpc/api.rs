@@ -1,29 +1,194 @@
 use crate::{
+    database::LabelItem,
+    descriptors::RecoveryTimelock,
     jsonrpc::{Error, Params, Request, Response},
     DaemonControl,
 };
 
-use std::{collections::HashMap, convert::TryInto, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+    str::FromStr,
+};
 
 use miniscript::bitcoin::{self, consensus, util::psbt::PartiallySignedTransaction as Psbt};
 
-fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+// Get the feerate to use for a transaction, either given directly or as a target number of blocks
+// within which the transaction should confirm. Exactly one of the two must be provided.
+fn feerate_param(
+    control: &DaemonControl,
+    params: &Params,
+    feerate_index: usize,
+    conf_target_index: usize,
+) -> Result<u64, Error> {
+    let feerate: Option<u64> = params
+        .get(feerate_index, "feerate")
+        .filter(|f| !f.is_null())
+        .map(|f| {
+            f.as_u64()
+                .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))
+        })
+        .transpose()?;
+    let conf_target: Option<u16> = params
+        .get(conf_target_index, "conf_target")
+        .filter(|t| !t.is_null())
+        .map(|t| {
+            t.as_u64()
+                .and_then(|t| t.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'conf_target' parameter."))
+        })
+        .transpose()?;
+    match (feerate, conf_target) {
+        (Some(_), Some(_)) => Err(Error::invalid_params(
+            "Only one of 'feerate' and 'conf_target' may be provided.",
+        )),
+        (Some(feerate), None) => Ok(feerate),
+        (None, Some(conf_target)) => Ok(control.feerate_for_target(conf_target)?),
+        (None, None) => Err(Error::invalid_params("Missing 'feerate' parameter.")),
+    }
+}
+
+// Get the recovery path to use from either its relative or its absolute timelock, if any is given.
+fn recovery_timelock_param(
+    params: &Params,
+    timelock_index: usize,
+    locktime_index: usize,
+) -> Result<Option<RecoveryTimelock>, Error> {
+    let timelock: Option<u16> = params
+        .get(timelock_index, "timelock")
+        .filter(|tl| !tl.is_null())
+        .map(|tl| {
+            tl.as_u64()
+                .and_then(|tl| tl.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'timelock' parameter."))
+        })
+        .transpose()?;
+    let locktime: Option<u32> = params
+        .get(locktime_index, "locktime")
+        .filter(|lt| !lt.is_null())
+        .map(|lt| {
+            lt.as_u64()
+                .and_then(|lt| lt.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'locktime' parameter."))
+        })
+        .transpose()?;
+    match (timelock, locktime) {
+        (Some(_), Some(_)) => Err(Error::invalid_params(
+            "Only one of 'timelock' and 'locktime' may be provided.",
+        )),
+        (Some(csv), None) => Ok(Some(RecoveryTimelock::Relative(csv))),
+        (None, Some(cltv)) => Ok(Some(RecoveryTimelock::Absolute(cltv))),
+        (None, None) => Ok(None),
+    }
+}
+
+// Get the payments to make, either as a map from address to amount or as a list of address and
+// amount entries. Only the latter can pay the same address more than once.
+fn destinations_param(
+    params: &Params,
+    index: usize,
+) -> Result<Vec<(bitcoin::Address, u64)>, Error> {
     let destinations = params
-        .get(0, "destinations")
-        .ok_or_else(|| Error::invalid_params("Missing 'destinations' parameter."))?
-        .as_object()
-        .and_then(|obj| {
-            obj.into_iter()
-                .map(|(k, v)| {
-                    let addr = bitcoin::Address::from_str(k).ok()?;
-                    let amount: u64 = v.as_i64()?.try_into().ok()?;
-                    Some((addr, amount))
+        .get(index, "destinations")
+        .ok_or_else(|| Error::invalid_params("Missing 'destinations' parameter."))?;
+    let parse_payment = |addr: &str, amount: &serde_json::Value| {
+        let addr = bitcoin::Address::from_str(addr).ok()?;
+        let amount: u64 = amount.as_i64()?.try_into().ok()?;
+        Some((addr, amount))
+    };
+    match destinations {
+        serde_json::Value::Object(obj) => obj
+            .iter()
+            .map(|(addr, amount)| parse_payment(addr, amount))
+            .collect::<Option<Vec<_>>>(),
+        serde_json::Value::Array(arr) => arr
+            .iter()
+            .map(|entry| parse_payment(entry.get("address")?.as_str()?, entry.get("amount")?))
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    }
+    .ok_or_else(|| Error::invalid_params("Invalid 'destinations' parameter."))
+}
+
+fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let destinations = destinations_param(&params, 0)?;
+    let outpoints = params
+        .get(1, "outpoints")
+        .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bitcoin::OutPoint::from_str(e).ok())
+                })
+                .collect::<Option<Vec<bitcoin::OutPoint>>>()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
+    let feerate = feerate_param(control, &params, 2, 4)?;
+    let remainder_address: Option<bitcoin::Address> = params
+        .get(3, "remainder_address")
+        .filter(|addr| !addr.is_null())
+        .map(|addr| {
+            addr.as_str()
+                .and_then(|s| bitcoin::Address::from_str(s).ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'remainder_address' parameter."))
+        })
+        .transpose()?;
+    let spend_frozen = params
+        .get(5, "spend_frozen")
+        .filter(|v| !v.is_null())
+        .map(|v| {
+            v.as_bool()
+                .ok_or_else(|| Error::invalid_params("Invalid 'spend_frozen' parameter."))
+        })
+        .transpose()?
+        .unwrap_or(false);
+
+    let res = control.create_spend(
+        &destinations,
+        &outpoints,
+        feerate,
+        remainder_address.as_ref(),
+        spend_frozen,
+    )?;
+    Ok(serde_json::json!(&res))
+}
+
+fn create_refresh(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let outpoints = params
+        .get(0, "outpoints")
+        .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bitcoin::OutPoint::from_str(e).ok())
                 })
-                .collect::<Option<HashMap<bitcoin::Address, u64>>>()
+                .collect::<Option<Vec<bitcoin::OutPoint>>>()
         })
-        .ok_or_else(|| Error::invalid_params("Invalid 'destinations' parameter."))?;
+        .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
+    let feerate = feerate_param(control, &params, 1, 3)?;
+    let expiring_within: Option<u32> = params
+        .get(2, "expiring_within")
+        .filter(|v| !v.is_null())
+        .map(|v| {
+            v.as_u64()
+                .and_then(|v| v.try_into().ok())
+                .ok_or_else(|| Error::invalid_params("Invalid 'expiring_within' parameter."))
+        })
+        .transpose()?;
+
+    let res = control.create_refresh(&outpoints, expiring_within, feerate)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn create_cpfp(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let outpoints = params
-        .get(1, "outpoints")
+        .get(0, "outpoints")
         .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
         .as_array()
         .and_then(|arr| {
@@ -37,15 +202,39 @@ fn create_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
         })
         .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
     let feerate: u64 = params
-        .get(2, "feerate")
+        .get(1, "feerate")
         .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
         .as_u64()
         .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
 
-    let res = control.create_spend(&destinations, &outpoints, feerate)?;
+    let res = control.create_cpfp(&outpoints, feerate)?;
     Ok(serde_json::json!(&res))
 }
 
+fn freeze_coins(
+    control: &DaemonControl,
+    params: Params,
+    frozen: bool,
+) -> Result<serde_json::Value, Error> {
+    let outpoints = params
+        .get(0, "outpoints")
+        .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bitcoin::OutPoint::from_str(e).ok())
+                })
+                .collect::<Option<Vec<bitcoin::OutPoint>>>()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
+
+    control.freeze_coins(&outpoints, frozen)?;
+    Ok(serde_json::json!({}))
+}
+
 fn update_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let psbt: Psbt = params
         .get(0, "psbt")
@@ -59,6 +248,19 @@ fn update_spend(control: &DaemonControl, params: Params) -> Result<serde_json::V
     Ok(serde_json::json!({}))
 }
 
+fn analyze_psbt(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let psbt: Psbt = params
+        .get(0, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| base64::decode(s).ok())
+        .and_then(|bytes| consensus::deserialize(&bytes).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+    let res = control.analyze_psbt(&psbt)?;
+
+    Ok(serde_json::json!(&res))
+}
+
 fn delete_spend(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txid = params
         .get(0, "txid")
@@ -83,6 +285,23 @@ fn broadcast_spend(control: &DaemonControl, params: Params) -> Result<serde_json
     Ok(serde_json::json!({}))
 }
 
+fn bump_fee(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let txid = params
+        .get(0, "txid")
+        .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Txid::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'txid' parameter."))?;
+    let feerate: u64 = params
+        .get(1, "feerate")
+        .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
+        .as_u64()
+        .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
+    let res = control.bump_fee(&txid, feerate)?;
+
+    Ok(serde_json::json!(&res))
+}
+
 fn list_confirmed(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let start: u32 = params
         .get(0, "start")
@@ -110,6 +329,162 @@ fn list_confirmed(control: &DaemonControl, params: Params) -> Result<serde_json:
     ))
 }
 
+fn list_addresses(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let start_index: u32 = params
+        .get(0, "start_index")
+        .ok_or_else(|| Error::invalid_params("Missing 'start_index' parameter."))?
+        .as_i64()
+        .and_then(|i| i.try_into().ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'start_index' parameter."))?;
+
+    let count: u32 = params
+        .get(1, "count")
+        .ok_or_else(|| Error::invalid_params("Missing 'count' parameter."))?
+        .as_i64()
+        .and_then(|i| i.try_into().ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'count' parameter."))?;
+
+    Ok(serde_json::json!(
+        &control.list_addresses(start_index, count)
+    ))
+}
+
+fn get_address_info(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let address = params
+        .get(0, "address")
+        .ok_or_else(|| Error::invalid_params("Missing 'address' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Address::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'address' parameter."))?;
+
+    let res = control.get_address_info(&address)?;
+    Ok(serde_json::json!(&res))
+}
+
+// The address and message parameters common to the message signing commands.
+fn message_params(params: &Params) -> Result<(bitcoin::Address, String), Error> {
+    let address = params
+        .get(0, "address")
+        .ok_or_else(|| Error::invalid_params("Missing 'address' parameter."))?
+        .as_str()
+        .and_then(|s| bitcoin::Address::from_str(s).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'address' parameter."))?;
+    let message = params
+        .get(1, "message")
+        .ok_or_else(|| Error::invalid_params("Missing 'message' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'message' parameter."))?
+        .to_string();
+
+    Ok((address, message))
+}
+
+fn create_message_psbt(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let (address, message) = message_params(&params)?;
+
+    let res = control.create_message_psbt(&address, &message)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn finalize_message_psbt(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let (address, message) = message_params(&params)?;
+    let psbt: Psbt = params
+        .get(2, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| base64::decode(s).ok())
+        .and_then(|bytes| consensus::deserialize(&bytes).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+
+    let res = control.finalize_message_psbt(&address, &message, psbt)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn verify_message(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let (address, message) = message_params(&params)?;
+    let signature = params
+        .get(2, "signature")
+        .ok_or_else(|| Error::invalid_params("Missing 'signature' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'signature' parameter."))?;
+
+    let res = control.verify_message(&address, &message, signature)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn create_reserves_proof(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let message = params
+        .get(0, "message")
+        .ok_or_else(|| Error::invalid_params("Missing 'message' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'message' parameter."))?;
+    // All our confirmed coins by default.
+    let outpoints = params
+        .get(1, "outpoints")
+        .filter(|v| !v.is_null())
+        .map(|v| {
+            v.as_array()
+                .and_then(|arr| {
+                    arr.iter()
+                        .map(|entry| {
+                            entry
+                                .as_str()
+                                .and_then(|e| bitcoin::OutPoint::from_str(e).ok())
+                        })
+                        .collect::<Option<Vec<bitcoin::OutPoint>>>()
+                })
+                .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let res = control.create_reserves_proof(message, &outpoints)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn verify_reserves_proof(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let message = params
+        .get(0, "message")
+        .ok_or_else(|| Error::invalid_params("Missing 'message' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'message' parameter."))?;
+    let psbt: Psbt = params
+        .get(1, "psbt")
+        .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?
+        .as_str()
+        .and_then(|s| base64::decode(s).ok())
+        .and_then(|bytes| consensus::deserialize(&bytes).ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'psbt' parameter."))?;
+
+    let res = control.verify_reserves_proof(message, psbt)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn get_expiry_report(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let expiring_within: u32 = params
+        .get(0, "expiring_within")
+        .ok_or_else(|| Error::invalid_params("Missing 'expiring_within' parameter."))?
+        .as_i64()
+        .and_then(|i| i.try_into().ok())
+        .ok_or_else(|| Error::invalid_params("Invalid 'expiring_within' parameter."))?;
+
+    Ok(serde_json::json!(
+        &control.get_expiry_report(expiring_within)
+    ))
+}
+
 fn list_transactions(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
     let txids: Vec<bitcoin::Txid> = params
         .get(0, "txids")
@@ -143,31 +518,148 @@ fn create_recovery(control: &DaemonControl, params: Params) -> Result<serde_json
         .as_str()
         .and_then(|s| bitcoin::Address::from_str(s).ok())
         .ok_or_else(|| Error::invalid_params("Invalid 'address' parameter."))?;
-    let feerate: u64 = params
-        .get(1, "feerate")
-        .ok_or_else(|| Error::invalid_params("Missing 'feerate' parameter."))?
-        .as_u64()
-        .ok_or_else(|| Error::invalid_params("Invalid 'feerate' parameter."))?;
+    let feerate = feerate_param(control, &params, 1, 4)?;
+    let timelock = recovery_timelock_param(&params, 2, 3)?;
+
+    let res = control.create_recovery(address, feerate, timelock)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn create_recovery_spend(
+    control: &DaemonControl,
+    params: Params,
+) -> Result<serde_json::Value, Error> {
+    let destinations = destinations_param(&params, 0)?;
+    let outpoints = params
+        .get(1, "outpoints")
+        .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .and_then(|e| bitcoin::OutPoint::from_str(e).ok())
+                })
+                .collect::<Option<Vec<bitcoin::OutPoint>>>()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'outpoints' parameter."))?;
+    let feerate = feerate_param(control, &params, 2, 5)?;
+    let timelock = recovery_timelock_param(&params, 3, 4)?;
+
+    let res = control.create_recovery_spend(&destinations, &outpoints, feerate, timelock)?;
+    Ok(serde_json::json!(&res))
+}
+
+fn update_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let items = params
+        .get(0, "labels")
+        .ok_or_else(|| Error::invalid_params("Missing 'labels' parameter."))?
+        .as_object()
+        .and_then(|obj| {
+            obj.into_iter()
+                .map(|(k, v)| {
+                    let item = LabelItem::from_str(k).ok()?;
+                    let value = if v.is_null() {
+                        None
+                    } else {
+                        Some(v.as_str()?.to_string())
+                    };
+                    Some((item, value))
+                })
+                .collect::<Option<HashMap<LabelItem, Option<String>>>>()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'labels' parameter."))?;
+    control.update_labels(&items)?;
+
+    Ok(serde_json::json!({}))
+}
+
+fn get_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let items = params
+        .get(0, "items")
+        .ok_or_else(|| Error::invalid_params("Missing 'items' parameter."))?
+        .as_array()
+        .and_then(|arr| {
+            arr.iter()
+                .map(|entry| entry.as_str().and_then(|e| LabelItem::from_str(e).ok()))
+                .collect::<Option<HashSet<LabelItem>>>()
+        })
+        .ok_or_else(|| Error::invalid_params("Invalid 'items' parameter."))?;
+
+    Ok(serde_json::json!(&control.get_labels(&items)))
+}
+
+fn import_labels(control: &DaemonControl, params: Params) -> Result<serde_json::Value, Error> {
+    let labels = params
+        .get(0, "labels")
+        .ok_or_else(|| Error::invalid_params("Missing 'labels' parameter."))?
+        .as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid 'labels' parameter."))?;
+    let res = control.import_labels(labels)?;
 
-    let res = control.create_recovery(address, feerate)?;
     Ok(serde_json::json!(&res))
 }
 
 /// Handle an incoming JSONRPC2 request.
 pub fn handle_request(control: &DaemonControl, req: Request) -> Result<Response, Error> {
     let result = match req.method.as_str() {
+        "analyzepsbt" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
+            analyze_psbt(control, params)?
+        }
         "broadcastspend" => {
             let params = req
                 .params
                 .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
             broadcast_spend(control, params)?
         }
+        "bumpfee" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'txid' and 'feerate' parameters."))?;
+            bump_fee(control, params)?
+        }
+        "createcpfp" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'outpoints' and 'feerate' parameters.")
+            })?;
+            create_cpfp(control, params)?
+        }
+        "createmessagepsbt" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'address' and 'message' parameters.")
+            })?;
+            create_message_psbt(control, params)?
+        }
         "createrecovery" => {
             let params = req.params.ok_or_else(|| {
                 Error::invalid_params("Missing 'address' and 'feerate' parameters.")
             })?;
             create_recovery(control, params)?
         }
+        "createrecoveryspend" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params(
+                    "Missing 'destinations', 'outpoints' and 'feerate' parameters.",
+                )
+            })?;
+            create_recovery_spend(control, params)?
+        }
+        "createrefresh" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'outpoints' and 'feerate' parameters.")
+            })?;
+            create_refresh(control, params)?
+        }
+        "createreservesproof" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'message' parameter."))?;
+            create_reserves_proof(control, params)?
+        }
         "createspend" => {
             let params = req.params.ok_or_else(|| {
                 Error::invalid_params(
@@ -182,8 +674,54 @@ pub fn handle_request(control: &DaemonControl, req: Request) -> Result<Response,
                 .ok_or_else(|| Error::invalid_params("Missing 'txid' parameter."))?;
             delete_spend(control, params)?
         }
+        "estimatefees" => serde_json::json!(&control.estimate_fees()),
+        "exportlabels" => serde_json::json!(&control.export_labels()),
+        "finalizemessagepsbt" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'address', 'message' and 'psbt' parameters.")
+            })?;
+            finalize_message_psbt(control, params)?
+        }
+        "freezecoins" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?;
+            freeze_coins(control, params, true)?
+        }
+        "getaddressinfo" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'address' parameter."))?;
+            get_address_info(control, params)?
+        }
+        "getexpiryreport" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'expiring_within' parameter."))?;
+            get_expiry_report(control, params)?
+        }
         "getinfo" => serde_json::json!(&control.get_info()),
+        "getlabels" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'items' parameter."))?;
+            get_labels(control, params)?
+        }
         "getnewaddress" => serde_json::json!(&control.get_new_address()),
+        "importlabels" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'labels' parameter."))?;
+            import_labels(control, params)?
+        }
+        "listaddresses" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params(
+                    "The 'listaddresses' command requires 2 parameters: 'start_index' and 'count'",
+                )
+            })?;
+            list_addresses(control, params)?
+        }
         "listcoins" => serde_json::json!(&control.list_coins()),
         "listconfirmed" => {
             let params = req.params.ok_or_else(|| {
@@ -209,12 +747,36 @@ pub fn handle_request(control: &DaemonControl, req: Request) -> Result<Response,
             start_rescan(control, params)?
         }
         "stop" => serde_json::json!({}),
+        "unfreezecoins" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'outpoints' parameter."))?;
+            freeze_coins(control, params, false)?
+        }
+        "updatelabels" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'labels' parameter."))?;
+            update_labels(control, params)?
+        }
         "updatespend" => {
             let params = req
                 .params
                 .ok_or_else(|| Error::invalid_params("Missing 'psbt' parameter."))?;
             update_spend(control, params)?
         }
+        "verifymessage" => {
+            let params = req.params.ok_or_else(|| {
+                Error::invalid_params("Missing 'address', 'message' and 'signature' parameters.")
+            })?;
+            verify_message(control, params)?
+        }
+        "verifyreservesproof" => {
+            let params = req
+                .params
+                .ok_or_else(|| Error::invalid_params("Missing 'message' and 'psbt' parameters."))?;
+            verify_reserves_proof(control, params)?
+        }
         _ => {
             return Err(Error::method_not_found());
         }
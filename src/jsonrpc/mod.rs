@@ -163,12 +163,31 @@ impl From<commands::CommandError> for Error {
             | commands::CommandError::SpendFinalization(..)
             | commands::CommandError::InsaneRescanTimestamp(..)
             | commands::CommandError::AlreadyRescanning
-            | commands::CommandError::RecoveryNotAvailable => {
+            | commands::CommandError::RecoveryNotAvailable
+            | commands::CommandError::UnknownRecoveryTimelock(..)
+            | commands::CommandError::MalformedPsbt
+            | commands::CommandError::NotReplaceable(..)
+            | commands::CommandError::ReplacementFeerate(..)
+            | commands::CommandError::ReplacedSpend(..)
+            | commands::CommandError::AlreadyConfirmed(..)
+            | commands::CommandError::NotInMempool(..)
+            | commands::CommandError::InvalidConfTarget(..)
+            | commands::CommandError::MalformedLabels(..)
+            | commands::CommandError::FrozenCoin(..)
+            | commands::CommandError::NoExpiringCoin(..)
+            | commands::CommandError::RecoveryNotMatured(..)
+            | commands::CommandError::UnknownAddress(..)
+            | commands::CommandError::InvalidMessageSignature(..)
+            | commands::CommandError::UnconfirmedCoin(..)
+            | commands::CommandError::NoConfirmedCoin
+            | commands::CommandError::InvalidReservesProof(..)
+            | commands::CommandError::UnconfirmedAncestors(..) => {
                 Error::new(ErrorCode::InvalidParams, e.to_string())
             }
             commands::CommandError::FetchingTransaction(..)
             | commands::CommandError::SanityCheckFailure(_)
-            | commands::CommandError::RescanTrigger(..) => {
+            | commands::CommandError::RescanTrigger(..)
+            | commands::CommandError::FeeEstimateUnavailable(..) => {
                 Error::new(ErrorCode::InternalError, e.to_string())
             }
             commands::CommandError::TxBroadcast(_) => {
@@ -1,4 +1,6 @@
+pub mod bip322;
 mod bitcoin;
+pub mod bsms;
 pub mod commands;
 pub mod config;
 #[cfg(unix)]
@@ -7,6 +9,7 @@ mod database;
 pub mod descriptors;
 #[cfg(feature = "jsonrpc_server")]
 mod jsonrpc;
+pub mod reserves;
 #[cfg(test)]
 mod testutils;
 
@@ -0,0 +1,329 @@
+//! Proofs of reserves in the format of BIP127.
+//!
+//! A proof of reserves is a transaction spending the coins whose ownership is proven, along with a
+//! first "commitment" input spending a non-existent output derived from a message. The commitment
+//! input ties the signatures to the message, and makes the transaction invalid so they may never
+//! be used to move the coins. The single output pays the value of all the coins to an unspendable
+//! Script.
+
+use std::{collections::HashSet, fmt, iter};
+
+use miniscript::{
+    bitcoin::{
+        self, blockdata::opcodes, blockdata::script, hashes::sha256d, hashes::Hash, secp256k1,
+        util::sighash,
+    },
+    interpreter::{Interpreter, KeySigPair, SatisfiedConstraint},
+};
+
+// The prefix of the message committed to by the commitment input.
+const MESSAGE_PREFIX: &[u8] = b"Proof-of-Reserves: ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReservesProofError {
+    /// The first input doesn't spend the commitment to this message.
+    MissingCommitment,
+    /// The transaction doesn't spend distinct coins into a single unspendable output.
+    MalformedProof,
+    /// The input at this index isn't validly signed.
+    InvalidSignature(usize, String),
+    /// A signature of the input at this index doesn't commit to the whole transaction.
+    InvalidSighashType(usize),
+}
+
+impl fmt::Display for ReservesProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingCommitment => {
+                write!(f, "The first input doesn't commit to this message.")
+            }
+            Self::MalformedProof => write!(
+                f,
+                "The transaction doesn't spend coins into a single unspendable output of the same value."
+            ),
+            Self::InvalidSignature(index, e) => {
+                write!(f, "Invalid signature for input {}: '{}'.", index, e)
+            }
+            Self::InvalidSighashType(index) => write!(
+                f,
+                "Signature for input {} doesn't use SIGHASH_ALL (or SIGHASH_DEFAULT for Taproot).",
+                index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReservesProofError {}
+
+/// The outpoint spent by the commitment input of a proof for this message.
+pub fn commitment_outpoint(message: &[u8]) -> bitcoin::OutPoint {
+    let commitment = sha256d::Hash::hash(&[MESSAGE_PREFIX, message].concat());
+    bitcoin::OutPoint::new(bitcoin::Txid::from_hash(commitment), 0)
+}
+
+/// The output spent by the commitment input. It doesn't exist, but signers may need it to compute
+/// the signature hash of the other inputs.
+pub fn commitment_txout() -> bitcoin::TxOut {
+    bitcoin::TxOut {
+        value: 0,
+        script_pubkey: script::Builder::new()
+            .push_opcode(opcodes::OP_TRUE)
+            .into_script(),
+    }
+}
+
+/// The unsigned proof of reserves for this message, spending these coins.
+pub fn proof_tx(
+    message: &[u8],
+    coins: &[(bitcoin::OutPoint, bitcoin::Amount)],
+) -> bitcoin::Transaction {
+    let txins = iter::once(commitment_outpoint(message))
+        .chain(coins.iter().map(|(op, _)| *op))
+        .map(|previous_output| bitcoin::TxIn {
+            previous_output,
+            sequence: bitcoin::Sequence::MAX,
+            ..bitcoin::TxIn::default()
+        })
+        .collect();
+    let value = coins.iter().map(|(_, amount)| amount.to_sat()).sum();
+    bitcoin::Transaction {
+        version: 1,
+        lock_time: bitcoin::PackedLockTime::ZERO,
+        input: txins,
+        output: vec![bitcoin::TxOut {
+            value,
+            script_pubkey: script::Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    }
+}
+
+/// Check a signed proof of reserves for this message. The `prevouts` are the outputs spent by all
+/// inputs but the commitment one, in order. It's up to the caller to make sure they are unspent.
+pub fn verify(
+    secp: &secp256k1::Secp256k1<impl secp256k1::Verification>,
+    tx: &bitcoin::Transaction,
+    message: &[u8],
+    prevouts: &[bitcoin::TxOut],
+) -> Result<(), ReservesProofError> {
+    if tx.input.first().map(|txin| txin.previous_output) != Some(commitment_outpoint(message)) {
+        return Err(ReservesProofError::MissingCommitment);
+    }
+
+    // The same coin must not be counted twice.
+    let coins: HashSet<_> = tx.input.iter().map(|txin| txin.previous_output).collect();
+    let value: u64 = prevouts.iter().map(|txo| txo.value).sum();
+    if tx.input.len() < 2
+        || tx.input.len() != prevouts.len() + 1
+        || coins.len() != tx.input.len()
+        || tx.output.len() != 1
+        || tx.output[0].value != value
+        || !tx.output[0].script_pubkey.is_provably_unspendable()
+    {
+        return Err(ReservesProofError::MalformedProof);
+    }
+
+    // Going through all the constraints checks the signatures of each input against the
+    // transaction. The signatures must also commit to all its inputs and outputs, otherwise they
+    // could be reused in a proof for another message or set of coins.
+    let all_prevouts: Vec<_> = iter::once(commitment_txout())
+        .chain(prevouts.iter().cloned())
+        .collect();
+    let sighash_prevouts = sighash::Prevouts::All(&all_prevouts);
+    for (index, (txin, prevout)) in tx.input.iter().zip(all_prevouts.iter()).enumerate().skip(1) {
+        let interpreter = Interpreter::from_txdata(
+            &prevout.script_pubkey,
+            &txin.script_sig,
+            &txin.witness,
+            txin.sequence,
+            tx.lock_time.into(),
+        )
+        .map_err(|e| ReservesProofError::InvalidSignature(index, e.to_string()))?;
+        for constraint in interpreter.iter(secp, tx, index, &sighash_prevouts) {
+            let constraint = constraint
+                .map_err(|e| ReservesProofError::InvalidSignature(index, e.to_string()))?;
+            if !commits_to_all(&constraint) {
+                return Err(ReservesProofError::InvalidSighashType(index));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Whether this constraint, if it is a signature, commits to all the inputs and outputs of the
+// transaction.
+fn commits_to_all(constraint: &SatisfiedConstraint) -> bool {
+    match constraint {
+        SatisfiedConstraint::PublicKey { key_sig }
+        | SatisfiedConstraint::PublicKeyHash { key_sig, .. } => match key_sig {
+            KeySigPair::Ecdsa(_, sig) => sig.hash_ty == bitcoin::EcdsaSighashType::All,
+            KeySigPair::Schnorr(_, sig) => matches!(
+                sig.hash_ty,
+                bitcoin::SchnorrSighashType::Default | bitcoin::SchnorrSighashType::All
+            ),
+        },
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // Sign the input at this index of the proof, spending a P2WPKH output.
+    fn sign_p2wpkh(
+        secp: &secp256k1::Secp256k1<secp256k1::All>,
+        tx: &mut bitcoin::Transaction,
+        index: usize,
+        value: u64,
+        privkey: &bitcoin::PrivateKey,
+        hash_ty: bitcoin::EcdsaSighashType,
+    ) {
+        let pubkey = privkey.public_key(secp);
+        let script_code = bitcoin::Script::new_p2pkh(&pubkey.pubkey_hash());
+        let sighash = sighash::SighashCache::new(&*tx)
+            .segwit_signature_hash(index, &script_code, value, hash_ty)
+            .unwrap();
+        let msg = secp256k1::Message::from_slice(&sighash[..]).unwrap();
+        let sig = bitcoin::EcdsaSig {
+            sig: secp.sign_ecdsa(&msg, &privkey.inner),
+            hash_ty,
+        };
+        tx.input[index].witness = bitcoin::Witness::from_vec(vec![sig.to_vec(), pubkey.to_bytes()]);
+    }
+
+    #[test]
+    fn reserves_proof() {
+        let secp = secp256k1::Secp256k1::new();
+        let privkey = bitcoin::PrivateKey::new(
+            secp256k1::SecretKey::from_slice(&[0x01; 32]).unwrap(),
+            bitcoin::Network::Bitcoin,
+        );
+        let pubkey = privkey.public_key(&secp);
+        let prevout = bitcoin::TxOut {
+            value: 100_000,
+            script_pubkey: bitcoin::Script::new_v0_p2wpkh(&pubkey.wpubkey_hash().unwrap()),
+        };
+        let coin = bitcoin::OutPoint::from_str(
+            "3753a1d74c0af8dd0a0f3b763c14faf3bd9ed03cbdf33337a074fb0e9f6c7810:1",
+        )
+        .unwrap();
+
+        // The unsigned proof commits to the message and has a single unspendable output.
+        let mut tx = proof_tx(
+            b"Reserves as of today",
+            &[(coin, bitcoin::Amount::from_sat(100_000))],
+        );
+        assert_eq!(tx.input.len(), 2);
+        assert_eq!(
+            tx.input[0].previous_output,
+            commitment_outpoint(b"Reserves as of today")
+        );
+        assert_ne!(
+            commitment_outpoint(b"Reserves as of today"),
+            commitment_outpoint(b"Reserves as of yesterday")
+        );
+        assert_eq!(tx.output[0].value, 100_000);
+        assert!(tx.output[0].script_pubkey.is_provably_unspendable());
+        assert!(matches!(
+            verify(&secp, &tx, b"Reserves as of today", &[prevout.clone()]),
+            Err(ReservesProofError::InvalidSignature(1, _))
+        ));
+
+        // Once signed, it's valid only for this message and coin.
+        sign_p2wpkh(
+            &secp,
+            &mut tx,
+            1,
+            100_000,
+            &privkey,
+            bitcoin::EcdsaSighashType::All,
+        );
+        verify(&secp, &tx, b"Reserves as of today", &[prevout.clone()]).unwrap();
+        assert_eq!(
+            verify(&secp, &tx, b"Reserves as of yesterday", &[prevout.clone()]),
+            Err(ReservesProofError::MissingCommitment)
+        );
+        let other_prevout = bitcoin::TxOut {
+            value: 100_000,
+            script_pubkey: bitcoin::Script::new_v0_p2wpkh(&bitcoin::WPubkeyHash::all_zeros()),
+        };
+        assert!(matches!(
+            verify(&secp, &tx, b"Reserves as of today", &[other_prevout]),
+            Err(ReservesProofError::InvalidSignature(1, _))
+        ));
+
+        // The signature must commit to the whole proof. Otherwise it could be reused with other
+        // inputs, for instance to commit to another message.
+        let mut acp_tx = tx.clone();
+        sign_p2wpkh(
+            &secp,
+            &mut acp_tx,
+            1,
+            100_000,
+            &privkey,
+            bitcoin::EcdsaSighashType::AllPlusAnyoneCanPay,
+        );
+        assert_eq!(
+            verify(&secp, &acp_tx, b"Reserves as of today", &[prevout.clone()]),
+            Err(ReservesProofError::InvalidSighashType(1))
+        );
+        sign_p2wpkh(
+            &secp,
+            &mut acp_tx,
+            1,
+            100_000,
+            &privkey,
+            bitcoin::EcdsaSighashType::None,
+        );
+        assert_eq!(
+            verify(&secp, &acp_tx, b"Reserves as of today", &[prevout.clone()]),
+            Err(ReservesProofError::InvalidSighashType(1))
+        );
+
+        // The output must be worth exactly the value of the coins.
+        let lower_prevout = bitcoin::TxOut {
+            value: 90_000,
+            ..prevout.clone()
+        };
+        assert_eq!(
+            verify(&secp, &tx, b"Reserves as of today", &[lower_prevout]),
+            Err(ReservesProofError::MalformedProof)
+        );
+
+        // The same coin can't be spent twice to inflate the reserves.
+        let coins = [
+            (coin, bitcoin::Amount::from_sat(100_000)),
+            (coin, bitcoin::Amount::from_sat(100_000)),
+        ];
+        let mut tx = proof_tx(b"Reserves as of today", &coins);
+        sign_p2wpkh(
+            &secp,
+            &mut tx,
+            1,
+            100_000,
+            &privkey,
+            bitcoin::EcdsaSighashType::All,
+        );
+        sign_p2wpkh(
+            &secp,
+            &mut tx,
+            2,
+            100_000,
+            &privkey,
+            bitcoin::EcdsaSighashType::All,
+        );
+        assert_eq!(
+            verify(
+                &secp,
+                &tx,
+                b"Reserves as of today",
+                &[prevout.clone(), prevout]
+            ),
+            Err(ReservesProofError::MalformedProof)
+        );
+    }
+}
@@ -1,11 +1,17 @@
 use crate::{
-    bitcoin::{BitcoinInterface, Block, BlockChainTip, UTxO},
+    bitcoin::{d::MempoolEntry, BitcoinInterface, Block, BlockChainTip, UTxO},
     config::{BitcoinConfig, Config},
-    database::{Coin, CoinType, DatabaseConnection, DatabaseInterface, SpendBlock},
+    database::{Coin, CoinType, DatabaseConnection, DatabaseInterface, LabelItem, SpendBlock},
     descriptors, DaemonHandle,
 };
 
-use std::{collections::HashMap, env, fs, io, path, process, str::FromStr, sync, thread, time};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs, io, path, process,
+    str::FromStr,
+    sync, thread, time,
+};
 
 use miniscript::{
     bitcoin::{
@@ -18,6 +24,7 @@ use miniscript::{
 
 pub struct DummyBitcoind {
     pub txs: HashMap<Txid, (Transaction, Option<Block>)>,
+    pub mempool: HashMap<Txid, MempoolEntry>,
 }
 
 impl DummyBitcoind {}
@@ -26,6 +33,7 @@ impl DummyBitcoind {
     pub fn new() -> Self {
         Self {
             txs: HashMap::new(),
+            mempool: HashMap::new(),
         }
     }
 }
@@ -104,12 +112,44 @@ impl BitcoinInterface for DummyBitcoind {
         todo!()
     }
 
+    fn tip_median_time_past(&self) -> u32 {
+        todo!()
+    }
+
+    fn block_time(&self, height: i32) -> Option<u32> {
+        // A block every 10 minutes up to the tip.
+        if (0..=self.chain_tip().height).contains(&height) {
+            Some(1_600_000_000 + height as u32 * 600)
+        } else {
+            None
+        }
+    }
+
     fn wallet_transaction(
         &self,
         txid: &bitcoin::Txid,
     ) -> Option<(bitcoin::Transaction, Option<Block>)> {
         self.txs.get(txid).cloned()
     }
+
+    fn mempool_entry(&self, txid: &bitcoin::Txid) -> Option<MempoolEntry> {
+        self.mempool.get(txid).cloned()
+    }
+
+    fn estimate_feerate(&self, conf_target: u16) -> Option<u64> {
+        // The further the target, the lower the feerate: 72 sats/vb for 2 blocks, 1 for a day.
+        if conf_target == 0 {
+            return None;
+        }
+        Some(cmp::max(144 / conf_target as u64, 1))
+    }
+
+    fn utxo(&self, outpoint: &bitcoin::OutPoint) -> Option<bitcoin::TxOut> {
+        self.txs
+            .get(&outpoint.txid)
+            .and_then(|(tx, _)| tx.output.get(outpoint.vout as usize))
+            .cloned()
+    }
 }
 
 struct DummyDbState {
@@ -118,6 +158,8 @@ struct DummyDbState {
     curr_tip: Option<BlockChainTip>,
     coins: HashMap<bitcoin::OutPoint, Coin>,
     spend_txs: HashMap<bitcoin::Txid, Psbt>,
+    spend_replacements: HashMap<bitcoin::Txid, bitcoin::Txid>,
+    labels: Vec<(LabelItem, String)>,
 }
 
 pub struct DummyDatabase {
@@ -141,6 +183,8 @@ impl DummyDatabase {
                 curr_tip: None,
                 coins: HashMap::new(),
                 spend_txs: HashMap::new(),
+                spend_replacements: HashMap::new(),
+                labels: Vec::new(),
             })),
         }
     }
@@ -255,6 +299,15 @@ impl DatabaseConnection for DummyDatabase {
         }
     }
 
+    fn freeze_coins(&mut self, outpoints: &[bitcoin::OutPoint], frozen: bool) {
+        let mut db = self.db.write().unwrap();
+        for op in outpoints {
+            if let Some(coin) = db.coins.get_mut(op) {
+                coin.is_frozen = frozen;
+            }
+        }
+    }
+
     fn derivation_index_by_address(
         &mut self,
         _: &bitcoin::Address,
@@ -286,6 +339,22 @@ impl DatabaseConnection for DummyDatabase {
             .insert(txid, psbt.clone());
     }
 
+    fn replace_spend(&mut self, replaced_txid: &bitcoin::Txid, psbt: &Psbt) {
+        let txid = psbt.unsigned_tx.txid();
+        let mut db = self.db.write().unwrap();
+        db.spend_txs.insert(txid, psbt.clone());
+        db.spend_replacements.insert(*replaced_txid, txid);
+    }
+
+    fn spend_replacement(&mut self, txid: &bitcoin::Txid) -> Option<bitcoin::Txid> {
+        self.db
+            .read()
+            .unwrap()
+            .spend_replacements
+            .get(txid)
+            .cloned()
+    }
+
     fn spend_tx(&mut self, txid: &bitcoin::Txid) -> Option<Psbt> {
         self.db.read().unwrap().spend_txs.get(txid).cloned()
     }
@@ -348,6 +417,36 @@ impl DatabaseConnection for DummyDatabase {
         txids_and_time.truncate(limit as usize);
         txids_and_time.into_iter().map(|(txid, _)| txid).collect()
     }
+
+    fn update_labels(&mut self, items: &HashMap<LabelItem, Option<String>>) {
+        let labels = &mut self.db.write().unwrap().labels;
+        for (item, value) in items {
+            if let Some(value) = value {
+                if let Some(entry) = labels.iter_mut().find(|(i, _)| i == item) {
+                    entry.1 = value.clone();
+                } else {
+                    labels.push((item.clone(), value.clone()));
+                }
+            } else {
+                labels.retain(|(i, _)| i != item);
+            }
+        }
+    }
+
+    fn labels(&mut self, items: &HashSet<LabelItem>) -> HashMap<LabelItem, String> {
+        self.db
+            .read()
+            .unwrap()
+            .labels
+            .iter()
+            .filter(|(item, _)| items.contains(item))
+            .cloned()
+            .collect()
+    }
+
+    fn list_labels(&mut self) -> Vec<(LabelItem, String)> {
+        self.db.read().unwrap().labels.clone()
+    }
 }
 
 pub struct DummyLiana {
@@ -373,11 +472,48 @@ pub fn tmp_dir() -> path::PathBuf {
     ))
 }
 
+/// The primary key of the descriptor used by default in a [DummyLiana].
+pub fn dummy_owner_key() -> descriptors::LianaDescKeys {
+    descriptors::LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST/<0;1>/*").unwrap())
+}
+
+/// The recovery key of the descriptor used by default in a [DummyLiana].
+pub fn dummy_heir_key() -> descriptors::LianaDescKeys {
+    descriptors::LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap())
+}
+
+/// The recovery paths of a descriptor with a single recovery path.
+pub fn single_recovery_path(
+    timelock: descriptors::RecoveryTimelock,
+    keys: descriptors::LianaDescKeys,
+) -> BTreeMap<descriptors::RecoveryTimelock, descriptors::LianaDescKeys> {
+    let mut recovery_paths = BTreeMap::new();
+    recovery_paths.insert(timelock, keys);
+    recovery_paths
+}
+
 impl DummyLiana {
     /// Creates a new DummyLiana interface
     pub fn new(
         bitcoin_interface: impl BitcoinInterface + 'static,
         database: impl DatabaseInterface + 'static,
+    ) -> DummyLiana {
+        let desc = descriptors::MultipathDescriptor::new(
+            dummy_owner_key(),
+            single_recovery_path(
+                descriptors::RecoveryTimelock::Relative(10_000),
+                dummy_heir_key(),
+            ),
+        )
+        .unwrap();
+        DummyLiana::with_descriptor(bitcoin_interface, database, desc)
+    }
+
+    /// Creates a new DummyLiana interface for this main descriptor
+    pub fn with_descriptor(
+        bitcoin_interface: impl BitcoinInterface + 'static,
+        database: impl DatabaseInterface + 'static,
+        desc: descriptors::MultipathDescriptor,
     ) -> DummyLiana {
         let tmp_dir = tmp_dir();
         fs::create_dir_all(&tmp_dir).unwrap();
@@ -390,9 +526,6 @@ impl DummyLiana {
             poll_interval_secs: time::Duration::from_secs(2),
         };
 
-        let owner_key = descriptors::LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8KLW4HGLXZBJknja7kDUJuFHnM424LbziEXsfkh1WQCiEjjHw4zLqSUm4rvhgyGkkuRowE9tCJSgt3TQB5J3SKAbZ2SdcKST/<0;1>/*").unwrap());
-        let heir_key = descriptors::LianaDescKeys::from_single(descriptor::DescriptorPublicKey::from_str("[aabbccdd]xpub68JJTXc1MWK8PEQozKsRatrUHXKFNkD1Cb1BuQU9Xr5moCv87anqGyXLyUd4KpnDyZgo3gz4aN1r3NiaoweFW8UutBsBbgKHzaD5HkTkifK/<0;1>/*").unwrap());
-        let desc = descriptors::MultipathDescriptor::new(owner_key, heir_key, 10_000).unwrap();
         let config = Config {
             bitcoin_config,
             bitcoind_config: None,
@@ -25,6 +25,10 @@ def sign_psbt(psbt, hds):
 
     # Sign each input.
     for i, psbt_in in enumerate(psbt.i):
+        # Some inputs aren't ours to sign, such as the commitment input of a proof of reserves.
+        if PSBT_IN_BIP32_DERIVATION not in psbt_in.map:
+            continue
+
         # First, gather the needed information from the PSBT input.
         # 'hd_keypaths' is of the form {pubkey: (fingerprint (4 bytes), derivation path (n * 4 bytes))}
         fing_der = next(iter(psbt_in.map[PSBT_IN_BIP32_DERIVATION].values()))
@@ -26,6 +26,11 @@ def test_getinfo(lianad):
     res = lianad.rpc.getinfo()
     assert res["sync"] == 1.0
     assert "main" in res["descriptors"]
+    assert res["policy"]["primary_path"]["path"] == "primary"
+    assert res["policy"]["primary_path"]["threshold"] == 1
+    recovery_path = res["policy"]["recovery_paths"][0]
+    assert recovery_path["path"] == {"recovery": {"relative": 10}}
+    assert recovery_path["approx_duration_secs"] == 10 * 600
     assert res["rescan_progress"] is None
 
 
@@ -36,6 +41,39 @@ def test_getaddress(lianad):
     assert res["address"] != lianad.rpc.getnewaddress()["address"]
 
 
+def test_addresses(lianad, bitcoind):
+    # Receive a coin on a new address. It's listed along with its usage.
+    addr = lianad.rpc.getnewaddress()["address"]
+    bitcoind.rpc.sendtoaddress(addr, 1)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    entries = lianad.rpc.listaddresses(0, 1_000)["addresses"]
+    entry = next(e for e in entries if e["address"] == addr)
+    assert not entry["is_change"]
+    assert entry["coins"] == 1
+    assert entry["received"] == COIN
+    assert all(e["coins"] == 0 for e in entries if e["address"] != addr)
+
+    # It can be paged through.
+    entries = lianad.rpc.listaddresses(entry["index"], 1)["addresses"]
+    assert all(e["index"] == entry["index"] for e in entries)
+    assert addr in [e["address"] for e in entries]
+
+    # The address is ours, along with the origin of its keys.
+    info = lianad.rpc.getaddressinfo(addr)
+    assert info["is_mine"]
+    assert info["index"] == entry["index"]
+    assert not info["is_change"]
+    assert len(info["derivations"]) > 0
+    for deriv in info["derivations"]:
+        assert deriv["derivation_path"].endswith(f"0/{entry['index']}")
+
+    # An address from another wallet isn't ours.
+    info = lianad.rpc.getaddressinfo(bitcoind.rpc.getnewaddress())
+    assert not info["is_mine"]
+    assert info["index"] is None
+    assert info["derivations"] == []
+
+
 def test_listcoins(lianad, bitcoind):
     # Initially empty
     res = lianad.rpc.listcoins()
@@ -92,6 +130,30 @@ def test_jsonrpc_server(lianad, bitcoind):
     assert "psbt" in res
 
 
+def test_estimatefees(lianad, bitcoind):
+    res = lianad.rpc.estimatefees()
+    targets = [e["conf_target"] for e in res["estimates"]]
+    assert targets == [2, 3, 6, 12, 24, 144, 1008]
+    # On a fresh regtest chain bitcoind doesn't have the data to estimate fees.
+    assert all(e["feerate"] is None for e in res["estimates"])
+
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+
+    # A confirmation target may be given instead of a feerate, but not both.
+    with pytest.raises(RpcError, match="No fee estimate available"):
+        lianad.rpc.createspend(destinations, [], None, None, 6)
+    with pytest.raises(RpcError, match="Invalid confirmation target"):
+        lianad.rpc.createspend(destinations, [], None, None, 0)
+    with pytest.raises(RpcError, match="Only one of 'feerate' and 'conf_target'"):
+        lianad.rpc.createspend(destinations, [], 2, None, 6)
+    with pytest.raises(RpcError, match="Missing 'feerate' parameter"):
+        lianad.rpc.createspend(destinations, [], None)
+
+
 def test_create_spend(lianad, bitcoind):
     # Receive a number of coins in different blocks on different addresses, and
     # one more on the same address.
@@ -133,6 +195,102 @@ def test_create_spend(lianad, bitcoind):
     sign_and_broadcast(lianad, bitcoind, PSBT.from_base64(res["psbt"]))
 
 
+def test_create_spend_coin_selection(lianad, bitcoind):
+    # Receive two coins of different values.
+    for amount in (0.01, 0.05):
+        addr = lianad.rpc.getnewaddress()["address"]
+        txid = bitcoind.rpc.sendtoaddress(addr, amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+    coins = {c["amount"]: c["outpoint"] for c in lianad.rpc.listcoins()["coins"]}
+
+    # If we don't provide any coin, the daemon will select them for us.
+    destinations = {bitcoind.rpc.getnewaddress(): 2_000_000}
+    res = lianad.rpc.createspend(destinations, [], 2)
+    assert res["coins"] == [coins[5_000_000]]
+    spend_psbt = PSBT.from_base64(res["psbt"])
+    assert len(spend_psbt.tx.vin) == 1
+    assert len(spend_psbt.tx.vout) == 2
+
+    # Once stored, a Spend transaction's coins won't be selected anymore.
+    lianad.rpc.updatespend(res["psbt"])
+    with pytest.raises(RpcError, match="Cannot create a 2 sat/vb transaction"):
+        lianad.rpc.createspend(destinations, [], 2)
+    destinations = {bitcoind.rpc.getnewaddress(): 500_000}
+    res = lianad.rpc.createspend(destinations, [], 2)
+    assert res["coins"] == [coins[1_000_000]]
+    sign_and_broadcast(lianad, bitcoind, PSBT.from_base64(res["psbt"]))
+
+
+def test_create_spend_remainder(lianad, bitcoind):
+    # Receive two coins.
+    for amount in (0.01, 0.02):
+        addr = lianad.rpc.getnewaddress()["address"]
+        txid = bitcoind.rpc.sendtoaddress(addr, amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+
+    # Sweep both coins to an external address, while paying another one.
+    destinations = {bitcoind.rpc.getnewaddress(): 500_000}
+    remainder_addr = bitcoind.rpc.getnewaddress()
+    res = lianad.rpc.createspend(destinations, [], 3, remainder_addr)
+    assert len(res["coins"]) == 2
+    spend_psbt = PSBT.from_base64(res["psbt"])
+    assert len(spend_psbt.tx.vin) == 2
+    assert len(spend_psbt.tx.vout) == 2
+    remainder_value = spend_psbt.tx.vout[-1].nValue
+    assert 2_500_000 - 1_000 < remainder_value < 2_500_000
+
+    # The fee paid is exactly what's needed at the requested feerate.
+    txid = sign_and_broadcast(lianad, bitcoind, spend_psbt)
+    entry = bitcoind.rpc.getmempoolentry(txid)
+    fee = 3_000_000 - 500_000 - remainder_value
+    assert round(entry["fees"]["base"] * COIN) == fee
+    assert fee / entry["vsize"] >= 3
+
+    # Without destination nor remainder address, we can't create a transaction.
+    with pytest.raises(RpcError, match="No provided destination"):
+        lianad.rpc.createspend({}, [], 3)
+
+
+def test_freeze_coins(lianad, bitcoind):
+    # Receive two coins.
+    for amount in (0.01, 0.02):
+        addr = lianad.rpc.getnewaddress()["address"]
+        txid = bitcoind.rpc.sendtoaddress(addr, amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+    coins = lianad.rpc.listcoins()["coins"]
+    assert all(not c["is_frozen"] for c in coins)
+    large_coin = next(c["outpoint"] for c in coins if c["amount"] == 2_000_000)
+
+    # Freeze the largest coin. It's reported as such and not selected anymore.
+    lianad.rpc.freezecoins([large_coin])
+    frozen = [c["outpoint"] for c in lianad.rpc.listcoins()["coins"] if c["is_frozen"]]
+    assert frozen == [large_coin]
+    destinations = {bitcoind.rpc.getnewaddress(): 500_000}
+    res = lianad.rpc.createspend(destinations, [], 2)
+    assert large_coin not in res["coins"]
+    with pytest.raises(RpcError, match="Cannot create a 2 sat/vb transaction"):
+        lianad.rpc.createspend({bitcoind.rpc.getnewaddress(): 1_500_000}, [], 2)
+
+    # It can only be spent explicitly if asked to.
+    with pytest.raises(RpcError, match=f"Coin at '{large_coin}' is frozen."):
+        lianad.rpc.createspend(destinations, [large_coin], 2)
+    res = lianad.rpc.createspend(destinations, [large_coin], 2, None, None, True)
+    assert res["coins"] == [large_coin]
+
+    # Once unfrozen it's available again.
+    lianad.rpc.unfreezecoins([large_coin])
+    assert all(not c["is_frozen"] for c in lianad.rpc.listcoins()["coins"])
+    lianad.rpc.createspend(destinations, [large_coin], 2)
+
+    # Unknown coins can't be frozen.
+    unknown = f"{'00' * 32}:0"
+    with pytest.raises(RpcError, match="Unknown outpoint"):
+        lianad.rpc.freezecoins([unknown])
+
+
 def test_list_spend(lianad, bitcoind):
     # Start by creating two conflicting Spend PSBTs. The first one will have a change
     # output but not the second one.
@@ -241,6 +399,48 @@ def test_update_spend(lianad, bitcoind):
     assert psbt_merged.i[0].map[PSBT_IN_PARTIAL_SIG][dummy_pk_b] == dummy_sig_b
 
 
+def test_analyze_psbt(lianad, bitcoind):
+    # Create a Spend PSBT with a change output.
+    addr = lianad.rpc.getnewaddress()["address"]
+    bitcoind.rpc.sendtoaddress(addr, 0.2567)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    dest_addr = bitcoind.rpc.getnewaddress()
+    res = lianad.rpc.createspend({dest_addr: 200_000}, [coin["outpoint"]], 6)
+    psbt = PSBT.from_base64(res["psbt"])
+
+    # The input is ours and can only be spent through the primary path, for which
+    # no signature is present yet. The change output is detected.
+    analysis = lianad.rpc.analyzepsbt(res["psbt"])
+    assert len(analysis["inputs"]) == 1
+    analyzed_in = analysis["inputs"][0]
+    assert analyzed_in["outpoint"] == coin["outpoint"]
+    assert analyzed_in["is_mine"]
+    assert analyzed_in["amount"] == coin["amount"]
+    assert len(analyzed_in["paths"]) == 1
+    assert analyzed_in["paths"][0]["path"] == "primary"
+    assert analyzed_in["paths"][0]["signed"] == []
+    assert len(analyzed_in["paths"][0]["missing"]) == 1
+    assert len(analysis["outputs"]) == 2
+    dest_out = next(o for o in analysis["outputs"] if o["address"] == dest_addr)
+    assert not dest_out["is_mine"]
+    change_out = next(o for o in analysis["outputs"] if o["address"] != dest_addr)
+    assert change_out["is_mine"] and change_out["is_change"]
+    assert analysis["fee"] == coin["amount"] - sum(o.nValue for o in psbt.tx.vout)
+    assert analysis["feerate_vb"] >= 6
+    assert analysis["inconsistency"] is None
+
+    # Once signed, the signature for the primary path is reported.
+    signed_psbt = lianad.signer.sign_psbt(psbt)
+    analysis = lianad.rpc.analyzepsbt(signed_psbt.to_base64())
+    primary_path = analysis["inputs"][0]["paths"][0]
+    assert len(primary_path["signed"]) == 1
+    assert primary_path["missing"] == []
+
+    # The PSBT isn't stored.
+    assert len(lianad.rpc.listspendtxs()["spend_txs"]) == 0
+
+
 def test_broadcast_spend(lianad, bitcoind):
     # Create a new coin and a spending tx for it.
     addr = lianad.rpc.getnewaddress()["address"]
@@ -558,6 +758,13 @@ def test_create_recovery(lianad, bitcoind):
     ):
         lianad.rpc.createrecovery(bitcoind.rpc.getnewaddress(), 2)
 
+    # We can't use a recovery path that doesn't exist
+    with pytest.raises(
+        RpcError,
+        match=r"There is no recovery path with timelock 'older\(11\)'",
+    ):
+        lianad.rpc.createrecovery(bitcoind.rpc.getnewaddress(), 2, 11)
+
     # Receive another coin, it will be one block after the others
     txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], 0.4)
 
@@ -583,3 +790,249 @@ def test_create_recovery(lianad, bitcoind):
     assert len(reco_psbt.tx.vout) == 1
     assert int(0.39999 * COIN) < int(reco_psbt.tx.vout[0].nValue) < int(0.4 * COIN)
     sign_and_broadcast(lianad, bitcoind, reco_psbt, recovery=True)
+
+
+def test_create_recovery_spend(lianad, bitcoind):
+    """Test spending some coins through the timelocked path to several destinations."""
+    # Receive two coins, one block apart.
+    for amount in (0.1, 0.2):
+        txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+    coins = sorted(lianad.rpc.listcoins()["coins"], key=lambda c: c["amount"])
+    outpoints = [c["outpoint"] for c in coins]
+
+    # Make the timelock of the first coin mature (we use a csv of 10 in the fixture).
+    bitcoind.generate_block(8)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    destinations = {
+        bitcoind.rpc.getnewaddress(): 2_000_000,
+        bitcoind.rpc.getnewaddress(): 3_000_000,
+    }
+    with pytest.raises(
+        RpcError, match=f"The recovery path of the coin at '{outpoints[1]}' isn't available"
+    ):
+        lianad.rpc.createrecoveryspend(destinations, outpoints, 2)
+    with pytest.raises(RpcError, match="There is no recovery path with timelock"):
+        lianad.rpc.createrecoveryspend(destinations, outpoints[:1], 2, 11)
+
+    # Spend the first coin to both destinations, with a change output.
+    res = lianad.rpc.createrecoveryspend(destinations, outpoints[:1], 2)
+    assert res["coins"] == outpoints[:1]
+    psbt = PSBT.from_base64(res["psbt"])
+    assert len(psbt.tx.vin) == 1
+    assert psbt.tx.vin[0].nSequence == 10
+    assert len(psbt.tx.vout) == 3
+    txid = sign_and_broadcast(lianad, bitcoind, psbt, recovery=True)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(
+        lambda: any(
+            c["outpoint"].startswith(txid) and c["block_height"] is not None
+            for c in lianad.rpc.listcoins()["coins"]
+        )
+    )
+
+
+def test_create_refresh(lianad, bitcoind):
+    """Test the refresh of coins whose timelock is about to expire."""
+    # Receive two coins, 6 blocks apart.
+    for amount in (0.1, 0.2):
+        txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+        if amount == 0.1:
+            bitcoind.generate_block(5)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+
+    # The timelock of the first coin expires in 4 blocks (we use a csv of 10 in the fixture),
+    # in 10 blocks for the second one.
+    with pytest.raises(
+        RpcError, match="No coin's recovery path becomes available within 3 blocks."
+    ):
+        lianad.rpc.createrefresh([], 2, 3)
+    res = lianad.rpc.createrefresh([], 2, 4)
+    assert len(res["coins"]) == 1
+    first_coin = next(
+        c for c in lianad.rpc.listcoins()["coins"] if c["amount"] == 0.1 * COIN
+    )
+    assert res["coins"] == [first_coin["outpoint"]]
+
+    # The transaction was stored and pays to one of our change addresses.
+    refresh_psbt = PSBT.from_base64(res["psbt"])
+    spend_txs = lianad.rpc.listspendtxs()["spend_txs"]
+    assert [s["psbt"] for s in spend_txs] == [res["psbt"]]
+    assert len(refresh_psbt.tx.vout) == 1
+    txid = sign_and_broadcast(lianad, bitcoind, refresh_psbt)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(
+        lambda: any(
+            c["outpoint"] == f"{txid}:0" and c["block_height"] is not None
+            for c in lianad.rpc.listcoins()["coins"]
+        )
+    )
+
+    # The refreshed coin isn't about to expire anymore.
+    res = lianad.rpc.createrefresh([], 2, 9)
+    assert len(res["coins"]) == 1
+    assert res["coins"] != [f"{txid}:0"]
+
+
+def test_expiry_report(lianad, bitcoind):
+    """Test the report of when the recovery path becomes available for our coins."""
+    # Receive two coins, 6 blocks apart.
+    for amount in (0.1, 0.2):
+        txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+        if amount == 0.1:
+            bitcoind.generate_block(5)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+    coins = sorted(lianad.rpc.listcoins()["coins"], key=lambda c: c["amount"])
+    outpoints = [c["outpoint"] for c in coins]
+    tip_height = bitcoind.rpc.getblockcount()
+
+    # The timelock of the first coin expires in 4 blocks (we use a csv of 10 in the fixture),
+    # in 10 blocks for the second one.
+    res = lianad.rpc.getexpiryreport(3)
+    assert res["avg_block_interval_secs"] > 0
+    assert res["recoverable"] == res["expiring"] == []
+    assert [e["outpoint"] for e in res["safe"]] == outpoints
+    assert all(e["timelock"] == {"relative": 10} for e in res["safe"])
+    res = lianad.rpc.getexpiryreport(4)
+    assert [e["outpoint"] for e in res["expiring"]] == outpoints[:1]
+    entry = res["expiring"][0]
+    assert entry["unlock_height"] == coins[0]["block_height"] + 10 == tip_height + 4
+    assert entry["unlock_time"] is not None
+    assert [e["outpoint"] for e in res["safe"]] == outpoints[1:]
+
+    # Once the recovery path of the first coin is available, it's reported as recoverable.
+    bitcoind.generate_block(3)
+    wait_for(
+        lambda: lianad.rpc.getinfo()["block_height"] == bitcoind.rpc.getblockcount()
+    )
+    res = lianad.rpc.getexpiryreport(7)
+    assert [e["outpoint"] for e in res["recoverable"]] == outpoints[:1]
+    assert [e["outpoint"] for e in res["expiring"]] == outpoints[1:]
+    assert res["safe"] == []
+
+
+def test_labels(lianad, bitcoind):
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    assert coin["label"] is None
+
+    # Label the coin, the address and the transaction which created it.
+    outpoint = coin["outpoint"]
+    lianad.rpc.updatelabels(
+        {outpoint: "invoice 42", addr: "customer A", txid: "payment from A"}
+    )
+    assert lianad.rpc.listcoins()["coins"][0]["label"] == "invoice 42"
+    res = lianad.rpc.listtransactions([txid])["transactions"]
+    assert res[0]["label"] == "payment from A"
+    assert lianad.rpc.getlabels([outpoint, addr, txid])["labels"] == {
+        outpoint: "invoice 42",
+        addr: "customer A",
+        txid: "payment from A",
+    }
+
+    # Spend transactions can be labeled too.
+    destinations = {bitcoind.rpc.getnewaddress(): 200_000}
+    spend_psbt = lianad.rpc.createspend(destinations, [outpoint], 2)["psbt"]
+    lianad.rpc.updatespend(spend_psbt)
+    spend_txid = PSBT.from_base64(spend_psbt).tx.txid().hex()
+    lianad.rpc.updatelabels({spend_txid: "supplier payment"})
+    assert lianad.rpc.listspendtxs()["spend_txs"][0]["label"] == "supplier payment"
+
+    # Labels can be removed, exported and imported back.
+    export = lianad.rpc.exportlabels()["labels"]
+    assert len(export.splitlines()) == 4
+    lianad.rpc.updatelabels({outpoint: None, addr: None, txid: None, spend_txid: None})
+    assert lianad.rpc.getlabels([outpoint, addr, txid, spend_txid])["labels"] == {}
+    res = lianad.rpc.importlabels(export)
+    assert res == {"imported": 4, "skipped": 0}
+    assert lianad.rpc.listcoins()["coins"][0]["label"] == "invoice 42"
+    with pytest.raises(RpcError, match="Invalid BIP-329 label record at line 1"):
+        lianad.rpc.importlabels('{"type": "tx", "ref": "aa", "label": "a"}')
+
+
+def test_message_signing(lianad, bitcoind):
+    addr = lianad.rpc.getnewaddress()["address"]
+    message = "I control this address."
+
+    # Sign the message through the usual PSBT flow, and get the signature.
+    res = lianad.rpc.createmessagepsbt(addr, message)
+    psbt = lianad.signer.sign_psbt(PSBT.from_base64(res["psbt"]))
+    res = lianad.rpc.finalizemessagepsbt(addr, message, psbt.to_base64())
+    signature = res["signature"]
+
+    # It's only valid for this message and address.
+    assert lianad.rpc.verifymessage(addr, message, signature)["valid"]
+    assert not lianad.rpc.verifymessage(addr, "Another message.", signature)["valid"]
+    other_addr = lianad.rpc.getnewaddress()["address"]
+    assert not lianad.rpc.verifymessage(other_addr, message, signature)["valid"]
+
+    # An unsigned PSBT can't be finalized.
+    res = lianad.rpc.createmessagepsbt(addr, message)
+    with pytest.raises(RpcError, match="Invalid message signature"):
+        lianad.rpc.finalizemessagepsbt(addr, message, res["psbt"])
+
+    # We can't sign for an address which isn't ours.
+    with pytest.raises(RpcError, match="is not one of ours"):
+        lianad.rpc.createmessagepsbt(bitcoind.rpc.getnewaddress(), message)
+    with pytest.raises(RpcError, match="Invalid message signature"):
+        lianad.rpc.verifymessage(addr, message, "not a signature")
+
+
+def test_reserves_proof(lianad, bitcoind):
+    """Test creating and verifying a proof of reserves."""
+    message = "Reserves as of today"
+    with pytest.raises(RpcError, match="No confirmed unspent coin."):
+        lianad.rpc.createreservesproof(message)
+
+    # Receive two coins.
+    for amount in (0.1, 0.2):
+        txid = bitcoind.rpc.sendtoaddress(lianad.rpc.getnewaddress()["address"], amount)
+        bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(
+        lambda: len(
+            [c for c in lianad.rpc.listcoins()["coins"] if c["block_height"] is not None]
+        )
+        == 2
+    )
+    coins = lianad.rpc.listcoins()["coins"]
+
+    # Prove all of them.
+    res = lianad.rpc.createreservesproof(message)
+    proof = lianad.signer.sign_psbt(PSBT.from_base64(res["psbt"])).to_base64()
+    res = lianad.rpc.verifyreservesproof(message, proof)
+    assert res["amount"] == 30_000_000
+    assert sorted(res["coins"]) == sorted(c["outpoint"] for c in coins)
+    assert res["block_height"] == bitcoind.rpc.getblockcount()
+
+    # The proof is only valid for this message.
+    with pytest.raises(RpcError, match="doesn't commit to this message"):
+        lianad.rpc.verifyreservesproof("Reserves as of yesterday", proof)
+
+    # Prove a single coin, then spend it. The proof isn't valid anymore.
+    outpoint = coins[0]["outpoint"]
+    res = lianad.rpc.createreservesproof(message, [outpoint])
+    proof = lianad.signer.sign_psbt(PSBT.from_base64(res["psbt"])).to_base64()
+    assert lianad.rpc.verifyreservesproof(message, proof)["coins"] == [outpoint]
+    destinations = {bitcoind.rpc.getnewaddress(): 50_000}
+    res = lianad.rpc.createspend(destinations, [outpoint], 2)
+    spend_psbt = lianad.signer.sign_psbt(PSBT.from_base64(res["psbt"]))
+    lianad.rpc.updatespend(spend_psbt.to_base64())
+    spend_txid = spend_psbt.tx.txid().hex()
+    lianad.rpc.broadcastspend(spend_txid)
+    bitcoind.generate_block(1, wait_for_mempool=spend_txid)
+    with pytest.raises(RpcError, match="is not unspent"):
+        lianad.rpc.verifyreservesproof(message, proof)
@@ -1,6 +1,8 @@
+import pytest
+
 from fixtures import *
 from test_framework.serializations import PSBT
-from test_framework.utils import wait_for, COIN
+from test_framework.utils import wait_for, COIN, RpcError, sign_and_broadcast
 
 
 def test_spend_change(lianad, bitcoind):
@@ -170,3 +172,128 @@ def test_coin_marked_spent(lianad, bitcoind):
         return True
 
     wait_for(lambda: all(is_spent(c) for c in deposited_coins()))
+
+
+def test_bump_fee(lianad, bitcoind):
+    """We can replace a pending Spend transaction by one paying a higher feerate."""
+    # Receive a coin and create a transaction spending it, with a change output.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    outpoints = [c["outpoint"] for c in lianad.rpc.listcoins()["coins"]]
+    destinations = {
+        bitcoind.rpc.getnewaddress(): 100_000,
+    }
+    res = lianad.rpc.createspend(destinations, outpoints, 1)
+    spend_psbt = PSBT.from_base64(res["psbt"])
+    spend_txid = spend_psbt.tx.txid().hex()
+
+    # It can't be bumped until it's broadcast.
+    signed_psbt = lianad.signer.sign_psbt(spend_psbt)
+    lianad.rpc.updatespend(signed_psbt.to_base64())
+    with pytest.raises(RpcError, match="can't be replaced"):
+        lianad.rpc.bumpfee(spend_txid, 10)
+    lianad.rpc.broadcastspend(spend_txid)
+    wait_for(lambda: lianad.rpc.listcoins()["coins"][0]["spend_info"] is not None)
+
+    # The replacement must pay a higher feerate. It spends the same coin and reduces
+    # the change output.
+    with pytest.raises(RpcError, match="must be higher than the current one"):
+        lianad.rpc.bumpfee(spend_txid, 1)
+    res = lianad.rpc.bumpfee(spend_txid, 10)
+    bump_psbt = PSBT.from_base64(res["psbt"])
+    assert len(bump_psbt.tx.vin) == 1
+    assert (
+        bump_psbt.tx.vin[0].prevout.serialize()
+        == spend_psbt.tx.vin[0].prevout.serialize()
+    )
+    assert len(bump_psbt.tx.vout) == 2
+    assert sum(o.nValue for o in bump_psbt.tx.vout) < sum(
+        o.nValue for o in spend_psbt.tx.vout
+    )
+
+    # The replaced transaction can't be broadcast anymore. The replacement can.
+    with pytest.raises(RpcError, match="was replaced by"):
+        lianad.rpc.broadcastspend(spend_txid)
+    signed_psbt = lianad.signer.sign_psbt(bump_psbt)
+    lianad.rpc.updatespend(signed_psbt.to_base64())
+    bump_txid = signed_psbt.tx.txid().hex()
+    lianad.rpc.broadcastspend(bump_txid)
+    bitcoind.generate_block(1, wait_for_mempool=bump_txid)
+    wait_for(
+        lambda: any(
+            c["spend_info"] is not None
+            and c["spend_info"]["txid"] == bump_txid
+            and c["spend_info"]["height"] is not None
+            for c in lianad.rpc.listcoins()["coins"]
+        )
+    )
+
+
+def test_create_cpfp(lianad, bitcoind):
+    """We can accelerate an incoming payment made at a low feerate."""
+    # Receive a coin in a transaction paying 1sat/vb.
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(
+        addr, 0.01, "", "", False, True, None, "unset", None, 1
+    )
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+    coin = lianad.rpc.listcoins()["coins"][0]
+    assert coin["block_height"] is None
+
+    # Create a child transaction for the package to reach 50sat/vb.
+    res = lianad.rpc.createcpfp([coin["outpoint"]], 50)
+    cpfp_psbt = PSBT.from_base64(res["psbt"])
+    assert len(cpfp_psbt.tx.vin) == 1
+    assert len(cpfp_psbt.tx.vout) == 1
+    cpfp_txid = sign_and_broadcast(lianad, bitcoind, cpfp_psbt)
+    entry = bitcoind.rpc.getmempoolentry(cpfp_txid)
+    assert entry["fees"]["ancestor"] * COIN / entry["ancestorsize"] >= 50
+
+    # Once confirmed, the change output of the child can't be accelerated.
+    bitcoind.generate_block(1, wait_for_mempool=[txid, cpfp_txid])
+    wait_for(
+        lambda: all(
+            c["block_height"] is not None for c in lianad.rpc.listcoins()["coins"]
+        )
+    )
+    change = next(
+        c for c in lianad.rpc.listcoins()["coins"] if c["outpoint"][:64] == cpfp_txid
+    )
+    with pytest.raises(RpcError, match="is already confirmed"):
+        lianad.rpc.createcpfp([change["outpoint"]], 50)
+
+
+def test_spend_batch(lianad, bitcoind):
+    """We can pay the same address more than once in a transaction."""
+    addr = lianad.rpc.getnewaddress()["address"]
+    txid = bitcoind.rpc.sendtoaddress(addr, 0.01)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 1)
+
+    # The destinations can be given as a list of payments, which may repeat an address.
+    dest_addr = bitcoind.rpc.getnewaddress()
+    destinations = [
+        {"address": dest_addr, "amount": 100_000},
+        {"address": dest_addr, "amount": 200_000},
+        {"address": bitcoind.rpc.getnewaddress(), "amount": 300_000},
+    ]
+    res = lianad.rpc.createspend(destinations, [], 2)
+    spend_psbt = PSBT.from_base64(res["psbt"])
+    values = sorted(o.nValue for o in spend_psbt.tx.vout)
+    assert [v for v in values if v in (100_000, 200_000, 300_000)] == [
+        100_000,
+        200_000,
+        300_000,
+    ]
+    assert len(spend_psbt.tx.vout) == 4
+
+    # It's signed and broadcast like any other Spend.
+    txid = sign_and_broadcast(lianad, bitcoind, spend_psbt)
+    bitcoind.generate_block(1, wait_for_mempool=txid)
+    wait_for(lambda: len(lianad.rpc.listcoins()["coins"]) == 2)
+
+    # A malformed payment is refused.
+    with pytest.raises(RpcError, match="Invalid 'destinations' parameter."):
+        lianad.rpc.createspend([{"address": dest_addr}], [], 2)